@@ -1,6 +1,17 @@
+import { PgCodec } from "./codec";
 import { PgCommon } from "./common";
 import { PgExplorer, TupleFiles } from "./explorer";
-import { PgServer } from "./server";
+import { LockFormat, PgServer } from "./server";
+
+/** Bundled modules (package name to its module) */
+type Modules = {
+  [name: string]: {
+    /** Module name (the export name of the module in its entry file) */
+    name: string;
+    /** Path of the entry file of the module in the bundle files */
+    entry: string;
+  };
+};
 
 // TODO: Use explorer state to allow in temp projects
 const fs = PgExplorer.fs;
@@ -9,14 +20,32 @@ export class PgJsPackage {
   /** Install packages as a bundle. */
   static async install() {
     const manifest = await this._getManifest();
-    const lock = await this._getLock();
-    const result = await PgServer.bundle({ manifest, lock });
+    const { lock, lockFormat } = await this._getLock();
+    const response = await PgServer.bundle({ manifest, lock, lockFormat });
+    const result = await this._unpackArchive(
+      response.archive,
+      response.lockFormat
+    );
 
     // Save manifest
     await fs.writeFile(this._PATHS.MANIFEST_FILE, result.manifest);
 
-    // Save lock file
-    await fs.writeFile(this._PATHS.LOCK_FILE, result.lock);
+    // Save lock file, and remove the lock files of the other formats since they
+    // are outdated
+    for (const [format, path] of Object.entries(this._PATHS.LOCK_FILES)) {
+      if (format === response.lockFormat) {
+        await fs.writeFile(path, result.lock);
+      } else if (await fs.exists(path)) {
+        await fs.removeFile(path);
+      }
+    }
+
+    // Save module mapping
+    await fs.writeFile(
+      this._getInternalPath(this._PATHS.MODULES_FILE),
+      JSON.stringify(result.modules),
+      { createParents: true }
+    );
 
     // Save bundle: each chunk individually to support lazy-loading
     for (const [path, content] of result.bundle) {
@@ -31,6 +60,16 @@ export class PgJsPackage {
         createParents: true,
       });
     }
+
+    // Save type dependencies
+    for (const [name, dependencies] of Object.entries(result.dependencies)) {
+      const path = PgCommon.joinPaths(name, this._PATHS.DEPENDENCIES_FILE);
+      await fs.writeFile(
+        this._getInternalPath(path),
+        JSON.stringify(dependencies),
+        { createParents: true }
+      );
+    }
   }
 
   /**
@@ -43,10 +82,14 @@ export class PgJsPackage {
    */
   static async import(name: string) {
     // TODO: Cache
-    const mod = await this.importChunk(
-      PgCommon.joinPaths(name, this._PATHS.BUNDLE_FILE)
+    const modules = await fs.readToJSON<Modules>(
+      this._getInternalPath(this._PATHS.MODULES_FILE)
     );
-    const pkg = mod[this._toModuleName(name)];
+    const pkgModule = modules[name];
+    if (!pkgModule) throw new Error(`Package is not installed: ${name}`);
+
+    const mod = await this.importChunk(pkgModule.entry);
+    const pkg = mod[pkgModule.name];
     if (!pkg) throw new Error(`Failed to import: ${name}`);
     return pkg;
   }
@@ -93,9 +136,13 @@ export class PgJsPackage {
       "js-packages"
     ),
     MANIFEST_FILE: "package.json",
-    // TODO: Support `npm` and `pnpm`
-    LOCK_FILE: "yarn.lock",
-    BUNDLE_FILE: "bundle.js",
+    // TODO: Support `npm`
+    LOCK_FILES: {
+      yarn: "yarn.lock",
+      pnpm: "pnpm-lock.yaml",
+    } as Record<LockFormat, string>,
+    BUNDLE_FILE: "bundle.json",
+    MODULES_FILE: "modules.json",
     TYPES_FILE: "types.json",
     DEPENDENCIES_FILE: "dependencies.json",
   };
@@ -105,6 +152,56 @@ export class PgJsPackage {
     return PgCommon.joinPaths(this._PATHS.INTERNAL_ROOT_DIR, relativePath);
   }
 
+  /**
+   * Unpack the bundle archive (`.tar.gz`) of the server.
+   *
+   * @param archive compressed archive bytes
+   * @param lockFormat format of the lock file in the archive
+   * @returns the bundle files, module mapping, type declarations, manifest and
+   * lock file
+   */
+  private static async _unpackArchive(
+    archive: Uint8Array,
+    lockFormat: LockFormat
+  ) {
+    const { ungzip } = await import("pako");
+    const tar = ungzip(archive);
+
+    // Each entry is a 512-byte header followed by the content padded to 512
+    // bytes, and the archive ends with empty blocks
+    const BLOCK_SIZE = 512;
+    const files: Record<string, string> = {};
+    for (let i = 0; i + BLOCK_SIZE <= tar.length; ) {
+      const header = tar.subarray(i, i + BLOCK_SIZE);
+      const readField = (start: number, end: number) =>
+        PgCodec.decodeText(header.subarray(start, end)).split("\0")[0];
+      const name = readField(0, 100);
+      if (!name) break;
+
+      const size = parseInt(readField(124, 136), 8);
+      const start = i + BLOCK_SIZE;
+      files[name] = PgCodec.decodeText(tar.subarray(start, start + size));
+      i = start + Math.ceil(size / BLOCK_SIZE) * BLOCK_SIZE;
+    }
+
+    const get = (name: string) => {
+      const file = files[name];
+      if (file === undefined) throw new Error(`Missing bundle file: ${name}`);
+      return file;
+    };
+    return {
+      bundle: JSON.parse(get(this._PATHS.BUNDLE_FILE)) as TupleFiles,
+      modules: JSON.parse(get(this._PATHS.MODULES_FILE)) as Modules,
+      types: JSON.parse(get(this._PATHS.TYPES_FILE)) as TupleFiles,
+      dependencies: JSON.parse(get(this._PATHS.DEPENDENCIES_FILE)) as Record<
+        string,
+        string[]
+      >,
+      manifest: get(this._PATHS.MANIFEST_FILE),
+      lock: get(this._PATHS.LOCK_FILES[lockFormat]),
+    };
+  }
+
   /** Get the manifest file content (`package.json`). */
   private static async _getManifest() {
     try {
@@ -117,31 +214,23 @@ export class PgJsPackage {
     }
   }
 
-  /** Get the lock file content. */
+  /** Get the lock file content and its format. */
   private static async _getLock() {
-    try {
-      return await fs.readToString(this._PATHS.LOCK_FILE);
-    } catch {
-      // TODO: Make this based on framework and version
-      return await PgCommon.fetchText("/frameworks/" + this._PATHS.LOCK_FILE);
+    const lockFiles = Object.entries(this._PATHS.LOCK_FILES) as Array<
+      [LockFormat, string]
+    >;
+    for (const [lockFormat, path] of lockFiles) {
+      if (await fs.exists(path)) {
+        return { lock: await fs.readToString(path), lockFormat };
+      }
     }
-  }
 
-  /**
-   * Convert the given package name to a module name.
-   *
-   * NOTE: This must be kept in sync with the server.
-   *
-   * @param pkgName package name
-   * @returns the module name
-   */
-  private static _toModuleName(pkgName: string) {
-    return pkgName
-      .replace("@", "")
-      .replace("/", "")
-      .replaceAll("-", "")
-      .replaceAll("_", "")
-      .replaceAll(".", "");
+    // TODO: Make this based on framework and version
+    const lockFormat: LockFormat = "yarn";
+    const lock = await PgCommon.fetchText(
+      "/frameworks/" + this._PATHS.LOCK_FILES[lockFormat]
+    );
+    return { lock, lockFormat };
   }
 }
 
@@ -40,11 +40,18 @@ export class PgPlaynet {
     this._SAVE_INTERVAL_ID = PgCommon.setIntervalOnFocus(() => {
       this._save();
     }, this._SAVE_INTERVAL_MS);
+
+    // Seal blocks periodically for slots to progress without transactions.
+    // This is not paused on blur because the chain keeps progressing
+    // regardless of the focus, e.g. for pending confirmations.
+    this._TICK_INTERVAL_ID = setInterval(() => {
+      this._playnet?.tick();
+    }, this._TICK_INTERVAL_MS);
   }
 
   /**
    * Destroy the Playnet instance by:
-   * 1. Clear save interval.
+   * 1. Clear save and tick intervals.
    * 2. Save data.
    * 3. Set `connection`and `fetch` to default.
    * 4. Free WASM memory.
@@ -54,10 +61,13 @@ export class PgPlaynet {
   static async destroy() {
     if (!this._playnet) return;
 
-    // Clear save interval
+    // Clear save and tick intervals
     if (this._SAVE_INTERVAL_ID) {
       clearInterval(this._SAVE_INTERVAL_ID);
     }
+    if (this._TICK_INTERVAL_ID) {
+      clearInterval(this._TICK_INTERVAL_ID);
+    }
 
     // Save Playnet instance data
     await this._save();
@@ -99,6 +109,12 @@ export class PgPlaynet {
   /** Data saving interval that must be cleared while destroying the Playnet instance */
   private static _SAVE_INTERVAL_ID: NodeJS.Timer | null;
 
+  /** Check whether the current block is due at this interval */
+  private static _TICK_INTERVAL_MS = 400;
+
+  /** Tick interval that must be cleared while destroying the Playnet instance */
+  private static _TICK_INTERVAL_ID: NodeJS.Timer | null;
+
   /** Save the current playnet data */
   private static async _save() {
     if (!this._playnet) return;
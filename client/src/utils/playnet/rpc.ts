@@ -1,19 +1,12 @@
 import type { PgRpc, TransactionStatus } from "@solana-playground/playnet";
 
-import { PgSerde } from "./serde";
 import { PgPlaynetUtils } from "./utils";
-import { PgCodec } from "../codec";
 import { PgCommon } from "../common";
 import { PgConnection } from "../connection";
 import { PgSettings } from "../settings";
 import { PgWeb3 } from "../web3";
 import { Endpoint } from "../../constants";
-import type {
-  OverridableConnection,
-  RpcRequest,
-  RpcResponse,
-  RpcResponseWithContext,
-} from "./types";
+import type { OverridableConnection } from "./types";
 
 export class PgPlaynetRpc {
   /**
@@ -146,24 +139,21 @@ export class PgPlaynetRpc {
   private static _getNewFetch(rpc: PgRpc) {
     return async (...args: Parameters<Window["fetch"]>) => {
       // Get whether the request url is playnet
-      let parsedRequest: RpcRequest | null = null;
+      let requestBody: string | null = null;
       if (typeof args[0] === "string") {
         const url = args[0];
         if (this.isUrlPlaynet(url)) {
-          const requestBody = args[1]?.body;
-          if (requestBody) {
-            parsedRequest = JSON.parse(requestBody.toString());
-          }
+          requestBody = args[1]?.body?.toString() ?? null;
         }
       } else if (typeof args[0] === "object") {
         const request = args[0] as Request;
         if (this.isUrlPlaynet(request.url)) {
-          parsedRequest = await request.json();
+          requestBody = await request.text();
         }
       }
 
       // Playnet response
-      if (parsedRequest) return this._getPlaynetResponse(rpc, parsedRequest);
+      if (requestBody) return this._getPlaynetResponse(rpc, requestBody);
 
       // Response for every URL other than Playnet endpoint
       return await defaultFetch(...args);
@@ -176,338 +166,13 @@ export class PgPlaynetRpc {
    * This implementation allows Playnet to be used with `Connection` like a
    * normal cluster.
    */
-  private static _getPlaynetResponse(rpc: PgRpc, request: RpcRequest) {
-    const slot = PgCommon.bigintToInt(rpc.getSlot());
-    const context = {
-      apiVersion: "1.15.0",
-      slot,
-    };
-
-    switch (request.method) {
-      case "getAccountInfo": {
-        const [address] = request.params;
-        const account = rpc.getAccountInfo(address);
-        const lamports = PgCommon.bigintToInt(account.lamports);
-
-        const encoding = "base64";
-        return this._createRpcResponse<"getAccountInfo">(request, context, {
-          // @ts-ignore
-          value:
-            lamports === 0
-              ? null
-              : {
-                  data: [
-                    PgCodec.encodeBinary(account.data, encoding),
-                    encoding,
-                  ],
-                  executable: account.executable,
-                  lamports,
-                  owner: new PgWeb3.PublicKey(account.owner.toBytes()),
-                  rentEpoch: PgCommon.bigintToInt(account.rentEpoch),
-                },
-        });
-      }
-
-      case "getBalance": {
-        const [address] = request.params;
-        const account = rpc.getAccountInfo(address);
-        return this._createRpcResponse<"getBalance">(request, context, {
-          value: PgCommon.bigintToInt(account.lamports),
-        });
-      }
-
-      case "getBlockHeight": {
-        return this._createRpcResponse<"getBlockHeight">(request, context, {
-          result: PgCommon.bigintToInt(rpc.getBlockHeight()),
-        });
-      }
-
-      case "getFeeForMessage": {
-        const [msgBase64] = request.params;
-        const rustMsgBytes = PgSerde.serializeMsg(msgBase64);
-
-        const fee = PgCommon.bigintToInt(rpc.getFeeForMessage(rustMsgBytes));
-
-        return this._createRpcResponse<"getFeeForMessage">(request, context, {
-          result:
-            fee === undefined
-              ? undefined
-              : {
-                  context: { slot },
-                  value: fee,
-                },
-        });
-      }
-
-      case "getGenesisHash": {
-        return this._createRpcResponse<"getGenesisHash">(request, context, {
-          result: rpc.getGenesisHash(),
-        });
-      }
-
-      case "getLatestBlockhash": {
-        const blockhashInfo = rpc.getLatestBlockhash();
-        return this._createRpcResponse<"getLatestBlockhash">(request, context, {
-          value: {
-            blockhash: blockhashInfo.blockhash(),
-            lastValidBlockHeight: PgCommon.bigintToInt(
-              blockhashInfo.lastValidBlockHeight()
-            ),
-          },
-        });
-      }
-
-      case "getMinimumBalanceForRentExemption": {
-        const [dataLen] = request.params;
-        return this._createRpcResponse<"getMinimumBalanceForRentExemption">(
-          request,
-          context,
-          {
-            result: PgCommon.bigintToInt(
-              rpc.getMinimumBalanceForRentExemption(dataLen)
-            ),
-          }
-        );
-      }
-
-      case "getRecentBlockhash": {
-        return this._createRpcResponse<"getRecentBlockhash">(request, context, {
-          value: {
-            blockhash: rpc.getLatestBlockhash().blockhash(),
-            feeCalculator: { lamportsPerSignature: 5000 },
-          },
-        });
-      }
-
-      // TODO: Actually support priority fees instead of a dummy impl
-      case "getRecentPrioritizationFees": {
-        return this._createRpcResponse<"getRecentPrioritizationFees">(
-          request,
-          context,
-          {
-            result: [
-              {
-                prioritizationFee: 0,
-                slot: PgCommon.bigintToInt(rpc.getSlot()),
-              },
-            ],
-          }
-        );
-      }
-
-      case "getSignatureStatuses": {
-        const [signatures] = request.params;
-
-        const statusesResult = rpc.getSignatureStatuses(signatures);
-        const statuses: (TransactionStatus | undefined)[] =
-          statusesResult.statuses();
-
-        return this._createRpcResponse<"getSignatureStatuses">(
-          request,
-          context,
-          {
-            result: {
-              context,
-              value: statuses.map((status) =>
-                status
-                  ? {
-                      confirmationStatus: (() => {
-                        switch (status.confirmationStatus) {
-                          case 0:
-                            return "processed";
-                          case 1:
-                            return "confirmed";
-                          case 2:
-                            return "finalized";
-                          default:
-                            return "finalized";
-                        }
-                      })() as PgWeb3.TransactionConfirmationStatus,
-                      confirmations: status.confirmations!,
-                      err: status.error() ?? null,
-                      slot: PgCommon.bigintToInt(status.slot),
-                    }
-                  : null
-              ),
-            },
-          }
-        );
-      }
-
-      case "getSlot": {
-        return this._createRpcResponse<"getSlot">(request, context, {
-          result: slot,
-        });
-      }
-
-      case "getTransaction": {
-        const [signature, options] = request.params;
-        const getTxResult = rpc.getTransaction(signature);
-
-        const meta = getTxResult.meta();
-
-        // web3.js expects tx object but solana-cli expects base64 encoded tx
-        // string. We get base64 tx string from `playnet` and convert it to
-        // `VersionedTransaction`
-        let tx:
-          | [string, string]
-          | PgWeb3.VersionedTransactionResponse["transaction"] = [
-          getTxResult.transaction(),
-          "base64",
-        ];
-        if (!options?.encoding) {
-          const versionedTx = PgWeb3.VersionedTransaction.deserialize(
-            PgCodec.decodeBinary(tx[0], "base64")
-          );
-          const signatures = versionedTx.signatures.map((signatureBytes) => {
-            return PgCodec.encodeBinary(signatureBytes, "base58");
-          });
-          tx = { message: versionedTx.message, signatures };
-        }
-
-        const convertBalances = (bigintBalances: BigUint64Array) => {
-          const balances = [];
-          for (const i in bigintBalances) {
-            balances.push(PgCommon.bigintToInt(bigintBalances[i]));
-          }
-
-          return balances;
-        };
-
-        return this._createRpcResponse<"getTransaction">(request, context, {
-          // @ts-ignore
-          result: getTxResult.exists()
-            ? {
-                slot,
-                transaction: tx,
-                meta: {
-                  fee: PgCommon.bigintToInt(meta.fee()),
-                  innerInstructions: meta.innerInstructions(),
-                  preBalances: convertBalances(meta.preBalances()),
-                  postBalances: convertBalances(meta.postBalances()),
-                  logMessages: meta.logs(),
-                  preTokenBalances: meta.preTokenBalances(),
-                  postTokenBalances: meta.postTokenBalances(),
-                  err: meta.err() ?? null,
-                  loadedAddresses: meta.loadedAddresses(),
-                  computeUnitsConsumed: PgCommon.bigintToInt(
-                    meta.computeUnitsConsumed()
-                  ),
-                  rewards: [],
-                  status: meta.err() ? { Err: meta.err() } : { Ok: null },
-                },
-                blockTime: PgCommon.bigintToInt(getTxResult.blockTime()),
-                version: getTxResult.version() ?? "legacy",
-              }
-            : null,
-        });
-      }
-
-      case "requestAirdrop": {
-        const [address, lamports] = request.params;
-        const airdropResult = rpc.requestAirdrop(address, BigInt(lamports));
-        this._handleError(airdropResult);
-
-        return this._createRpcResponse<"requestAirdrop">(request, context, {
-          result: airdropResult.txHash(),
-        });
-      }
-
-      case "sendTransaction": {
-        const [txBase64] = request.params;
-        const rustTxBytes = PgSerde.serializeTx(txBase64);
-
-        const txResult = rpc.sendTransaction(rustTxBytes);
-        this._handleError(txResult);
-
-        return this._createRpcResponse<"sendTransaction">(request, context, {
-          result: txResult.txHash(),
-        });
-      }
-
-      case "simulateTransaction": {
-        const [txBase64] = request.params;
-        const rustTxBytes = PgSerde.serializeTx(txBase64);
-
-        const simulationResult = rpc.simulateTransaction(rustTxBytes);
-        const returnData = simulationResult.returnData();
-
-        const encoding = "base64";
-        return this._createRpcResponse<"simulateTransaction">(
-          request,
-          context,
-          {
-            result: {
-              context,
-              value: {
-                err: simulationResult.error() ?? null,
-                logs: simulationResult.logs(),
-                unitsConsumed: PgCommon.bigintToInt(
-                  simulationResult.unitsConsumed()
-                ),
-                returnData: returnData
-                  ? {
-                      programId: returnData.programId.toString(),
-                      data: [
-                        PgCodec.encodeBinary(returnData.data, encoding),
-                        encoding,
-                      ],
-                    }
-                  : null,
-                accounts: null,
-              },
-            },
-          }
-        );
-      }
-
-      default: {
-        throw new Error(`Method: '${request.method}' is not yet implemented.`);
-      }
-    }
-  }
-
-  /** Create `web3.js` compatible responses with type safety. */
-  private static _createRpcResponse<K extends keyof PgWeb3.Connection>(
-    request: RpcRequest,
-    context: RpcResponseWithContext<K>["result"]["context"],
-    data: {
-      result?: RpcResponse<K>["result"];
-      value?: RpcResponseWithContext<K>["result"]["value"];
-    }
-  ) {
-    const responseBody: RpcResponse<K> | RpcResponseWithContext<K> = {
-      id: request.id,
-      jsonrpc: request.jsonrpc,
-      result:
-        data.result !== undefined
-          ? data.result!
-          : {
-              context,
-              value: data.value!,
-            },
-    };
+  private static _getPlaynetResponse(rpc: PgRpc, requestBody: string) {
+    const responseBody = rpc.handleRequest(requestBody);
 
     // WASM URL parsing fails if the `Response.url` is empty
-    return Object.defineProperty(
-      new Response(JSON.stringify(responseBody)),
-      "url",
-      { value: Endpoint.PLAYNET }
-    );
-  }
-
-  /**
-   * Handle WASM errors.
-   *
-   * @param result WASM result object that has `error()` method
-   */
-  private static _handleError<R extends { error: () => string | undefined }>(
-    result: R
-  ) {
-    const error = result.error();
-    if (error) {
-      throw new Error(error);
-    }
+    return Object.defineProperty(new Response(responseBody), "url", {
+      value: Endpoint.PLAYNET,
+    });
   }
 }
 
@@ -24,12 +24,17 @@ interface BuildRequest {
   }>;
 }
 
+/** Lock file format of a JS package manager */
+export type LockFormat = "yarn" | "pnpm";
+
 /** `/unstable/bundle` request */
 interface BundleRequest {
   /** Package manifest file (`package.json`) */
   manifest: string;
   /** Package lock file */
   lock?: Option<string>;
+  /** Format of the lock file, defaults to `yarn` */
+  lockFormat?: Option<LockFormat>;
 }
 
 /** `/new` request */
@@ -83,24 +88,17 @@ export class PgServer {
    * Bundle ESM.
    *
    * @param req bundle request
-   * @returns the bundle response
+   * @returns the compressed bundle archive (`.tar.gz`) and the format of its
+   * lock file
    */
   static async bundle(req: BundleRequest) {
-    interface BundleResponse {
-      /** Bundle files */
-      bundle: TupleFiles;
-      /** Type declaration files */
-      types: TupleFiles;
-      /** Manifest file */
-      manifest: string;
-      /** Lock file */
-      lock: string;
-    }
-
     const response = await this._send("/unstable/bundle", {
       post: { body: JSON.stringify(req) },
     });
-    return (await response.json()) as BundleResponse;
+    const lockFormat = (response.headers.get("X-Lock-Format") ??
+      "yarn") as LockFormat;
+    const arrayBuffer = await response.arrayBuffer();
+    return { archive: new Uint8Array(arrayBuffer), lockFormat };
   }
 
   /**
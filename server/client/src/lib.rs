@@ -0,0 +1,214 @@
+//! Client of the Solana Playground server.
+//!
+//! See `/openapi.json` of the server for the full API document.
+
+mod types;
+
+use reqwest::{header, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub use solpg_server_types::*;
+pub use types::*;
+
+/// Client result type
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Client error type
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Request couldn't be sent or the response couldn't be read
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// Server responded with an error
+    #[error("Server error ({status}): {message}")]
+    Server {
+        /// HTTP status code
+        status: StatusCode,
+        /// Error message
+        message: String,
+    },
+    /// Progress events ended before the bundle was completed
+    #[error("Progress events ended unexpectedly")]
+    UnexpectedEnd,
+}
+
+/// Solana Playground server client
+#[derive(Clone, Debug)]
+pub struct Client {
+    /// HTTP client
+    http: reqwest::Client,
+    /// Server URL
+    url: String,
+}
+
+impl Client {
+    /// Create a new client of the server at the given URL, e.g. `http://localhost:8080`.
+    pub fn new(url: impl ToString) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.to_string().trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Build the program.
+    pub async fn build(&self, req: &BuildRequest) -> Result<BuildResponse> {
+        let resp = self.http.post(self.url("/build")).json(req).send().await?;
+        json(resp).await
+    }
+
+    /// Get the program binary of a previous build.
+    pub async fn deploy(&self, uuid: &str) -> Result<Vec<u8>> {
+        let resp = self
+            .http
+            .get(self.url(&format!("/deploy/{uuid}")))
+            .send()
+            .await?;
+        let bytes = check(resp).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Get the available program build toolchains.
+    pub async fn toolchains(&self) -> Result<Vec<Toolchain>> {
+        let resp = self.http.get(self.url("/toolchains")).send().await?;
+        json(resp).await
+    }
+
+    /// Get the share from its id.
+    pub async fn share_get(&self, id: &str) -> Result<Value> {
+        let resp = self
+            .http
+            .get(self.url(&format!("/share/{id}")))
+            .send()
+            .await?;
+        json(resp).await
+    }
+
+    /// Create a new share and return its id.
+    pub async fn share_new(&self, req: &ShareNewRequest) -> Result<String> {
+        let resp = self.http.post(self.url("/new")).json(req).send().await?;
+        check(resp).await?.text().await.map_err(Into::into)
+    }
+
+    /// Bundle ESM packages.
+    ///
+    /// `None` is returned if `etag` matches the `ETag` of the bundle, i.e. the bundle has not
+    /// changed.
+    pub async fn bundle(
+        &self,
+        req: &BundleRequest,
+        etag: Option<&str>,
+    ) -> Result<Option<BundleResponse>> {
+        let mut builder = self.http.post(self.url("/unstable/bundle")).json(req);
+        if let Some(etag) = etag {
+            builder = builder.header(header::IF_NONE_MATCH, etag);
+        }
+
+        let resp = builder.send().await?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        let resp = check(resp).await?;
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToOwned::to_owned);
+        let lock_format = resp
+            .headers()
+            .get(LockFormat::HEADER)
+            .and_then(|lock_format| lock_format.to_str().ok())
+            .and_then(|lock_format| lock_format.parse().ok());
+        let archive = resp.bytes().await?.to_vec();
+        Ok(Some(BundleResponse {
+            archive,
+            etag,
+            lock_format,
+        }))
+    }
+
+    /// Bundle ESM packages and call `on_event` with the progress events as they happen.
+    ///
+    /// Returns the `ETag` of the bundle. The bundle is cached by the server, meaning
+    /// [`Client::bundle`] returns it without bundling again.
+    pub async fn bundle_progress(
+        &self,
+        req: &BundleRequest,
+        mut on_event: impl FnMut(BundleEvent),
+    ) -> Result<String> {
+        let resp = self
+            .http
+            .post(self.url("/unstable/bundle"))
+            .header(header::ACCEPT, "text/event-stream")
+            .json(req)
+            .send()
+            .await?;
+        let mut resp = check(resp).await?;
+
+        // Events are separated by an empty line
+        let mut buf = vec![];
+        while let Some(chunk) = resp.chunk().await? {
+            buf.extend_from_slice(&chunk);
+            while let Some(end) = buf.windows(2).position(|w| w == b"\n\n") {
+                let raw = String::from_utf8_lossy(&buf[..end]).into_owned();
+                buf.drain(..end + 2);
+
+                // Multi-line data is sent as multiple `data` fields
+                let mut event = BundleEvent::default();
+                let mut data = vec![];
+                for line in raw.lines() {
+                    if let Some(name) = line.strip_prefix("event:") {
+                        event.event = name.trim_start().to_owned();
+                    } else if let Some(line) = line.strip_prefix("data:") {
+                        data.push(line.strip_prefix(' ').unwrap_or(line));
+                    }
+                }
+                event.data = data.join("\n");
+                match event.event.as_str() {
+                    // Keep-alive comments don't have a name
+                    "" => {}
+                    "done" => return Ok(event.data),
+                    "error" => {
+                        return Err(Error::Server {
+                            status: StatusCode::INTERNAL_SERVER_ERROR,
+                            message: event.data,
+                        })
+                    }
+                    _ => on_event(event),
+                }
+            }
+        }
+
+        Err(Error::UnexpectedEnd)
+    }
+
+    /// Get the OpenAPI document of the server.
+    pub async fn openapi(&self) -> Result<Value> {
+        let resp = self.http.get(self.url("/openapi.json")).send().await?;
+        json(resp).await
+    }
+
+    /// Get the full URL of the given route path.
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.url)
+    }
+}
+
+/// Return an error if the response status is not successful.
+async fn check(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+
+    Err(Error::Server {
+        status,
+        message: resp.text().await?,
+    })
+}
+
+/// Deserialize the JSON response.
+async fn json<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T> {
+    check(resp).await?.json().await.map_err(Into::into)
+}
@@ -0,0 +1,21 @@
+use solpg_server_types::LockFormat;
+
+/// Bundle response
+#[derive(Clone, Debug)]
+pub struct BundleResponse {
+    /// Compressed archive (`.tar.gz`) of the bundle
+    pub archive: Vec<u8>,
+    /// `ETag` of the bundle, can be used to skip downloading unchanged bundles
+    pub etag: Option<String>,
+    /// Format of the lock file in the archive
+    pub lock_format: Option<LockFormat>,
+}
+
+/// Bundle progress event
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleEvent {
+    /// Event name: `started`, `stdout`, `stderr` or `exited`
+    pub event: String,
+    /// JSON data of the event
+    pub data: String,
+}
@@ -0,0 +1,48 @@
+import * as esbuild from "esbuild";
+import fs from "fs";
+
+// Generated by the bundle process (package name -> entry file)
+const entries = JSON.parse(fs.readFileSync("entries.json", "utf8"));
+
+// Same as `resolve.fallback: { <module>: false }` in `webpack.config.js`
+const emptyModules = ["fs", "path", "perf_hooks", "process", "zlib"];
+
+await esbuild.build({
+  entryPoints: Object.entries(entries).map(([name, entry]) => ({
+    in: entry,
+    out: `${name}/bundle`, // split each package to its own dir
+  })),
+  outdir: "dist",
+  bundle: true,
+  minify: true,
+  format: "esm",
+  platform: "browser",
+  target: "es2020",
+  // Code splitting is not enabled because `esbuild` chunks are imported statically, which doesn't
+  // work with the Playground chunk loader (see `PgImportChunkPlugin` for `webpack`)
+  splitting: false,
+  // Resolve Node polyfills
+  inject: ["./plugins/esbuild-polyfills.js"],
+  alias: {
+    // `mocha`
+    stream: "stream-browserify",
+
+    // `@metaplex-foundation/js` polyfills
+    crypto: "crypto-browserify",
+  },
+  plugins: [
+    {
+      name: "empty-modules",
+      setup(build) {
+        const filter = new RegExp(`^(${emptyModules.join("|")})$`);
+        build.onResolve({ filter }, (args) => ({
+          path: args.path,
+          namespace: "empty-module",
+        }));
+        build.onLoad({ filter: /.*/, namespace: "empty-module" }, () => ({
+          contents: "export default {}",
+        }));
+      },
+    },
+  ],
+});
@@ -0,0 +1,3 @@
+// Globals that are provided with `webpack.ProvidePlugin` in `webpack.config.js`
+export { Buffer } from "buffer";
+export { default as process } from "process/browser.js";
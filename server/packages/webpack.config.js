@@ -1,4 +1,5 @@
 import webpack from "webpack";
+import fs from "fs";
 import path from "path";
 import { createRequire } from "module";
 
@@ -9,9 +10,8 @@ const require = createRequire(import.meta.url);
 export default {
   mode: "production",
   target: ["web", "es2020"],
-  entry: {
-    /* <DYNAMIC_ENTRIES> */
-  },
+  // Generated by the bundle process (package name -> entry file)
+  entry: JSON.parse(fs.readFileSync("entries.json", "utf8")),
   output: {
     path: path.resolve("dist"), // already the default but keep for safety
     filename: "[name]/bundle.js", // split each package to a its own dir
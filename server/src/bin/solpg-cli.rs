@@ -0,0 +1,229 @@
+//! Headless client of the Playground server, e.g. to build programs in CI.
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use solpg_server::utils::Files;
+use solpg_server_client::{BuildFlags, BuildRequest, Client, ShareNewRequest, ToolchainSelector};
+
+/// Default server URL
+const DEFAULT_URL: &str = "https://api.solpg.io";
+
+/// Usage of the CLI
+const USAGE: &str = "\
+Usage: solpg-cli build <DIR> [--uuid <UUID>] [--out <DIR>] [--seeds-feature <BOOL>] \
+[--no-docs <BOOL>] [--safety-checks <BOOL>] [--platform-tools <VERSION>] [--anchor <VERSION>] \
+[--previous-idl <FILE>]
+       solpg-cli toolchains
+       solpg-cli share new <DIR>
+       solpg-cli share get <ID> [--out <DIR>]
+
+All commands accept `--url <URL>` (defaults to `PG_SERVER_URL` or `https://api.solpg.io`).";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let mut positionals = vec![];
+    let mut opts = HashMap::new();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(key) => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("Missing value for `--{key}`"))?;
+                opts.insert(key.to_owned(), value);
+            }
+            None => positionals.push(arg),
+        }
+    }
+
+    let url = match opts.remove("url") {
+        Some(url) => url,
+        None => env::var("PG_SERVER_URL").unwrap_or(DEFAULT_URL.to_owned()),
+    };
+    let client = Client::new(url);
+
+    match positionals.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["build", dir] => build(&client, Path::new(dir), &opts).await,
+        ["toolchains"] => {
+            for toolchain in client.toolchains().await? {
+                let default = if toolchain.default { " (default)" } else { "" };
+                println!(
+                    "platform-tools {}, Anchor {}{default}",
+                    toolchain.platform_tools, toolchain.anchor
+                );
+            }
+            Ok(())
+        }
+        ["share", "new", dir] => {
+            let files = read_files(Path::new(dir))?
+                .into_iter()
+                .map(|(path, content)| (path, json!({ "content": content })))
+                .collect::<Map<_, _>>();
+            let explorer = json!({ "files": files });
+            let id = client.share_new(&ShareNewRequest { explorer }).await?;
+            println!("{id}");
+            Ok(())
+        }
+        ["share", "get", id] => {
+            let share = client.share_get(id).await?;
+            let out_path = opts.get("out").map(PathBuf::from).unwrap_or(id.into());
+            write_share(&share, &out_path)
+        }
+        _ => Err(anyhow!(USAGE)),
+    }
+}
+
+/// Build the program in `dir` and save the program binary and the IDL to the output directory.
+///
+/// The output directory defaults to `<dir>/target/deploy`.
+async fn build(client: &Client, dir: &Path, opts: &HashMap<String, String>) -> Result<()> {
+    let get_flag = |key: &str| {
+        opts.get(key)
+            .map(|value| value.parse::<bool>())
+            .transpose()
+            .map_err(|e| anyhow!("Invalid `--{key}`: {e}"))
+    };
+
+    let previous_idl = opts
+        .get("previous-idl")
+        .map(|path| -> Result<Value> { Ok(serde_json::from_str(&fs::read_to_string(path)?)?) })
+        .transpose()
+        .map_err(|e| anyhow!("Invalid `--previous-idl`: {e}"))?;
+
+    // Only Rust files are allowed to be built
+    let mut files = read_files(dir)?;
+    files.retain(|(path, _)| path.ends_with(".rs"));
+    let resp = client
+        .build(&BuildRequest {
+            files: files.into_iter().collect(),
+            uuid: opts.get("uuid").cloned(),
+            flags: Some(BuildFlags {
+                seeds_feature: get_flag("seeds-feature")?,
+                no_docs: get_flag("no-docs")?,
+                safety_checks: get_flag("safety-checks")?,
+            }),
+            toolchain: Some(ToolchainSelector {
+                platform_tools: opts.get("platform-tools").cloned(),
+                anchor: opts.get("anchor").cloned(),
+            }),
+            previous_idl,
+        })
+        .await?;
+
+    // Build tools output diagnostics to `stderr` regardless of the compilation status
+    eprint!("{}", resp.stderr);
+    if resp.stderr.contains("error: could not compile") {
+        return Err(anyhow!("Build failed: compile error"));
+    }
+
+    let uuid = resp
+        .uuid
+        .or_else(|| opts.get("uuid").cloned())
+        .ok_or_else(|| anyhow!("Server didn't return the program UUID"))?;
+    let binary = client
+        .deploy(&uuid)
+        .await
+        .map_err(|e| anyhow!("Build failed: {e}"))?;
+
+    let name = dir
+        .canonicalize()?
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.replace('-', "_"))
+        .ok_or_else(|| anyhow!("Invalid program directory: {dir:?}"))?;
+    let out_path = opts
+        .get("out")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| dir.join("target").join("deploy"));
+    fs::create_dir_all(&out_path)?;
+
+    let binary_path = out_path.join(&name).with_extension("so");
+    fs::write(&binary_path, binary)?;
+    eprintln!("Program binary: {}", binary_path.display());
+    if let Some(idl) = resp.idl {
+        let idl_path = out_path.join(&name).with_extension("json");
+        fs::write(&idl_path, serde_json::to_vec_pretty(&idl)?)?;
+        eprintln!("IDL: {}", idl_path.display());
+    }
+
+    if let Some(report) = resp.compatibility {
+        for change in &report.changes {
+            let breaking = if change.is_breaking { "breaking" } else { "ok" };
+            eprintln!("[{breaking}] {}: {}", change.path, change.description);
+        }
+        if report.is_breaking {
+            eprintln!("The new IDL is not compatible with the previous IDL");
+        }
+    }
+
+    // Print the UUID last in order to make it easy to reuse in subsequent builds
+    println!("{uuid}");
+    Ok(())
+}
+
+/// Read the files inside the `src` directory of the program at `dir`.
+///
+/// Paths are relative to `dir` with a leading `/`, e.g. `/src/lib.rs`.
+fn read_files(dir: &Path) -> Result<Files> {
+    let mut files = vec![];
+    extend_files(&mut files, &dir.join("src"), dir)?;
+    if files.is_empty() {
+        return Err(anyhow!("No files found in `{}`", dir.join("src").display()));
+    }
+
+    Files::try_from(files)
+}
+
+/// Recursively extend the given files from `path`.
+fn extend_files(files: &mut Vec<(PathBuf, String)>, path: &Path, root: &Path) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            extend_files(files, &path, root)?;
+        } else if file_type.is_file() {
+            let content = fs::read_to_string(&path)?;
+            let path = Path::new("/").join(path.strip_prefix(root)?);
+            files.push((path, content));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the files of the share to the `out_path` directory.
+fn write_share(share: &Value, out_path: &Path) -> Result<()> {
+    let files = share
+        .get("files")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("Invalid share: missing `files`"))?;
+    for (path, file) in files {
+        let relative_path = Path::new(path.trim_start_matches('/'));
+        if !relative_path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid share file path: {path}"));
+        }
+
+        let content = file
+            .get("content")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let file_path = out_path.join(relative_path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file_path, content)?;
+        eprintln!("{}", file_path.display());
+    }
+
+    Ok(())
+}
@@ -1,15 +1,35 @@
-use std::str::FromStr;
+use std::{
+    fmt::Display,
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
+use anyhow::{anyhow, Result};
 use dotenv::dotenv;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{
+    package::{Bundler, PackageManager},
+    SandboxBackend,
+};
+
+/// Current server configuration
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
 
 /// Server configuration
-#[derive(Debug)]
+///
+/// The configuration can be set with a TOML file (path from `PG_CONFIG`) and environment
+/// variables, where environment variables override the values in the file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     /// Client URLs to allow requests from
     pub client_urls: Vec<String>,
     /// Port to listen from
     pub port: u16,
-    /// Request payload size limit in bytes
+    /// Request payload size limit in bytes (for routes without a specific limit)
     pub payload_limit: usize,
     /// Whether logs should be verbose
     pub verbose: bool,
@@ -17,38 +37,506 @@ pub struct Config {
     pub db_uri: String,
     /// Database name
     pub db_name: String,
+    /// Sandbox configuration
+    pub sandbox: SandboxConfig,
+    /// `/build` route configuration
+    pub build: BuildConfig,
+    /// `/unstable/bundle` route configuration
+    pub bundle: BundleConfig,
+    /// `/new` route configuration
+    pub share: ShareConfig,
+}
+
+/// Sandbox configuration
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Backend to run the sandboxed processes with
+    #[serde(deserialize_with = "from_str")]
+    pub backend: SandboxBackend,
+    /// Amount of idle containers to keep per sandbox image (`0` disables pooling)
+    pub pool_size: usize,
+    /// Maximum amount of uses per pooled container (only read-only containers are reused)
+    pub pool_max_uses: usize,
+    /// Directory of the exported image root filesystems for the `local` backend
+    pub local_images_dir: PathBuf,
+}
+
+/// `/build` route configuration
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    /// Request payload size limit in bytes
+    pub payload_limit: Option<usize>,
     /// Maximum amount of concurrent builds
-    pub build_concurrency: usize,
+    pub concurrency: usize,
+    /// Available toolchains, where the first one is the default
+    pub toolchains: Vec<ToolchainConfig>,
+}
+
+/// Pre-provisioned program build toolchain
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolchainConfig {
+    /// Solana platform-tools version, e.g. `1.37`
+    pub platform_tools: String,
+    /// Anchor version, e.g. `0.29.0`
+    pub anchor: String,
+    /// Directory that includes the `Cargo.toml` and `Cargo.lock` of the toolchain.
+    ///
+    /// Dependencies must already be fetched since the programs are built offline.
+    pub dir: PathBuf,
+    /// Directory of the Solana binaries (e.g. `cargo-build-sbf`) that use `platform_tools`,
+    /// defaults to the binaries in `PATH`
+    pub solana_bin: Option<PathBuf>,
+}
+
+/// `/unstable/bundle` route configuration
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BundleConfig {
+    /// Request payload size limit in bytes
+    pub payload_limit: Option<usize>,
+    /// Maximum amount of concurrent bundles
+    pub concurrency: usize,
+    /// NPM registry (mirror) URL to install the bundle packages from
+    pub npm_registry: String,
+    /// Docker network that has access to `npm_registry`
+    pub npm_network: String,
+    /// Package manager to install the bundle packages with
+    #[serde(deserialize_with = "from_str")]
+    pub package_manager: PackageManager,
+    /// Bundler to bundle the packages with
+    #[serde(deserialize_with = "from_str")]
+    pub bundler: Bundler,
+    /// Sandbox CPU (cores) limit
+    pub cpu_limit: usize,
+    /// Sandbox memory limit in bytes (also affects the bundling speed)
+    pub memory_limit: usize,
+    /// Sandbox process (PIDs) limit
+    pub process_limit: usize,
+    /// Sandbox timeout in seconds
+    pub timeout: u64,
+    /// Maximum size of the bundle cache in bytes, the least recently used bundles are removed
+    /// when the cache exceeds it
+    pub cache_max_bytes: u64,
+}
+
+/// `/new` route configuration
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShareConfig {
+    /// Request payload size limit in bytes
+    pub payload_limit: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            client_urls: vec![
+                "http://localhost".to_owned(),
+                "https://beta.solpg.io".to_owned(),
+            ],
+            port: 8080,
+            payload_limit: 1024 * 1024,
+            verbose: false,
+            db_uri: "mongodb://localhost:27017".to_owned(),
+            db_name: "solpg".to_owned(),
+            sandbox: Default::default(),
+            build: Default::default(),
+            bundle: Default::default(),
+            share: Default::default(),
+        }
+    }
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            backend: Default::default(),
+            pool_size: 0,
+            pool_max_uses: 10,
+            local_images_dir: "images/rootfs".into(),
+        }
+    }
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            payload_limit: None,
+            concurrency: 16,
+            toolchains: vec![ToolchainConfig {
+                platform_tools: "1.37".to_owned(),
+                anchor: "0.29.0".to_owned(),
+                dir: "programs".into(),
+                solana_bin: None,
+            }],
+        }
+    }
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        Self {
+            payload_limit: None,
+            concurrency: 2,
+            npm_registry: "http://npm-registry:4873".to_owned(),
+            npm_network: "solpg-npm-registry".to_owned(),
+            package_manager: Default::default(),
+            bundler: Default::default(),
+            cpu_limit: 4, // diminishing returns after 4
+            memory_limit: 4 * 1024 * 1024 * 1024,
+            process_limit: 64,
+            timeout: 300,
+            cache_max_bytes: 10 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl BuildConfig {
+    /// Find the toolchain that matches the given versions, or the default toolchain if no versions
+    /// are given.
+    ///
+    /// Versions match by prefix, e.g. `0.29` matches `0.29.0`, and the leading `v` is ignored.
+    pub fn toolchain(
+        &self,
+        platform_tools: Option<&str>,
+        anchor: Option<&str>,
+    ) -> Result<&ToolchainConfig> {
+        let matches = |version: &str, req: Option<&str>| match req {
+            Some(req) => version_matches(version, req),
+            None => true,
+        };
+        self.toolchains
+            .iter()
+            .find(|t| matches(&t.platform_tools, platform_tools) && matches(&t.anchor, anchor))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unsupported toolchain: platform-tools {}, Anchor {}",
+                    platform_tools.unwrap_or("*"),
+                    anchor.unwrap_or("*")
+                )
+            })
+    }
+}
+
+impl ToolchainConfig {
+    /// Get whether the Anchor version of the toolchain matches the given version `req`.
+    pub fn anchor_matches(&self, req: &str) -> bool {
+        version_matches(&self.anchor, req)
+    }
+}
+
+/// Get whether the `version` matches the version `req`uirement by prefix, e.g. `0.29` matches
+/// `0.29.0`. The leading `v` is ignored.
+fn version_matches(version: &str, req: &str) -> bool {
+    let (version, req) = (version.trim_start_matches('v'), req.trim_start_matches('v'));
+    version == req || version.starts_with(&format!("{req}."))
 }
 
 impl Config {
-    /// Create [`Config`] from the environment variables.
+    /// Load the config from the config file and the environment variables.
     ///
-    /// `.env` file is supported.
-    pub fn from_env() -> Config {
+    /// `.env` file is supported. Unlike missing values, malformed values result in an error.
+    pub fn load() -> Result<Self> {
         dotenv().ok();
-        Config {
-            client_urls: get_env::<String>("CLIENT_URLS", "http://localhost,https://beta.solpg.io")
+
+        let mut config = match dotenv::var("PG_CONFIG") {
+            Ok(path) => {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read config file `{path}`: {e}"))?;
+                toml::from_str(&content)
+                    .map_err(|e| anyhow!("Invalid config file `{path}`: {e}"))?
+            }
+            Err(_) => Self::default(),
+        };
+
+        if let Some(client_urls) = get_env::<String>("CLIENT_URLS")? {
+            config.client_urls = client_urls
                 .split(',')
                 .map(str::trim)
                 .map(ToOwned::to_owned)
-                .collect(),
-            port: get_env("PORT", 8080u16),
-            payload_limit: get_env("PAYLOAD_LIMIT", 1024usize * 1024),
-            verbose: get_env("VERBOSE", false),
-            db_uri: get_env("DB_URI", "mongodb://localhost:27017"),
-            db_name: get_env("DB_NAME", "solpg"),
-            build_concurrency: get_env("BUILD_CONCURRENCY", 16usize),
+                .collect();
         }
+        set_env(&mut config.port, "PORT")?;
+        set_env(&mut config.payload_limit, "PAYLOAD_LIMIT")?;
+        set_env(&mut config.verbose, "VERBOSE")?;
+        set_env(&mut config.db_uri, "DB_URI")?;
+        set_env(&mut config.db_name, "DB_NAME")?;
+
+        let sandbox = &mut config.sandbox;
+        set_env(&mut sandbox.backend, "SANDBOX_BACKEND")?;
+        set_env(&mut sandbox.pool_size, "SANDBOX_POOL_SIZE")?;
+        set_env(&mut sandbox.pool_max_uses, "SANDBOX_POOL_MAX_USES")?;
+        set_env(&mut sandbox.local_images_dir, "SANDBOX_LOCAL_IMAGES_DIR")?;
+
+        let build = &mut config.build;
+        set_env_opt(&mut build.payload_limit, "BUILD_PAYLOAD_LIMIT")?;
+        set_env(&mut build.concurrency, "BUILD_CONCURRENCY")?;
+
+        let bundle = &mut config.bundle;
+        set_env_opt(&mut bundle.payload_limit, "BUNDLE_PAYLOAD_LIMIT")?;
+        set_env(&mut bundle.concurrency, "BUNDLE_CONCURRENCY")?;
+        set_env(&mut bundle.npm_registry, "NPM_REGISTRY")?;
+        set_env(&mut bundle.npm_network, "NPM_NETWORK")?;
+        set_env(&mut bundle.package_manager, "PACKAGE_MANAGER")?;
+        set_env(&mut bundle.bundler, "BUNDLER")?;
+        set_env(&mut bundle.cpu_limit, "BUNDLE_CPU_LIMIT")?;
+        set_env(&mut bundle.memory_limit, "BUNDLE_MEMORY_LIMIT")?;
+        set_env(&mut bundle.process_limit, "BUNDLE_PROCESS_LIMIT")?;
+        set_env(&mut bundle.timeout, "BUNDLE_TIMEOUT")?;
+        set_env(&mut bundle.cache_max_bytes, "BUNDLE_CACHE_MAX_BYTES")?;
+
+        set_env_opt(&mut config.share.payload_limit, "SHARE_PAYLOAD_LIMIT")?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Get the current config.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if [`Config::set_current`] hasn't been called.
+    pub fn current() -> Arc<Self> {
+        CONFIG
+            .read()
+            .expect("Poisoned lock")
+            .clone()
+            .expect("`Config::set_current` must be called before `Config::current`")
+    }
+
+    /// Set the current config, returning the previous one.
+    ///
+    /// Values that are read from [`Config::current`] on each request (e.g. limits) apply to new
+    /// requests immediately, while requests in progress keep using the previous config.
+    pub fn set_current(config: Self) -> Option<Arc<Self>> {
+        CONFIG
+            .write()
+            .expect("Poisoned lock")
+            .replace(Arc::new(config))
+    }
+
+    /// Get the names of the changed values that only apply after a restart.
+    pub fn restart_required(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("port", self.port != other.port),
+            ("verbose", self.verbose != other.verbose),
+            ("db_uri", self.db_uri != other.db_uri),
+            ("db_name", self.db_name != other.db_name),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+
+    /// Validate the config values.
+    fn validate(&self) -> Result<()> {
+        if self.client_urls.is_empty() || self.client_urls.iter().any(String::is_empty) {
+            return Err(anyhow!("`client_urls` must only include non-empty URLs"));
+        }
+        if !["mongodb://", "mongodb+srv://"]
+            .iter()
+            .any(|scheme| self.db_uri.starts_with(scheme))
+        {
+            return Err(anyhow!("`db_uri` must be a MongoDB connection string"));
+        }
+        if !["http://", "https://"]
+            .iter()
+            .any(|scheme| self.bundle.npm_registry.starts_with(scheme))
+        {
+            return Err(anyhow!("`bundle.npm_registry` must be an HTTP(S) URL"));
+        }
+
+        if self.build.toolchains.is_empty() {
+            return Err(anyhow!(
+                "`build.toolchains` must include at least one toolchain"
+            ));
+        }
+        for (i, toolchain) in self.build.toolchains.iter().enumerate() {
+            let is_duplicate = self.build.toolchains[..i].iter().any(|other| {
+                other.platform_tools == toolchain.platform_tools && other.anchor == toolchain.anchor
+            });
+            if is_duplicate {
+                return Err(anyhow!(
+                    "Duplicate toolchain in `build.toolchains`: platform-tools {}, Anchor {}",
+                    toolchain.platform_tools,
+                    toolchain.anchor
+                ));
+            }
+        }
+
+        let non_zero = [
+            ("payload_limit", self.payload_limit as u64),
+            ("sandbox.pool_max_uses", self.sandbox.pool_max_uses as u64),
+            ("build.concurrency", self.build.concurrency as u64),
+            ("bundle.concurrency", self.bundle.concurrency as u64),
+            ("bundle.cpu_limit", self.bundle.cpu_limit as u64),
+            ("bundle.memory_limit", self.bundle.memory_limit as u64),
+            ("bundle.process_limit", self.bundle.process_limit as u64),
+            ("bundle.timeout", self.bundle.timeout),
+            ("bundle.cache_max_bytes", self.bundle.cache_max_bytes),
+        ]
+        .into_iter()
+        .chain(
+            [
+                ("build.payload_limit", self.build.payload_limit),
+                ("bundle.payload_limit", self.bundle.payload_limit),
+                ("share.payload_limit", self.share.payload_limit),
+            ]
+            .into_iter()
+            .filter_map(|(name, limit)| limit.map(|limit| (name, limit as u64))),
+        );
+        for (name, value) in non_zero {
+            if value == 0 {
+                return Err(anyhow!("`{name}` must be greater than 0"));
+            }
+        }
+
+        Ok(())
     }
 }
 
-/// Get the environment variable value or return the `default`.
+/// Get the environment variable value, `None` if it's not set.
 ///
 /// All environment variables are prefixed with `PG_` in order to prevent clashes.
-fn get_env<T: FromStr>(key: &str, default: impl Into<T>) -> T {
-    dotenv::var(format!("PG_{key}"))
+fn get_env<T>(key: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let key = format!("PG_{key}");
+    dotenv::var(&key)
         .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(default.into())
+        .map(|s| {
+            s.parse()
+                .map_err(|e| anyhow!("Invalid `{key}` value `{s}`: {e}"))
+        })
+        .transpose()
+}
+
+/// Override the value with the environment variable if it's set.
+fn set_env<T>(value: &mut T, key: &str) -> Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(env_value) = get_env(key)? {
+        *value = env_value;
+    }
+
+    Ok(())
+}
+
+/// Override the optional value with the environment variable if it's set.
+fn set_env_opt<T>(value: &mut Option<T>, key: &str) -> Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(env_value) = get_env(key)? {
+        value.replace(env_value);
+    }
+
+    Ok(())
+}
+
+/// Deserialize the value from its string representation.
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a toolchain config of the given versions.
+    fn toolchain(platform_tools: &str, anchor: &str) -> ToolchainConfig {
+        ToolchainConfig {
+            platform_tools: platform_tools.to_owned(),
+            anchor: anchor.to_owned(),
+            dir: format!("toolchains/{platform_tools}-{anchor}").into(),
+            solana_bin: None,
+        }
+    }
+
+    /// Build config with two toolchains, where the first one is the default
+    fn build_config() -> BuildConfig {
+        BuildConfig {
+            toolchains: vec![toolchain("1.41", "0.30.1"), toolchain("1.37", "0.29.0")],
+            ..Default::default()
+        }
+    }
+
+    /// Get the versions of the selected toolchain.
+    fn select(platform_tools: Option<&str>, anchor: Option<&str>) -> Result<(String, String)> {
+        build_config()
+            .toolchain(platform_tools, anchor)
+            .map(|t| (t.platform_tools.clone(), t.anchor.clone()))
+    }
+
+    #[test]
+    fn toolchain_default() {
+        assert_eq!(
+            select(None, None).unwrap(),
+            ("1.41".to_owned(), "0.30.1".to_owned())
+        );
+    }
+
+    #[test]
+    fn toolchain_prefix() {
+        let other = ("1.37".to_owned(), "0.29.0".to_owned());
+        assert_eq!(select(None, Some("0.29")).unwrap(), other);
+        assert_eq!(select(None, Some("0.29.0")).unwrap(), other);
+        assert_eq!(select(None, Some("v0.29")).unwrap(), other);
+        assert_eq!(select(Some("1.37"), None).unwrap(), other);
+        assert_eq!(select(Some("1.37"), Some("0")).unwrap(), other);
+
+        // Prefixes only match whole version components
+        assert!(select(None, Some("0.2")).is_err());
+        assert!(select(Some("1.3"), None).is_err());
+    }
+
+    #[test]
+    fn toolchain_unsupported() {
+        let err = select(Some("1.41"), Some("0.29")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported toolchain: platform-tools 1.41, Anchor 0.29"
+        );
+        let err = select(None, Some("0.31")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported toolchain: platform-tools *, Anchor 0.31"
+        );
+    }
+
+    #[test]
+    fn toolchain_validation() {
+        let mut config = Config {
+            build: build_config(),
+            ..Default::default()
+        };
+        config.validate().unwrap();
+
+        config.build.toolchains.push(toolchain("1.41", "0.30.1"));
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Duplicate toolchain in `build.toolchains`: platform-tools 1.41, Anchor 0.30.1"
+        );
+
+        config.build.toolchains.clear();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "`build.toolchains` must include at least one toolchain"
+        );
+    }
 }
@@ -8,8 +8,17 @@ use mongodb::{
 };
 use serde_json::Value;
 
-/// Global database client
-static DB: OnceLock<Client> = OnceLock::new();
+/// Global database
+static DB: OnceLock<Db> = OnceLock::new();
+
+/// Database backend
+enum Db {
+    /// MongoDB client
+    Mongo(Client),
+    /// In-memory store of collection names to their values
+    #[cfg(feature = "test-util")]
+    Memory(memory::Store),
+}
 
 /// Initialize the global database singleton.
 ///
@@ -20,41 +29,107 @@ pub async fn init(uri: &str, name: String) -> Result<()> {
     options.server_selection_timeout = Some(Duration::from_secs(2));
 
     let client = Client::with_options(options)?;
-    DB.set(client).map_err(|_| anyhow!("Failed to init `DB`"))
+    DB.set(Db::Mongo(client))
+        .map_err(|_| anyhow!("Failed to init `DB`"))
+}
+
+/// Initialize the global database singleton with an in-memory store.
+///
+/// Values are lost when the process exits, which makes this only useful for tests.
+#[cfg(feature = "test-util")]
+pub fn init_memory() -> Result<()> {
+    DB.set(Db::Memory(Default::default()))
+        .map_err(|_| anyhow!("Failed to init `DB`"))
 }
 
 /// Find the value by id in the given `collection`.
 pub async fn find_by_id(id: &str, collection: &str) -> Result<Option<Value>> {
     let id = ObjectId::parse_str(id)?;
-    let value = get_collection(collection)
-        .find_one(doc! { "_id": id }, None)
-        .await?;
-    Ok(value)
+    match get_db() {
+        Db::Mongo(client) => {
+            let value = get_collection(client, collection)
+                .find_one(doc! { "_id": id }, None)
+                .await?;
+            Ok(value)
+        }
+        #[cfg(feature = "test-util")]
+        Db::Memory(store) => Ok(memory::find_by_id(store, id, collection)),
+    }
 }
 
 /// Insert the value inside the given `collection`.
 pub async fn insert(value: Value, collection: &str) -> Result<String> {
-    match get_collection(collection)
-        .insert_one(value, None)
-        .await?
-        .inserted_id
-    {
-        Bson::ObjectId(id) => Ok(id.to_string()),
-        _ => Err(anyhow!("Unexpected `insert_one` result")),
+    match get_db() {
+        Db::Mongo(client) => match get_collection(client, collection)
+            .insert_one(value, None)
+            .await?
+            .inserted_id
+        {
+            Bson::ObjectId(id) => Ok(id.to_string()),
+            _ => Err(anyhow!("Unexpected `insert_one` result")),
+        },
+        #[cfg(feature = "test-util")]
+        Db::Memory(store) => Ok(memory::insert(store, value, collection)),
     }
 }
 
-/// Get collection from the given collection `name`.
+/// Get the global database.
 ///
 /// # Panics
 ///
-/// This function panics in the following scenerios:
-/// - [`DB`] isn't initialized
-/// - Default database isn't set
-fn get_collection(name: &str) -> Collection<Value> {
+/// This function panics if [`DB`] isn't initialized.
+fn get_db() -> &'static Db {
     DB.get()
-        .expect("`db::init` must be called before `get_collection`")
+        .expect("`db::init` or `db::init_memory` must be called before using the database")
+}
+
+/// Get collection from the given collection `name`.
+///
+/// # Panics
+///
+/// This function panics if the default database isn't set.
+fn get_collection(client: &Client, name: &str) -> Collection<Value> {
+    client
         .default_database()
         .expect("Default database must be set")
         .collection(name)
 }
+
+/// In-memory database that is only available with the `test-util` feature
+#[cfg(feature = "test-util")]
+mod memory {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use mongodb::bson::oid::ObjectId;
+    use serde_json::{json, Value};
+
+    /// Collection names to their values
+    pub(super) type Store = Mutex<HashMap<String, HashMap<ObjectId, Value>>>;
+
+    /// Find the value by id in the given `collection`.
+    pub(super) fn find_by_id(store: &Store, id: ObjectId, collection: &str) -> Option<Value> {
+        store
+            .lock()
+            .expect("Poisoned lock")
+            .get(collection)
+            .and_then(|values| values.get(&id))
+            .cloned()
+    }
+
+    /// Insert the value inside the given `collection` and return its id.
+    pub(super) fn insert(store: &Store, mut value: Value, collection: &str) -> String {
+        // Same representation as the values that are returned from MongoDB
+        let id = ObjectId::new();
+        if let Value::Object(map) = &mut value {
+            map.insert("_id".to_owned(), json!({ "$oid": id.to_hex() }));
+        }
+
+        store
+            .lock()
+            .expect("Poisoned lock")
+            .entry(collection.to_owned())
+            .or_default()
+            .insert(id, value);
+        id.to_string()
+    }
+}
@@ -0,0 +1,949 @@
+//! Compatibility checks between the IDLs of two builds of the same program.
+//!
+//! Only the parts of the IDL that affect clients and the data on chain are compared, i.e.
+//! instructions, accounts and types. Anchor derives discriminators from the names, which means
+//! renamed instructions and accounts are reported as discriminator changes.
+
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlAccountItem, IdlEnumVariant, IdlField, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
+pub use solpg_server_types::{ChangeKind, CompatibilityReport, IdlChange};
+
+/// Maximum depth of nested defined types to calculate the size of
+const MAX_TYPE_DEPTH: usize = 32;
+
+/// Compare the IDLs and return the compatibility report of upgrading from `old` to `new`.
+pub fn compare(old: &Idl, new: &Idl) -> CompatibilityReport {
+    let mut diff = Diff {
+        old,
+        new,
+        report: Default::default(),
+    };
+    diff.instructions();
+    diff.definitions("accounts", &old.accounts, &new.accounts, true);
+    diff.definitions("types", &old.types, &new.types, false);
+    diff.report
+}
+
+/// Kind of the compared fields
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Fields {
+    /// Instruction arguments
+    Args,
+    /// Account fields
+    Account,
+    /// Fields of other types, which can be embedded in accounts
+    Type,
+}
+
+/// Comparison state of two IDLs
+struct Diff<'a> {
+    /// Previous IDL
+    old: &'a Idl,
+    /// New IDL
+    new: &'a Idl,
+    /// Report of the changes so far
+    report: CompatibilityReport,
+}
+
+impl Diff<'_> {
+    /// Add a change to the report.
+    fn push(&mut self, kind: ChangeKind, path: String, description: String, is_breaking: bool) {
+        self.report.is_breaking |= is_breaking;
+        self.report.changes.push(IdlChange {
+            kind,
+            path,
+            description,
+            is_breaking,
+        });
+    }
+
+    /// Compare the instructions.
+    ///
+    /// Every change to the existing instructions is breaking since clients encode the arguments
+    /// and pass the accounts by position.
+    fn instructions(&mut self) {
+        let (old, new) = (&self.old.instructions, &self.new.instructions);
+        let renames = renames(
+            old,
+            new,
+            |ix| &ix.name,
+            |a, b| a.args == b.args && account_names(&a.accounts) == account_names(&b.accounts),
+        );
+        for (o, n) in &renames {
+            self.push(
+                ChangeKind::DiscriminatorChanged,
+                format!("instructions.{}", n.name),
+                format!("Renamed from `{}`", o.name),
+                true,
+            );
+        }
+
+        for o in old {
+            let is_renamed = renames.iter().any(|(r, _)| r.name == o.name);
+            match new.iter().find(|n| n.name == o.name) {
+                Some(n) => {
+                    let path = format!("instructions.{}", o.name);
+                    self.fields(&format!("{path}.args"), &o.args, &n.args, Fields::Args);
+                    self.names(
+                        &format!("{path}.accounts"),
+                        &account_names(&o.accounts),
+                        &account_names(&n.accounts),
+                    );
+                }
+                None if !is_renamed => self.push(
+                    ChangeKind::Removed,
+                    format!("instructions.{}", o.name),
+                    "Removed instruction".to_owned(),
+                    true,
+                ),
+                None => {}
+            }
+        }
+
+        for n in new {
+            let is_renamed = renames.iter().any(|(_, r)| r.name == n.name);
+            if !is_renamed && !old.iter().any(|o| o.name == n.name) {
+                self.push(
+                    ChangeKind::Added,
+                    format!("instructions.{}", n.name),
+                    "Added instruction".to_owned(),
+                    false,
+                );
+            }
+        }
+    }
+
+    /// Compare the type definitions of the given IDL section.
+    ///
+    /// Renames are only detected for accounts, since the names of other types are not encoded.
+    fn definitions(
+        &mut self,
+        section: &str,
+        old: &[IdlTypeDefinition],
+        new: &[IdlTypeDefinition],
+        is_account: bool,
+    ) {
+        let renames = match is_account {
+            true => renames(old, new, |def| &def.name, |a, b| a.ty == b.ty),
+            false => vec![],
+        };
+        for (o, n) in &renames {
+            self.push(
+                ChangeKind::DiscriminatorChanged,
+                format!("{section}.{}", n.name),
+                format!(
+                    "Renamed from `{}`, existing accounts can't be deserialized",
+                    o.name
+                ),
+                true,
+            );
+        }
+
+        for o in old {
+            let is_renamed = renames.iter().any(|(r, _)| r.name == o.name);
+            let path = format!("{section}.{}", o.name);
+            match new.iter().find(|n| n.name == o.name) {
+                Some(n) => match (&o.ty, &n.ty) {
+                    _ if o.ty == n.ty => {}
+                    (
+                        IdlTypeDefinitionTy::Struct { fields: of },
+                        IdlTypeDefinitionTy::Struct { fields: nf },
+                    ) => {
+                        let kind = if is_account {
+                            Fields::Account
+                        } else {
+                            Fields::Type
+                        };
+                        self.fields(&path, of, nf, kind)
+                    }
+                    (
+                        IdlTypeDefinitionTy::Enum { variants: ov },
+                        IdlTypeDefinitionTy::Enum { variants: nv },
+                    ) => self.variants(&path, ov, nv),
+                    (
+                        IdlTypeDefinitionTy::Alias { value: ov },
+                        IdlTypeDefinitionTy::Alias { value: nv },
+                    ) => {
+                        let (os, ns) = (size(ov, self.old, 0), size(nv, self.new, 0));
+                        let description = format!(
+                            "Aliased type changed from `{}` to `{}`",
+                            type_name(ov),
+                            type_name(nv)
+                        );
+                        self.type_change(path, os, ns, description);
+                    }
+                    _ => {
+                        let (os, ns) = (
+                            definition_size(o, self.old, 0),
+                            definition_size(n, self.new, 0),
+                        );
+                        self.type_change(path, os, ns, "Type kind changed".to_owned());
+                    }
+                },
+                None if !is_renamed => self.push(
+                    ChangeKind::Removed,
+                    path,
+                    match is_account {
+                        true => "Removed account, existing accounts can't be used".to_owned(),
+                        false => "Removed type".to_owned(),
+                    },
+                    is_account,
+                ),
+                None => {}
+            }
+        }
+
+        for n in new {
+            let is_renamed = renames.iter().any(|(_, r)| r.name == n.name);
+            if !is_renamed && !old.iter().any(|o| o.name == n.name) {
+                self.push(
+                    ChangeKind::Added,
+                    format!("{section}.{}", n.name),
+                    match is_account {
+                        true => "Added account".to_owned(),
+                        false => "Added type".to_owned(),
+                    },
+                    false,
+                );
+            }
+        }
+    }
+
+    /// Compare the fields of a struct or the arguments of an instruction.
+    ///
+    /// Removing trailing account fields doesn't break existing accounts since the extra bytes are
+    /// ignored during deserialization.
+    fn fields(&mut self, path: &str, old: &[IdlField], new: &[IdlField], kind: Fields) {
+        let is_args = kind == Fields::Args;
+        let item = if is_args { "argument" } else { "field" };
+        let exists = |fields: &[IdlField], name: &str| fields.iter().any(|f| f.name == name);
+
+        for (i, o) in old.iter().enumerate() {
+            let path = format!("{path}.{}", o.name);
+            match new.iter().find(|n| n.name == o.name) {
+                Some(n) if n.ty != o.ty => {
+                    let (os, ns) = (size(&o.ty, self.old, 0), size(&n.ty, self.new, 0));
+                    let description = format!(
+                        "Type changed from `{}` to `{}`",
+                        type_name(&o.ty),
+                        type_name(&n.ty)
+                    );
+                    self.type_change(path, os, ns, description);
+                }
+                Some(_) => {}
+                None => {
+                    let is_trailing = old[i + 1..].iter().all(|o| !exists(new, &o.name));
+                    let is_breaking = kind != Fields::Account || !is_trailing;
+                    let description = match is_breaking {
+                        true => format!("Removed {item}"),
+                        false => format!("Removed trailing {item}, existing data is ignored"),
+                    };
+                    self.push(ChangeKind::Removed, path, description, is_breaking);
+                }
+            }
+        }
+
+        for n in new.iter().filter(|n| !exists(old, &n.name)) {
+            let description = match is_args {
+                true => format!("Added argument of type `{}`", type_name(&n.ty)),
+                false => format!(
+                    "Added field of type `{}`, existing data doesn't include it",
+                    type_name(&n.ty)
+                ),
+            };
+            self.push(
+                ChangeKind::Added,
+                format!("{path}.{}", n.name),
+                description,
+                true,
+            );
+        }
+
+        let old_order = old.iter().filter(|o| exists(new, &o.name));
+        let new_order = new.iter().filter(|n| exists(old, &n.name));
+        if !old_order.map(|f| &f.name).eq(new_order.map(|f| &f.name)) {
+            self.push(
+                ChangeKind::Reordered,
+                path.to_owned(),
+                format!("Order of the {item}s changed"),
+                true,
+            );
+        }
+    }
+
+    /// Compare the variants of an enum.
+    ///
+    /// Variants are encoded by their index, meaning only appending new variants is non-breaking.
+    fn variants(&mut self, path: &str, old: &[IdlEnumVariant], new: &[IdlEnumVariant]) {
+        for (i, o) in old.iter().enumerate() {
+            let path = format!("{path}.{}", o.name);
+            match new.iter().position(|n| n.name == o.name) {
+                Some(j) if i != j => self.push(
+                    ChangeKind::Reordered,
+                    path,
+                    format!("Variant index changed from {i} to {j}"),
+                    true,
+                ),
+                Some(j) if new[j].fields != o.fields => {
+                    let (os, ns) = (
+                        variant_size(o, self.old, 0).map(|s| s + 1),
+                        variant_size(&new[j], self.new, 0).map(|s| s + 1),
+                    );
+                    self.type_change(path, os, ns, "Variant fields changed".to_owned());
+                }
+                Some(_) => {}
+                None => self.push(
+                    ChangeKind::Removed,
+                    path,
+                    "Removed variant".to_owned(),
+                    true,
+                ),
+            }
+        }
+
+        for (j, n) in new.iter().enumerate() {
+            if !old.iter().any(|o| o.name == n.name) {
+                let is_appended = j >= old.len();
+                self.push(
+                    ChangeKind::Added,
+                    format!("{path}.{}", n.name),
+                    match is_appended {
+                        true => "Appended variant".to_owned(),
+                        false => format!("Inserted variant at index {j}"),
+                    },
+                    !is_appended,
+                );
+            }
+        }
+    }
+
+    /// Compare the instruction account names.
+    fn names(&mut self, path: &str, old: &[String], new: &[String]) {
+        for o in old.iter().filter(|o| !new.contains(o)) {
+            self.push(
+                ChangeKind::Removed,
+                format!("{path}.{o}"),
+                "Removed account".to_owned(),
+                true,
+            );
+        }
+        for n in new.iter().filter(|n| !old.contains(n)) {
+            self.push(
+                ChangeKind::Added,
+                format!("{path}.{n}"),
+                "Added account".to_owned(),
+                true,
+            );
+        }
+
+        let old_order = old.iter().filter(|o| new.contains(o));
+        let new_order = new.iter().filter(|n| old.contains(n));
+        if !old_order.eq(new_order) {
+            self.push(
+                ChangeKind::Reordered,
+                path.to_owned(),
+                "Order of the accounts changed".to_owned(),
+                true,
+            );
+        }
+    }
+
+    /// Add a breaking type change, which is reported as a resize if the size changed.
+    fn type_change(
+        &mut self,
+        path: String,
+        old_size: Option<usize>,
+        new_size: Option<usize>,
+        description: String,
+    ) {
+        let fmt_size = |size: Option<usize>| match size {
+            Some(size) => format!("{size} bytes"),
+            None => "variable size".to_owned(),
+        };
+        match old_size == new_size {
+            true => self.push(ChangeKind::TypeChanged, path, description, true),
+            false => self.push(
+                ChangeKind::Resized,
+                path,
+                format!(
+                    "{description} ({} -> {})",
+                    fmt_size(old_size),
+                    fmt_size(new_size)
+                ),
+                true,
+            ),
+        }
+    }
+}
+
+/// Match the removed items to the added items with the same content.
+///
+/// Items are only matched if the match is unambiguous, e.g. two removed instructions without any
+/// arguments or accounts can't be matched to an added instruction.
+fn renames<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    name: impl Fn(&T) -> &String,
+    is_same: impl Fn(&T, &T) -> bool,
+) -> Vec<(&'a T, &'a T)> {
+    let removed = old
+        .iter()
+        .filter(|o| !new.iter().any(|n| name(n) == name(o)))
+        .collect::<Vec<_>>();
+    let added = new
+        .iter()
+        .filter(|n| !old.iter().any(|o| name(o) == name(n)))
+        .collect::<Vec<_>>();
+    removed
+        .iter()
+        .filter_map(
+            |o| match added.iter().filter(|n| is_same(o, n)).collect::<Vec<_>>()[..] {
+                [n] if removed.iter().filter(|r| is_same(r, n)).count() == 1 => Some((*o, *n)),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Get the flattened account names of an instruction, e.g. `group.account`.
+fn account_names(accounts: &[IdlAccountItem]) -> Vec<String> {
+    accounts
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(acc) => vec![acc.name.clone()],
+            IdlAccountItem::IdlAccounts(group) => account_names(&group.accounts)
+                .into_iter()
+                .map(|name| format!("{}.{name}", group.name))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Get the serialized size of the type, `None` if the size is variable or unknown.
+fn size(ty: &IdlType, idl: &Idl, depth: usize) -> Option<usize> {
+    match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => Some(1),
+        IdlType::U16 | IdlType::I16 => Some(2),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => Some(4),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => Some(8),
+        IdlType::U128 | IdlType::I128 => Some(16),
+        IdlType::U256 | IdlType::I256 | IdlType::PublicKey => Some(32),
+        IdlType::Array(ty, len) => size(ty, idl, depth)?.checked_mul(*len),
+        IdlType::Defined(name) => idl
+            .types
+            .iter()
+            .chain(&idl.accounts)
+            .find(|def| &def.name == name)
+            .and_then(|def| definition_size(def, idl, depth + 1)),
+        _ => None,
+    }
+}
+
+/// Get the serialized size of the type definition, `None` if the size is variable or unknown.
+fn definition_size(def: &IdlTypeDefinition, idl: &Idl, depth: usize) -> Option<usize> {
+    if depth > MAX_TYPE_DEPTH {
+        return None;
+    }
+
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            fields.iter().map(|field| size(&field.ty, idl, depth)).sum()
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            // Enums only have a fixed size if all of their variants have the same size
+            let sizes = variants
+                .iter()
+                .map(|variant| variant_size(variant, idl, depth))
+                .collect::<Option<Vec<_>>>()?;
+            match sizes.windows(2).all(|w| w[0] == w[1]) {
+                true => Some(1 + sizes.first().copied().unwrap_or_default()),
+                false => None,
+            }
+        }
+        IdlTypeDefinitionTy::Alias { value } => size(value, idl, depth),
+    }
+}
+
+/// Get the serialized size of the enum variant fields, excluding the variant index.
+fn variant_size(variant: &IdlEnumVariant, idl: &Idl, depth: usize) -> Option<usize> {
+    match &variant.fields {
+        Some(EnumFields::Named(fields)) => fields.iter().map(|f| size(&f.ty, idl, depth)).sum(),
+        Some(EnumFields::Tuple(types)) => types.iter().map(|ty| size(ty, idl, depth)).sum(),
+        None => Some(0),
+    }
+}
+
+/// Get the Rust-like name of the type, e.g. `Option<u64>`.
+fn type_name(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Defined(name) | IdlType::Generic(name) => name.clone(),
+        IdlType::DefinedWithTypeArgs { name, .. } => format!("{name}<..>"),
+        IdlType::Option(ty) => format!("Option<{}>", type_name(ty)),
+        IdlType::Vec(ty) => format!("Vec<{}>", type_name(ty)),
+        IdlType::Array(ty, len) => format!("[{}; {len}]", type_name(ty)),
+        IdlType::GenericLenArray(ty, len) => format!("[{}; {len}]", type_name(ty)),
+        _ => serde_json::to_value(ty)
+            .ok()
+            .and_then(|value| value.as_str().map(ToOwned::to_owned))
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Create an IDL from the given sections.
+    fn idl(instructions: Value, accounts: Value, types: Value) -> Idl {
+        serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "test",
+            "instructions": instructions,
+            "accounts": accounts,
+            "types": types,
+        }))
+        .unwrap()
+    }
+
+    /// Create a struct type definition from `(name, type)` fields.
+    fn def(name: &str, fields: &[(&str, &str)]) -> Value {
+        let fields = fields
+            .iter()
+            .map(|(name, ty)| json!({ "name": name, "type": ty }))
+            .collect::<Vec<_>>();
+        json!({ "name": name, "type": { "kind": "struct", "fields": fields } })
+    }
+
+    /// Create an enum type definition from variant names, where the variants don't have fields.
+    fn enum_def(name: &str, variants: &[&str]) -> Value {
+        let variants = variants
+            .iter()
+            .map(|name| json!({ "name": name }))
+            .collect::<Vec<_>>();
+        json!({ "name": name, "type": { "kind": "enum", "variants": variants } })
+    }
+
+    /// Create an instruction from its account names and `(name, type)` arguments.
+    fn ix(name: &str, accounts: &[&str], args: &[(&str, &str)]) -> Value {
+        let accounts = accounts
+            .iter()
+            .map(|name| json!({ "name": name, "isMut": false, "isSigner": false }))
+            .collect::<Vec<_>>();
+        let args = args
+            .iter()
+            .map(|(name, ty)| json!({ "name": name, "type": ty }))
+            .collect::<Vec<_>>();
+        json!({ "name": name, "accounts": accounts, "args": args })
+    }
+
+    /// Compare the IDLs and return the changes as `(kind, path, is_breaking)`.
+    fn changes(old: &Idl, new: &Idl) -> Vec<(ChangeKind, String, bool)> {
+        let report = compare(old, new);
+        assert_eq!(
+            report.is_breaking,
+            report.changes.iter().any(|change| change.is_breaking)
+        );
+        report
+            .changes
+            .into_iter()
+            .map(|change| (change.kind, change.path, change.is_breaking))
+            .collect()
+    }
+
+    /// Create an expected change.
+    fn change(kind: ChangeKind, path: &str, is_breaking: bool) -> (ChangeKind, String, bool) {
+        (kind, path.to_owned(), is_breaking)
+    }
+
+    #[test]
+    fn unchanged() {
+        let idl = idl(
+            json!([ix("initialize", &["signer"], &[("amount", "u64")])]),
+            json!([def("Counter", &[("count", "u64")])]),
+            json!([enum_def("State", &["Active", "Closed"])]),
+        );
+        let report = compare(&idl, &idl);
+        assert!(!report.is_breaking);
+        assert!(report.changes.is_empty());
+    }
+
+    #[test]
+    fn instruction_rename() {
+        let old = idl(json!([ix("init", &["signer"], &[])]), json!([]), json!([]));
+        let new = idl(
+            json!([ix("initialize", &["signer"], &[])]),
+            json!([]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::DiscriminatorChanged,
+                "instructions.initialize",
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn instruction_ambiguous_rename() {
+        // Both removed instructions match the added instruction
+        let old = idl(
+            json!([ix("a", &[], &[]), ix("b", &[], &[])]),
+            json!([]),
+            json!([]),
+        );
+        let new = idl(json!([ix("c", &[], &[])]), json!([]), json!([]));
+        assert_eq!(
+            changes(&old, &new),
+            [
+                change(ChangeKind::Removed, "instructions.a", true),
+                change(ChangeKind::Removed, "instructions.b", true),
+                change(ChangeKind::Added, "instructions.c", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn instruction_added_and_removed() {
+        let old = idl(json!([ix("a", &[], &[])]), json!([]), json!([]));
+        let new = idl(
+            json!([ix("a", &[], &[]), ix("b", &["signer"], &[])]),
+            json!([]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Added, "instructions.b", false)]
+        );
+        assert_eq!(
+            changes(&new, &old),
+            [change(ChangeKind::Removed, "instructions.b", true)]
+        );
+    }
+
+    #[test]
+    fn instruction_args() {
+        let old = idl(
+            json!([ix("a", &[], &[("x", "u64"), ("y", "u8")])]),
+            json!([]),
+            json!([]),
+        );
+        let new = idl(json!([ix("a", &[], &[("x", "u64")])]), json!([]), json!([]));
+        // Removing trailing arguments is breaking unlike account fields
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Removed, "instructions.a.args.y", true)]
+        );
+        assert_eq!(
+            changes(&new, &old),
+            [change(ChangeKind::Added, "instructions.a.args.y", true)]
+        );
+    }
+
+    #[test]
+    fn instruction_accounts() {
+        let old = idl(
+            json!([ix("a", &["signer", "counter"], &[])]),
+            json!([]),
+            json!([]),
+        );
+
+        let new = idl(
+            json!([ix("a", &["signer", "counter", "system"], &[])]),
+            json!([]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::Added,
+                "instructions.a.accounts.system",
+                true
+            )]
+        );
+
+        let new = idl(json!([ix("a", &["signer"], &[])]), json!([]), json!([]));
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::Removed,
+                "instructions.a.accounts.counter",
+                true
+            )]
+        );
+
+        let new = idl(
+            json!([ix("a", &["counter", "signer"], &[])]),
+            json!([]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::Reordered,
+                "instructions.a.accounts",
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn account_rename() {
+        let old = idl(
+            json!([]),
+            json!([def("Counter", &[("count", "u64")])]),
+            json!([]),
+        );
+        let new = idl(
+            json!([]),
+            json!([def("Count", &[("count", "u64")])]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::DiscriminatorChanged,
+                "accounts.Count",
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn type_rename() {
+        // Type names are not encoded, meaning renames are reported as removing and adding
+        let old = idl(json!([]), json!([]), json!([def("Data", &[("x", "u64")])]));
+        let new = idl(json!([]), json!([]), json!([def("Info", &[("x", "u64")])]));
+        assert_eq!(
+            changes(&old, &new),
+            [
+                change(ChangeKind::Removed, "types.Data", false),
+                change(ChangeKind::Added, "types.Info", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_type_change() {
+        let old = idl(
+            json!([]),
+            json!([def("Counter", &[("count", "u64")])]),
+            json!([]),
+        );
+
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("count", "i64")])]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(
+                ChangeKind::TypeChanged,
+                "accounts.Counter.count",
+                true
+            )]
+        );
+
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("count", "u128")])]),
+            json!([]),
+        );
+        let report = compare(&old, &new);
+        assert_eq!(report.changes[0].kind, ChangeKind::Resized);
+        assert_eq!(
+            report.changes[0].description,
+            "Type changed from `u64` to `u128` (8 bytes -> 16 bytes)"
+        );
+    }
+
+    #[test]
+    fn field_reorder() {
+        let old = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64"), ("b", "u64")])]),
+            json!([]),
+        );
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("b", "u64"), ("a", "u64")])]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Reordered, "accounts.Counter", true)]
+        );
+    }
+
+    #[test]
+    fn field_removal() {
+        let old = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64"), ("b", "u64"), ("c", "u64")])]),
+            json!([def("Data", &[("a", "u64"), ("b", "u64")])]),
+        );
+
+        // Trailing account fields are ignored during deserialization
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64"), ("b", "u64")])]),
+            json!([def("Data", &[("a", "u64")])]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [
+                change(ChangeKind::Removed, "accounts.Counter.c", false),
+                // Types can be embedded in the middle of accounts
+                change(ChangeKind::Removed, "types.Data.b", true),
+            ]
+        );
+
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64"), ("c", "u64")])]),
+            json!([def("Data", &[("a", "u64"), ("b", "u64")])]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Removed, "accounts.Counter.b", true)]
+        );
+    }
+
+    #[test]
+    fn field_added() {
+        let old = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64")])]),
+            json!([]),
+        );
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("a", "u64"), ("b", "u64")])]),
+            json!([]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Added, "accounts.Counter.b", true)]
+        );
+    }
+
+    #[test]
+    fn enum_variant_append() {
+        let old = idl(
+            json!([]),
+            json!([]),
+            json!([enum_def("State", &["A", "B"])]),
+        );
+        let new = idl(
+            json!([]),
+            json!([]),
+            json!([enum_def("State", &["A", "B", "C"])]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Added, "types.State.C", false)]
+        );
+    }
+
+    #[test]
+    fn enum_variant_insert() {
+        let old = idl(
+            json!([]),
+            json!([]),
+            json!([enum_def("State", &["A", "B"])]),
+        );
+        let new = idl(
+            json!([]),
+            json!([]),
+            json!([enum_def("State", &["A", "C", "B"])]),
+        );
+        assert_eq!(
+            changes(&old, &new),
+            [
+                change(ChangeKind::Reordered, "types.State.B", true),
+                change(ChangeKind::Added, "types.State.C", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_variant_removal() {
+        let old = idl(
+            json!([]),
+            json!([]),
+            json!([enum_def("State", &["A", "B"])]),
+        );
+        let new = idl(json!([]), json!([]), json!([enum_def("State", &["A"])]));
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::Removed, "types.State.B", true)]
+        );
+    }
+
+    #[test]
+    fn definition_added_and_removed() {
+        let old = idl(json!([]), json!([]), json!([]));
+        let new = idl(
+            json!([]),
+            json!([def("Counter", &[("count", "u64")])]),
+            json!([def("Data", &[("x", "u8")])]),
+        );
+        let report = compare(&old, &new);
+        assert!(!report.is_breaking);
+        assert_eq!(
+            changes(&old, &new),
+            [
+                change(ChangeKind::Added, "accounts.Counter", false),
+                change(ChangeKind::Added, "types.Data", false),
+            ]
+        );
+
+        // Existing accounts can't be used after removing the account, unlike types
+        let report = compare(&new, &old);
+        assert!(report.is_breaking);
+        assert_eq!(
+            changes(&new, &old),
+            [
+                change(ChangeKind::Removed, "accounts.Counter", true),
+                change(ChangeKind::Removed, "types.Data", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_kind_change() {
+        let old = idl(json!([]), json!([]), json!([def("Data", &[("x", "u8")])]));
+        let new = idl(json!([]), json!([]), json!([enum_def("Data", &["X"])]));
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::TypeChanged, "types.Data", true)]
+        );
+    }
+
+    #[test]
+    fn alias_change() {
+        let alias =
+            |ty: &str| json!({ "name": "Amount", "type": { "kind": "alias", "value": ty } });
+        let old = idl(json!([]), json!([]), json!([alias("u64")]));
+
+        let new = idl(json!([]), json!([]), json!([alias("i64")]));
+        assert_eq!(
+            changes(&old, &new),
+            [change(ChangeKind::TypeChanged, "types.Amount", true)]
+        );
+
+        let new = idl(json!([]), json!([]), json!([alias("u32")]));
+        let report = compare(&old, &new);
+        assert_eq!(report.changes[0].kind, ChangeKind::Resized);
+        assert_eq!(
+            report.changes[0].description,
+            "Aliased type changed from `u64` to `u32` (8 bytes -> 4 bytes)"
+        );
+    }
+}
@@ -3,11 +3,15 @@ mod error;
 mod sandbox;
 
 pub mod db;
+pub mod idl;
 pub mod log;
 pub mod package;
 pub mod program;
 pub mod utils;
 
-pub use config::Config;
+pub use config::{BuildConfig, BundleConfig, Config, SandboxConfig, ShareConfig, ToolchainConfig};
 pub use error::{Error, Result};
-pub use sandbox::Sandbox;
+pub use sandbox::{
+    Backend as SandboxBackend, CommandOutput as SandboxCommandOutput, Event as SandboxEvent,
+    Output as SandboxOutput, Pool as SandboxPool, Sandbox,
+};
@@ -2,64 +2,145 @@ mod middlewares;
 mod routes;
 #[cfg(feature = "unstable")]
 mod setup;
+#[cfg(test)]
+mod tests;
 
 use std::net::{Ipv4Addr, SocketAddr};
 
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{get, post},
     Router,
 };
 use solpg_server::{
     db,
-    log::{self, info},
+    log::{self, error, info, warn},
     Config,
 };
-use tokio::net::TcpListener;
+use tokio::{
+    net::TcpListener,
+    signal::{
+        self,
+        unix::{self, SignalKind},
+    },
+};
 
 use self::{middlewares::*, routes::*};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = Config::from_env();
+    let config = Config::load()?;
     log::init(config.verbose);
     info!("Config loaded: {config:#?}");
+    Config::set_current(config.clone());
 
     #[cfg(feature = "unstable")]
     setup::setup().await?;
 
-    db::init(&config.db_uri, config.db_name).await?;
+    db::init(&config.db_uri, config.db_name.clone()).await?;
     info!("DB initialized");
 
+    let build_state = BuildState::new(config.build.concurrency);
+    let bundle_state = cfg!(feature = "unstable").then(|| BundleState::new(&config));
+
+    // Reload the config without dropping the connections
+    tokio::spawn(reload_on_sighup(build_state.clone(), bundle_state.clone()));
+
+    let app = app(build_state, bundle_state.clone());
+
+    let addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, config.port));
+    let listener = TcpListener::bind(addr).await?;
+    info!("Listening on {addr}");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    // Pooled sandbox containers would otherwise keep running after the server exits
+    if let Some(bundle_state) = bundle_state {
+        bundle_state.close().await;
+    }
+
+    Ok(())
+}
+
+/// Create the router of all routes with the given route states.
+///
+/// Unstable routes are only included if `bundle_state` exists.
+fn app(build_state: BuildState, bundle_state: Option<BundleState>) -> Router {
     let stable_routes = Router::new()
         .route(
             "/build",
-            post(build).with_state(BuildState::new(config.build_concurrency)),
+            payload_limit(post(build).with_state(build_state), |c| {
+                c.build.payload_limit
+            }),
+        )
+        .route("/deploy/{uuid}", payload_limit(get(deploy), |_| None))
+        .route("/openapi.json", payload_limit(get(openapi), |_| None))
+        .route("/share/{id}", payload_limit(get(share_get), |_| None))
+        .route(
+            "/new",
+            payload_limit(post(share_new), |c| c.share.payload_limit),
         )
-        .route("/deploy/{uuid}", get(deploy))
-        .route("/share/{id}", get(share_get))
-        .route("/new", post(share_new));
-
-    let unstable_routes = if cfg!(feature = "unstable") {
-        Router::new().route("/bundle", post(bundle))
-    } else {
-        Router::new()
+        .route("/toolchains", payload_limit(get(toolchains), |_| None));
+
+    let unstable_routes = match bundle_state {
+        Some(bundle_state) => Router::new().route(
+            "/bundle",
+            payload_limit(post(bundle).with_state(bundle_state), |c| {
+                c.bundle.payload_limit
+            }),
+        ),
+        None => Router::new(),
     };
 
-    let app = Router::new()
+    Router::new()
         .merge(stable_routes)
         .nest("/unstable", unstable_routes)
         .layer(compression())
-        .layer(payload_limit(config.payload_limit))
-        .layer(cors(config.client_urls))
-        .layer(middleware::from_fn(log));
+        // Payload limits are handled per route
+        .layer(DefaultBodyLimit::disable())
+        .layer(cors())
+        .layer(middleware::from_fn(log))
+}
 
-    let addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, config.port));
-    let listener = TcpListener::bind(addr).await?;
-    info!("Listening on {addr}");
+/// Reload the config each time `SIGHUP` is received.
+///
+/// Malformed configs are rejected and the current config is kept.
+async fn reload_on_sighup(build_state: BuildState, bundle_state: Option<BundleState>) {
+    let mut sighup = unix::signal(SignalKind::hangup()).expect("Failed to listen for `SIGHUP`");
+    while sighup.recv().await.is_some() {
+        let new = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Failed to reload config: {e}");
+                continue;
+            }
+        };
 
-    axum::serve(listener, app).await?;
+        let old = Config::current();
+        for name in old.restart_required(&new) {
+            warn!("Changing `{name}` requires a restart");
+        }
+        build_state.reload(&old, &new);
+        if let Some(bundle_state) = &bundle_state {
+            bundle_state.reload(&old, &new);
+        }
 
-    Ok(())
+        info!("Config reloaded: {new:#?}");
+        Config::set_current(new);
+    }
+}
+
+/// Wait for a shutdown signal (`SIGINT` or `SIGTERM`).
+async fn shutdown_signal() {
+    let mut sigterm =
+        unix::signal(SignalKind::terminate()).expect("Failed to listen for `SIGTERM`");
+    tokio::select! {
+        _ = signal::ctrl_c() => {},
+        _ = sigterm.recv() => {},
+    }
+    info!("Shutting down");
 }
@@ -1,8 +1,14 @@
-use tower_http::compression::CompressionLayer;
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate},
+    CompressionLayer, DefaultPredicate,
+};
 
 /// Create a compression middleware.
 ///
-/// The compression algorithm used will be based on the request headers.
-pub fn compression() -> CompressionLayer {
-    CompressionLayer::new()
+/// The compression algorithm used will be based on the request headers. Responses that are
+/// already compressed (e.g. bundle archives) are skipped.
+pub fn compression() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        DefaultPredicate::new().and(NotForContentType::const_new("application/gzip")),
+    )
 }
@@ -1,18 +1,22 @@
 use std::time::Duration;
 
-use axum::http::{header, Method};
+use axum::http::{header, HeaderName, Method};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 
+use solpg_server::Config;
+use solpg_server_types::LockFormat;
+
 use crate::log::error;
 
 /// Create a CORS middleware.
 ///
-/// Request origins other than `client_urls` are not allowed.
-pub fn cors(client_urls: Vec<String>) -> CorsLayer {
+/// Request origins other than the current config's `client_urls` are not allowed.
+pub fn cors() -> CorsLayer {
     CorsLayer::new()
-        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+        .allow_origin(AllowOrigin::predicate(|origin, _| {
             let origin_bytes = origin.as_bytes();
-            let allowed = client_urls
+            let allowed = Config::current()
+                .client_urls
                 .iter()
                 .any(|url| origin_bytes.starts_with(url.as_bytes()));
 
@@ -27,6 +31,9 @@ pub fn cors(client_urls: Vec<String>) -> CorsLayer {
             allowed
         }))
         .allow_methods([Method::GET, Method::POST])
-        .allow_headers([header::CONTENT_TYPE])
+        // `If-None-Match` and `ETag` are used for the bundle cache, `Accept` for the bundle
+        // progress events and `X-Lock-Format` for the lock file of the bundle
+        .allow_headers([header::CONTENT_TYPE, header::IF_NONE_MATCH, header::ACCEPT])
+        .expose_headers([header::ETAG, HeaderName::from_static(LockFormat::HEADER)])
         .max_age(Duration::from_secs(600))
 }
@@ -1,8 +1,39 @@
-use tower_http::limit::RequestBodyLimitLayer;
+use axum::{
+    body::{self, Body},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+};
+use solpg_server::Config;
 
-/// Create a payload limit middleware.
+/// Getter of the route specific payload limit from the config
+pub type RouteLimit = fn(&Config) -> Option<usize>;
+
+/// Add a payload limit middleware to the route.
 ///
-/// A response with status code 413(Payload Too Large) is returned for any payload above `limit`.
-pub fn payload_limit(limit: usize) -> RequestBodyLimitLayer {
-    RequestBodyLimitLayer::new(limit)
+/// A response with status code 413(Payload Too Large) is returned for any payload above the
+/// route's limit, or the global `payload_limit` if the route doesn't have a specific limit. The
+/// limit is read from the current config on each request in order to support config reloads.
+pub fn payload_limit<S>(route: MethodRouter<S>, limit: RouteLimit) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.layer(middleware::from_fn_with_state(limit, limit_payload))
+}
+
+/// Buffer the request body up to the payload limit.
+async fn limit_payload(State(limit): State<RouteLimit>, req: Request, next: Next) -> Response {
+    let config = Config::current();
+    let limit = limit(&config).unwrap_or(config.payload_limit);
+
+    let (parts, body) = req.into_parts();
+    match body::to_bytes(body, limit).await {
+        Ok(bytes) => {
+            next.run(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        }
+        Err(_) => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    }
 }
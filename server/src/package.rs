@@ -1,29 +1,129 @@
-// TODO: All packages and versions from NPM
-// TODO: Switch to `pnpm` without shared cache (simpler transition if we decide to use shared cache)
 // TODO: Use shared cache with `pnpm`? (shared cache is better for speed but worse for security)
 // TODO: Check if bundling client-side is feasible with a tool like `esbuild-wasm`?
 
-use std::path::{Path, PathBuf};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+use solpg_server_types::LockFormat;
 
 /// Packages directory
 pub const PACKAGES_DIR: &str = "packages";
 
+/// Process input directory
+const IN_DIR: &str = "in";
+
 /// Process output directory
 const OUT_DIR: &str = "out";
 
+/// Bundle cache directory
+const CACHE_DIR: &str = "cache";
+
 /// `package.json`
 pub const MANIFEST_FILE: &str = "package.json";
 
-/// Path to the lock file (currently only `yarn`)
-pub const LOCK_FILE: &str = "yarn.lock";
+/// Process output archive that includes all the other output files
+pub const ARCHIVE_FILE: &str = "bundle.tar.gz";
 
 /// Bundled files
 pub const BUNDLE_FILE: &str = "bundle.json";
 
+/// Bundled modules (package name to module name and entry file)
+pub const MODULES_FILE: &str = "modules.json";
+
 /// Type declarations
 pub const TYPES_FILE: &str = "types.json";
 
+/// Type dependencies
+pub const DEPENDENCIES_FILE: &str = "dependencies.json";
+
+/// Get the path to the process input directory.
+pub fn get_in_path() -> PathBuf {
+    Path::new(PACKAGES_DIR).join(IN_DIR)
+}
+
 /// Get the path to the process output directory.
 pub fn get_out_path() -> PathBuf {
     Path::new(PACKAGES_DIR).join(OUT_DIR)
 }
+
+/// Get the path to the bundle cache directory.
+pub fn get_cache_path() -> PathBuf {
+    Path::new(PACKAGES_DIR).join(CACHE_DIR)
+}
+
+/// JS package manager to install the packages with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackageManager {
+    #[default]
+    Yarn,
+    Pnpm,
+}
+
+impl PackageManager {
+    /// Get the lock file format of the package manager.
+    pub fn lock_format(&self) -> LockFormat {
+        match self {
+            Self::Yarn => LockFormat::Yarn,
+            Self::Pnpm => LockFormat::Pnpm,
+        }
+    }
+
+    /// Get the lock file name of the package manager.
+    pub fn lock_file(&self) -> &'static str {
+        self.lock_format().file_name()
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yarn => write!(f, "yarn"),
+            Self::Pnpm => write!(f, "pnpm"),
+        }
+    }
+}
+
+impl FromStr for PackageManager {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yarn" => Ok(Self::Yarn),
+            "pnpm" => Ok(Self::Pnpm),
+            _ => Err(anyhow!("Unknown package manager: `{s}`")),
+        }
+    }
+}
+
+/// JS bundler to bundle the packages with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bundler {
+    #[default]
+    Webpack,
+    Esbuild,
+}
+
+impl fmt::Display for Bundler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Webpack => write!(f, "webpack"),
+            Self::Esbuild => write!(f, "esbuild"),
+        }
+    }
+}
+
+impl FromStr for Bundler {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "webpack" => Ok(Self::Webpack),
+            "esbuild" => Ok(Self::Esbuild),
+            _ => Err(anyhow!("Unknown bundler: `{s}`")),
+        }
+    }
+}
@@ -1,24 +1,72 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
     fs::{self, DirEntry},
     io,
     path::{Path, PathBuf},
     process::Command,
+    time::Instant,
 };
 
 use anyhow::{anyhow, Result};
+use flate2::{write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use solpg_server::{
-    package::{get_out_path, BUNDLE_FILE, LOCK_FILE, MANIFEST_FILE, PACKAGES_DIR, TYPES_FILE},
+    package::{
+        get_in_path, get_out_path, Bundler, PackageManager, ARCHIVE_FILE, BUNDLE_FILE,
+        DEPENDENCIES_FILE, MANIFEST_FILE, MODULES_FILE, PACKAGES_DIR, TYPES_FILE,
+    },
     utils::Files,
 };
 
-// TODO: Make the process output a single compressed archive with all the files in it
 fn main() -> Result<()> {
-    let manifest = install_packages()?;
-    generate_bundle(&manifest)?;
-    generate_types(&manifest)?;
-    Ok(())
+    let mut args = env::args().skip(1);
+    let cmd = args.next();
+    let mut opts = HashMap::new();
+    while let Some(arg) = args.next() {
+        let key = arg
+            .strip_prefix("--")
+            .ok_or_else(|| anyhow!("Unexpected argument: `{arg}`"))?
+            .to_owned();
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("Missing value for `--{key}`"))?;
+        opts.insert(key, value);
+    }
+    let get_opt = |key: &str| {
+        opts.get(key)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("Missing option: `--{key}`"))
+    };
+
+    match cmd.as_deref() {
+        Some("install") => {
+            install_packages(get_opt("registry")?, get_opt("package-manager")?.parse()?)
+        }
+        Some("build") => {
+            let pm = get_opt("package-manager")?.parse::<PackageManager>()?;
+            let out_path = get_out_path();
+            let manifest = fs::read(out_path.join(MANIFEST_FILE))?;
+            let lock = fs::read(out_path.join(pm.lock_file()))?;
+            let parsed_manifest = serde_json::from_slice(&manifest)?;
+            let (bundle, modules) =
+                generate_bundle(&parsed_manifest, get_opt("bundler")?.parse()?, pm)?;
+            let (types, deps) = generate_types(&parsed_manifest)?;
+            create_archive([
+                (BUNDLE_FILE, serde_json::to_vec(&bundle)?),
+                (MODULES_FILE, serde_json::to_vec(&modules)?),
+                (TYPES_FILE, serde_json::to_vec(&types)?),
+                (DEPENDENCIES_FILE, serde_json::to_vec(&deps)?),
+                (MANIFEST_FILE, manifest),
+                (pm.lock_file(), lock),
+            ])
+        }
+        _ => Err(anyhow!(
+            "Usage: bundle install --registry <URL> --package-manager <yarn|pnpm>\n       \
+             bundle build --bundler <webpack|esbuild> --package-manager <yarn|pnpm>"
+        )),
+    }
 }
 
 /// `package.json` manifest
@@ -53,35 +101,245 @@ impl Manifest {
 /// `package.json` dependencies map
 type Dependencies = HashMap<String, String>;
 
-/// Install packages.
-fn install_packages() -> Result<Manifest> {
-    // TODO: Install
-    // TODO: Validate?
-
+/// Packages that are not allowed to be installed.
+///
+/// Entries without a version deny all versions of the package.
+const DENYLIST: &[&str] = &[
+    // Compromised releases
+    "@solana/web3.js@1.95.6",
+    "@solana/web3.js@1.95.7",
+    "event-stream@3.3.6",
+    "flatmap-stream",
+    "ua-parser-js@0.7.29",
+    "ua-parser-js@0.8.0",
+    "ua-parser-js@1.0.0",
+    "coa@2.0.3",
+    "coa@2.0.4",
+    "coa@2.1.1",
+    "coa@2.1.3",
+    "coa@3.0.1",
+    "coa@3.1.3",
+    "rc@1.2.9",
+    "rc@1.3.9",
+    "rc@2.3.9",
+];
+
+/// Install the packages of the input manifest from the given NPM `registry`.
+///
+/// The input manifest is merged into the base manifest because the base dependencies are required
+/// for bundling (e.g. `webpack` and Node polyfills). Runtime dependencies of the input manifest
+/// take precedence, but the build tooling (base `devDependencies`) is always kept as is.
+fn install_packages(registry: &str, pm: PackageManager) -> Result<()> {
     let packages_path = Path::new(PACKAGES_DIR);
+    let in_path = get_in_path();
     let out_path = get_out_path();
     fs::create_dir_all(&out_path)?;
 
-    let manifest_path = packages_path.join(MANIFEST_FILE);
-    fs::copy(&manifest_path, out_path.join(MANIFEST_FILE))?;
+    // The base packages of the image are read-only, install into a writable copy of them
+    if fs::exists(BASE_PACKAGES_DIR)? {
+        let status = Command::new("cp")
+            .arg("-R")
+            .arg(Path::new(BASE_PACKAGES_DIR).join("."))
+            .arg(packages_path)
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("Failed to copy the base packages"));
+        }
+    }
+
+    // Validate the input manifest
+    let manifest_path = in_path.join(MANIFEST_FILE);
+    let manifest = fs::read(&manifest_path).map(|b| serde_json::from_slice::<Manifest>(&b))??;
+    for (name, spec) in manifest.get_all_dependencies() {
+        validate_dependency(&name, &spec)?;
+    }
 
-    let lock_file_path = packages_path.join(LOCK_FILE);
-    fs::copy(lock_file_path, out_path.join(LOCK_FILE))?;
+    // Merge the input manifest into the base manifest
+    let base_manifest_path = packages_path.join(MANIFEST_FILE);
+    let mut base_manifest =
+        fs::read(&base_manifest_path).map(|b| serde_json::from_slice::<Value>(&b))??;
+    let deps = [
+        ("dependencies", &manifest.dependencies),
+        ("devDependencies", &manifest.dev_dependencies),
+        ("peerDependencies", &manifest.peer_dependencies),
+        ("optionalDependencies", &manifest.optional_dependencies),
+    ];
+    for (key, deps) in deps {
+        let base_deps = base_manifest
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("Invalid base manifest"))?
+            .entry(key)
+            .or_insert_with(|| Value::Object(Default::default()))
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("Invalid base manifest `{key}`"))?;
+        for (name, spec) in deps {
+            if key != "devDependencies" || !base_deps.contains_key(name) {
+                base_deps.insert(name.to_owned(), Value::String(spec.to_owned()));
+            }
+        }
+    }
+    fs::write(
+        &base_manifest_path,
+        serde_json::to_string_pretty(&base_manifest)?,
+    )?;
 
-    fs::read(manifest_path)
-        .map(|b| serde_json::from_slice(&b))?
-        .map_err(Into::into)
+    // Prefer the input lock file (if it exists) over the base lock file
+    let lock_file_path = packages_path.join(pm.lock_file());
+    match fs::copy(in_path.join(pm.lock_file()), &lock_file_path) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(anyhow!("Failed to copy lock file: {e}")),
+    }
+
+    let mut cmd = Command::new(pm.to_string());
+    cmd.current_dir(PACKAGES_DIR)
+        .arg("install")
+        .arg("--ignore-scripts")
+        .arg("--registry")
+        .arg(registry);
+    match pm {
+        PackageManager::Yarn => cmd.arg("--non-interactive"),
+        // Hoist all packages in order to be able to find the transitive type declarations
+        PackageManager::Pnpm => cmd.arg("--config.node-linker=hoisted"),
+    };
+
+    let start = Instant::now();
+    let status = cmd.status()?;
+    eprintln!("Installed packages with {pm} in {:?}", start.elapsed());
+    if !status.success() {
+        return Err(anyhow!("Failed to install packages"));
+    }
+
+    // Transitive dependencies can only be checked after the resolution
+    let lock = fs::read_to_string(&lock_file_path)?;
+    for (name, version) in get_locked_packages(&lock, pm) {
+        if is_denied(name, Some(version)) {
+            return Err(anyhow!("Denied package: `{name}@{version}`"));
+        }
+    }
+
+    fs::copy(manifest_path, out_path.join(MANIFEST_FILE))?;
+    fs::write(out_path.join(pm.lock_file()), lock)?;
+
+    Ok(())
+}
+
+/// Validate the dependency from its `name` and version specifier.
+///
+/// Only packages from the registry are allowed because other sources (e.g. Git, tarball URLs,
+/// local paths) would bypass the registry mirror.
+fn validate_dependency(name: &str, spec: &str) -> Result<()> {
+    let spec = spec.trim();
+    let (name, version) = match spec.strip_prefix("npm:") {
+        Some(alias) => split_package_spec(alias),
+        None if spec.contains([':', '/']) => {
+            return Err(anyhow!(
+                "Only registry dependencies are allowed: `{name}` (`{spec}`)"
+            ))
+        }
+        None => (name, Some(spec)),
+    };
+    if is_denied(name, version) {
+        return Err(anyhow!("Denied package: `{name}` (`{spec}`)"));
+    }
+
+    Ok(())
+}
+
+/// Get whether the given package is in the [`DENYLIST`].
+///
+/// `version` is compared as is, meaning version ranges are only checked after the resolution.
+fn is_denied(name: &str, version: Option<&str>) -> bool {
+    DENYLIST
+        .iter()
+        .map(|entry| split_package_spec(entry))
+        .any(|denied| match denied {
+            (denied_name, None) => denied_name == name,
+            (denied_name, denied_version) => denied_name == name && denied_version == version,
+        })
+}
+
+/// Get the resolved `(name, version)` pairs from the lock file.
+fn get_locked_packages(lock: &str, pm: PackageManager) -> Vec<(&str, &str)> {
+    match pm {
+        PackageManager::Yarn => {
+            let mut pkgs = vec![];
+            let mut names = vec![];
+            for line in lock.lines() {
+                if !line.starts_with([' ', '#']) && line.ends_with(':') {
+                    names = line
+                        .trim_end_matches(':')
+                        .split(", ")
+                        .map(|spec| split_package_spec(spec.trim_matches('"')).0)
+                        .collect();
+                } else if let Some(version) = line.trim().strip_prefix("version ") {
+                    let version = version.trim_matches('"');
+                    pkgs.extend(names.drain(..).map(|name| (name, version)));
+                }
+            }
+
+            pkgs
+        }
+        // Package keys are the only 2-space indented keys (e.g. `  '@scope/name@1.0.0(a@1.0.0)':`)
+        PackageManager::Pnpm => lock
+            .lines()
+            .filter(|line| line.starts_with("  ") && !line.starts_with("   "))
+            .filter_map(|line| line.trim().strip_suffix(':'))
+            .map(|spec| spec.trim_matches('\'').trim_start_matches('/'))
+            .map(|spec| spec.split('(').next().unwrap_or(spec))
+            .filter_map(|spec| match split_package_spec(spec) {
+                (name, Some(version)) => Some((name, version)),
+                _ => None,
+            })
+            .collect(),
+    }
 }
 
-/// Generate an ESM bundle.
-fn generate_bundle(manifest: &Manifest) -> Result<()> {
+/// Split the package spec (e.g. `@scope/name@^1.0.0`) into its name and version.
+fn split_package_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.get(1..).and_then(|s| s.find('@')) {
+        Some(index) => (&spec[..index + 1], Some(&spec[index + 2..])),
+        None => (spec, None),
+    }
+}
+
+/// Create a single compressed archive (`.tar.gz`) from the given files in the output directory.
+fn create_archive<const N: usize>(files: [(&str, Vec<u8>); N]) -> Result<()> {
+    let archive = fs::File::create(get_out_path().join(ARCHIVE_FILE))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, path, content.as_slice())?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Generate an ESM bundle and the module mapping of the bundled packages.
+fn generate_bundle(
+    manifest: &Manifest,
+    bundler: Bundler,
+    pm: PackageManager,
+) -> Result<(Files, Modules)> {
     // Create a separate directory for each package
     let packages_path = Path::new(PACKAGES_DIR);
     let src_path = packages_path.join(SRC_DIR);
-    let mut entries = vec![];
+    let mut entries = BTreeMap::new();
+    let mut modules = Modules::new();
     // TODO: Other deps (`optionalDependencies`...)
-    for pkg in manifest.dependencies.keys() {
-        let module = to_module_name(pkg);
+    for pkg in manifest.dependencies.keys().collect::<BTreeSet<_>>() {
+        let module = to_module_name(pkg, &modules);
+        modules.insert(
+            pkg.to_owned(),
+            Module {
+                name: module.to_owned(),
+                entry: format!("{pkg}/{BUNDLE_ENTRY_FILE}"),
+            },
+        );
+
         let pkg_path = src_path.join(pkg);
         let entry_path = pkg_path.join("index.js");
         fs::create_dir_all(&pkg_path)?;
@@ -89,29 +347,40 @@ fn generate_bundle(manifest: &Manifest) -> Result<()> {
             &entry_path,
             format!(r#"import * as {module} from "{pkg}"; export {{ {module} }}"#),
         )?;
-        entries.push(format!(
-            r#""{pkg}": {:?}"#,
+        entries.insert(
+            pkg,
             entry_path
                 .strip_prefix(PACKAGES_DIR)
-                .map(|entry| Path::new(".").join(entry))?
-        ));
+                .map(|entry| Path::new(".").join(entry))?,
+        );
     }
 
-    // Add entries to the webpack config
-    let webpack_cfg_path = packages_path.join(WEBPACK_CONFIG_FILE);
-    let webpack_cfg = fs::read_to_string(&webpack_cfg_path)?
-        .replace("/* <DYNAMIC_ENTRIES> */", &entries.join(","));
-    fs::write(webpack_cfg_path, webpack_cfg)?;
+    // Bundler configs read the entries from this file
+    fs::write(
+        packages_path.join(ENTRIES_FILE),
+        serde_json::to_string_pretty(&entries)?,
+    )?;
 
-    // TODO: Test `webpack` alternatives for faster builds
-    let status = Command::new("yarn")
-        .current_dir(PACKAGES_DIR)
-        .arg("--offline")
-        .arg("--ignore-scripts")
-        .arg("run")
-        .arg("webpack")
-        .status()?;
+    let mut cmd = match bundler {
+        Bundler::Webpack => {
+            let mut cmd = Command::new(pm.to_string());
+            match pm {
+                PackageManager::Yarn => cmd.arg("--offline").arg("--ignore-scripts").arg("run"),
+                PackageManager::Pnpm => cmd.arg("exec"),
+            };
+            cmd.arg("webpack");
+            cmd
+        }
+        Bundler::Esbuild => {
+            let mut cmd = Command::new("node");
+            cmd.arg(ESBUILD_CONFIG_FILE);
+            cmd
+        }
+    };
 
+    let start = Instant::now();
+    let status = cmd.current_dir(PACKAGES_DIR).status()?;
+    eprintln!("Bundled packages with {bundler} in {:?}", start.elapsed());
     if !status.success() {
         return Err(anyhow!("Failed to bundle"));
     }
@@ -123,25 +392,98 @@ fn generate_bundle(manifest: &Manifest) -> Result<()> {
             .map(|ext| ext == "js")
             .unwrap_or_default()
     })?;
-    fs::write(
-        get_out_path().join(BUNDLE_FILE),
-        serde_json::to_string(&files)?,
-    )?;
+    Ok((files, modules))
+}
 
-    Ok(())
+/// Bundled modules (package name to its module)
+type Modules = BTreeMap<String, Module>;
+
+/// Bundled module
+#[derive(Serialize)]
+struct Module {
+    /// Module name (a valid JS variable name that is unique among all modules)
+    name: String,
+    /// Path of the entry file of the module in the bundle files
+    entry: String,
 }
 
-/// Convert the given package name to a module name.
+/// Convert the given package name to a module name that doesn't exist in `modules`.
 ///
-/// Module names must be valid JS variable names.
-///
-/// NOTE: This must be kept in sync with the client.
-//
-// TODO: It might be better to include a mapping of package names to module names as a separate file
-fn to_module_name(pkg_name: &str) -> String {
-    pkg_name.replace(['@', '/', '-', '_', '.'], "")
+/// Module names must be valid JS variable names. Packages that result in the same name after
+/// removing the invalid characters (e.g. `foo-bar` and `foobar`), or in a reserved word (e.g.
+/// `delete`), get a numeric suffix.
+fn to_module_name(pkg_name: &str, modules: &Modules) -> String {
+    let name = pkg_name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '$', "");
+    let name = if name.starts_with(|c: char| !c.is_ascii_alphabetic() && c != '$') {
+        format!("_{name}")
+    } else {
+        name
+    };
+
+    (0..)
+        .map(|i| match i {
+            0 => name.to_owned(),
+            _ => format!("{name}{i}"),
+        })
+        .find(|name| {
+            !RESERVED_WORDS.contains(&name.as_str())
+                && !modules.values().any(|module| &module.name == name)
+        })
+        .expect("Always finds an unused name")
 }
 
+/// JS words that can't be used as variable names (including the strict mode ones)
+const RESERVED_WORDS: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
 /// Get the output files from the build directory.
 fn get_output_files<F>(filter: F) -> Result<Files>
 where
@@ -178,26 +520,33 @@ where
     Ok(())
 }
 
-/// Generate type declaration files.
-fn generate_types(manifest: &Manifest) -> Result<()> {
+/// Generate type declaration files and the dependency graph of the type declarations.
+fn generate_types(manifest: &Manifest) -> Result<(Files, DependencyGraph)> {
     for dep in manifest.get_all_dependencies().keys() {
         if let Err(e) = generate_package_types(dep) {
             eprintln!("Failed to generate types for `{dep}`: {e}")
         }
     }
 
-    let files = get_output_files(|entry| {
-        let file_name = entry.file_name();
-        file_name == TYPES_FILE || file_name == DEPENDENCIES_FILE
-    })?;
-    fs::write(
-        get_out_path().join(TYPES_FILE),
-        serde_json::to_string(&files)?,
-    )?;
+    let types = get_output_files(|entry| entry.file_name() == TYPES_FILE)?;
+    let deps = get_output_files(|entry| entry.file_name() == DEPENDENCIES_FILE)?
+        .into_iter()
+        .map(|(path, content)| {
+            let pkg = path
+                .strip_suffix(DEPENDENCIES_FILE)
+                .map(|pkg| pkg.trim_end_matches('/').to_owned())
+                .ok_or_else(|| anyhow!("Invalid dependencies path: {path}"))?;
+            let deps = serde_json::from_str(&content)?;
+            Ok((pkg, deps))
+        })
+        .collect::<Result<_>>()?;
 
-    Ok(())
+    Ok((types, deps))
 }
 
+/// Type declaration dependency graph (package name to its dependencies)
+type DependencyGraph = BTreeMap<String, Vec<String>>;
+
 /// Port of [`generate-packages.mjs`] (without the Monaco editor parts).
 ///
 /// [`generate-packages.mjs`]: https://github.com/solana-playground/solana-playground/blob/7d9f365a5009fd65aaa388e85bc541e5f4f51ae9/client/scripts/generate-packages.mjs
@@ -334,11 +683,21 @@ fn convert_type_files(files: Vec<(PathBuf, String)>) -> anyhow::Result<Files> {
         .collect()
 }
 
-/// Build directory (`webpack`)
+/// Base packages directory of the image (with the base dependencies already installed), which is
+/// copied to [`PACKAGES_DIR`] before installing the packages
+const BASE_PACKAGES_DIR: &str = "/opt/solpg/packages";
+
+/// Build directory
 const BUILD_DIR: &str = "dist";
 
-/// `weppack` config file
-const WEBPACK_CONFIG_FILE: &str = "webpack.config.js";
+/// Bundle entry file of each package in the build directory
+const BUNDLE_ENTRY_FILE: &str = "bundle.js";
+
+/// Bundle entries (package name to entry file path)
+const ENTRIES_FILE: &str = "entries.json";
+
+/// `esbuild` config file
+const ESBUILD_CONFIG_FILE: &str = "esbuild.config.mjs";
 
 /// The default directory of where the JS packages are stored
 const NODE_MODULES: &str = "node_modules";
@@ -346,10 +705,46 @@ const NODE_MODULES: &str = "node_modules";
 /// Source directory
 const SRC_DIR: &str = "src";
 
-/// Type dependencies
-const DEPENDENCIES_FILE: &str = "dependencies.json";
-
 /// Get the path to the directory that stores the `webpack` build directory.
 fn get_build_path() -> PathBuf {
     Path::new(PACKAGES_DIR).join(BUILD_DIR)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the module names of the given packages in order.
+    fn module_names(pkg_names: &[&str]) -> Vec<String> {
+        let mut modules = Modules::new();
+        for pkg_name in pkg_names {
+            let name = to_module_name(pkg_name, &modules);
+            let module = Module {
+                name,
+                entry: String::new(),
+            };
+            modules.insert(pkg_name.to_string(), module);
+        }
+
+        pkg_names
+            .iter()
+            .map(|pkg_name| modules[*pkg_name].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn module_name() {
+        assert_eq!(
+            module_names(&["@solana/web3.js", "bn.js", "4bytes", "$"]),
+            ["solanaweb3js", "bnjs", "_4bytes", "$"]
+        );
+        assert_eq!(
+            module_names(&["foo-bar", "foobar", "foo.bar"]),
+            ["foobar", "foobar1", "foobar2"]
+        );
+        assert_eq!(
+            module_names(&["delete", "new", "class", "default", "import", "de-lete"]),
+            ["delete1", "new1", "class1", "default1", "import1", "delete2"]
+        );
+    }
+}
@@ -1,4 +1,11 @@
-use std::{fs, io, path::Path, process::Command, sync::LazyLock};
+use std::{
+    collections::HashMap,
+    env::{self, join_paths, split_paths},
+    fs, io, iter,
+    path::Path,
+    process::Command,
+    sync::LazyLock,
+};
 
 use anchor_syn::idl::{parse::file::parse as parse_idl, types::Idl};
 use anyhow::anyhow;
@@ -7,11 +14,15 @@ use regex::Regex;
 use crate::{
     log::{info, warn},
     utils::Files,
+    ToolchainConfig,
 };
 
 /// Directory name of where the programs are stored
 const PROGRAMS_DIR: &str = "programs";
 
+/// File name of the program binary
+const BINARY_FILE: &str = "solpg.so";
+
 /// Maximum amount of files to pass to the [`build`] function
 const MAX_FILE_AMOUNT: usize = 64;
 
@@ -21,6 +32,12 @@ const MAX_PATH_LEN: usize = 128;
 /// Max program build output stderr length
 const MAX_STDERR_LEN: usize = 1024 * 1024 * 1024;
 
+/// Anchor version of the IDL generation (`anchor-syn`).
+///
+/// The IDL format and the parsing of the programs change between Anchor versions, meaning the IDL
+/// is only generated for the toolchains with this Anchor version.
+const IDL_ANCHOR_VERSION: &str = "0.29";
+
 /// Build the program from the given program name and files.
 ///
 /// `program_name` is only being used as the directory name of the program and it doesn't have an
@@ -29,11 +46,16 @@ const MAX_STDERR_LEN: usize = 1024 * 1024 * 1024;
 /// Only Rust source files starting with `/src` are allowed to be passed in, an error is returned
 /// otherwise.
 ///
+/// The program is built with the manifest and the Solana binaries of the given `toolchain`. The
+/// IDL is generated with the server's Anchor version (`anchor-syn` 0.29), and it's not generated
+/// for toolchains with a different Anchor version.
+///
 /// NOTE: This function doesn't return an error in the case of a compiler error.
 pub fn build(
     concurrency_id: usize,
     program_name: &str,
     files: &Files,
+    toolchain: &ToolchainConfig,
     seeds_feature: bool,
     no_docs: bool,
     safety_checks: bool,
@@ -60,25 +82,31 @@ pub fn build(
     }
 
     // Copy `Cargo.*` files into a separate directory (only once)
-    let concurrency_path = Path::new(PROGRAMS_DIR).join(concurrency_id.to_string());
+    //
+    // Each toolchain has its own concurrency directories in order to not share build artifacts
+    let toolchain_path = &toolchain.dir;
+    let concurrency_path = toolchain_path.join(concurrency_id.to_string());
     let concurrency_ready_path = concurrency_path.join("ready");
     if !fs::exists(&concurrency_ready_path)? {
-        info!("Initializing concurrency id {concurrency_id}");
+        info!("Initializing concurrency id {concurrency_id} in {toolchain_path:?}");
         fs::create_dir_all(&concurrency_path)?;
         fs::copy(
-            Path::new(PROGRAMS_DIR).join("Cargo.toml"),
+            toolchain_path.join("Cargo.toml"),
             concurrency_path.join("Cargo.toml"),
         )?;
         fs::copy(
-            Path::new(PROGRAMS_DIR).join("Cargo.lock"),
+            toolchain_path.join("Cargo.lock"),
             concurrency_path.join("Cargo.lock"),
         )?;
         fs::write(concurrency_ready_path, [])?;
-        info!("Initialized concurrency id {concurrency_id}");
+        info!("Initialized concurrency id {concurrency_id} in {toolchain_path:?}");
     }
 
     // Remove existing files
     //
+    // The binary of the previous build is also removed in order to not return a stale binary if
+    // the build fails.
+    //
     // TODO: Compare with existing files and only remove the unused ones instead of removing all
     let program_path = Path::new(PROGRAMS_DIR).join(program_name);
     if let Err(e) = fs::remove_dir_all(program_path.join("src")) {
@@ -86,6 +114,11 @@ pub fn build(
             return Err(anyhow!("Failed to remove existing files: {e}"));
         }
     };
+    if let Err(e) = fs::remove_file(program_path.join(BINARY_FILE)) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(anyhow!("Failed to remove existing binary: {e}"));
+        }
+    };
 
     // Write files
     for (path, content) in files {
@@ -101,25 +134,36 @@ pub fn build(
     }
 
     // Update manifest
-    static MANIFEST: LazyLock<String> = LazyLock::new(|| {
-        fs::read_to_string(Path::new(PROGRAMS_DIR).join("Cargo.toml"))
-            .expect("Could not read manifest")
-    });
+    //
+    // The program path is absolute because toolchain directories can be anywhere
+    let manifest = fs::read_to_string(toolchain_path.join("Cargo.toml"))
+        .map_err(|e| anyhow!("Could not read toolchain manifest: {e}"))?;
     let manifest_path = concurrency_path.join("Cargo.toml");
+    let absolute_program_path = fs::canonicalize(&program_path)?;
     fs::write(
         &manifest_path,
-        MANIFEST.replacen("default", &format!("../{program_name}"), 1),
+        manifest.replacen("default", &absolute_program_path.to_string_lossy(), 1),
     )?;
 
     // Build the program with a clean env, inheriting only toolchain locator vars from the parent.
-    let output = Command::new("cargo-build-sbf")
-        .env_clear()
-        .envs(["PATH", "HOME"].into_iter().filter_map(|key| {
-            std::env::var(key)
+    let mut envs = ["PATH", "HOME"]
+        .into_iter()
+        .filter_map(|key| {
+            env::var(key)
                 .inspect_err(|e| warn!("Failed to get env variable: `{key}`: {e}"))
                 .ok()
                 .map(|value| (key, value))
-        }))
+        })
+        .collect::<HashMap<_, _>>();
+    if let Some(solana_bin) = &toolchain.solana_bin {
+        // Solana binaries of the toolchain take precedence over the ones in `PATH`
+        let path = envs.get("PATH").map(String::as_str).unwrap_or_default();
+        let path = join_paths(iter::once(solana_bin.clone()).chain(split_paths(path)))?;
+        envs.insert("PATH", path.to_string_lossy().into_owned());
+    }
+    let output = Command::new("cargo-build-sbf")
+        .env_clear()
+        .envs(envs)
         .arg("--manifest-path")
         .arg(manifest_path)
         .arg("--sbf-out-dir")
@@ -143,8 +187,17 @@ pub fn build(
 
     // Generate IDL if it's an Anchor program
     let lib_path = program_path.join("src").join("lib.rs");
-    let ret = fs::read_to_string(&lib_path)?
-        .contains("anchor_lang")
+    let is_anchor = fs::read_to_string(&lib_path)?.contains("anchor_lang");
+    if is_anchor && !toolchain.anchor_matches(IDL_ANCHOR_VERSION) {
+        let stderr = format!(
+            "{stderr}IDL error: IDL generation is only supported with Anchor {IDL_ANCHOR_VERSION} \
+            (toolchain Anchor version: {})\n",
+            toolchain.anchor
+        );
+        return Ok((stderr, None));
+    }
+
+    let ret = is_anchor
         .then(|| {
             parse_idl(
                 lib_path,
@@ -164,6 +217,6 @@ pub fn build(
 /// In order for the program binary to exist, the program must be built using the [`build`] function
 /// before this command is executed.
 pub async fn get_binary(program_name: &str) -> tokio::io::Result<Vec<u8>> {
-    let binary_path = Path::new(PROGRAMS_DIR).join(program_name).join("solpg.so");
+    let binary_path = Path::new(PROGRAMS_DIR).join(program_name).join(BINARY_FILE);
     tokio::fs::read(binary_path).await
 }
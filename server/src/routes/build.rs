@@ -6,48 +6,11 @@ use axum::{
     extract::{Json, State},
     response::IntoResponse,
 };
-use serde::{Deserialize, Serialize};
-use solpg_server::{program, utils::Files, Result};
+use solpg_server::{idl, program, utils::Files, Config, Result};
+use solpg_server_types::{BuildRequest, BuildResponse};
 use tokio::{sync::Semaphore, task};
 use uuid::Uuid;
 
-/// Build request
-#[derive(Deserialize)]
-pub struct BuildRequest {
-    /// Program files to build
-    files: Files,
-    /// UUID of the program.
-    ///
-    /// In the first ever request from a client, this will not exist and [`BuildResponse`] will
-    /// return a `uuid`. Client is responsible for saving the `uuid` and using it with every
-    /// subseqent requests in order to save resources not re-creating the project.
-    uuid: Option<String>,
-    /// Build flags
-    flags: Option<BuildFlags>,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct BuildFlags {
-    /// Enable Anchor `seeds` feature, defaults to `false`
-    seeds_feature: Option<bool>,
-    /// Remove doc comments from the IDL, defaults to `true`
-    no_docs: Option<bool>,
-    /// Enable safety checks, defaults to `false`
-    safety_checks: Option<bool>,
-}
-
-/// Build response
-#[derive(Serialize)]
-struct BuildResponse {
-    /// Solana build tools output to `stderr` regardless of the compilation status
-    stderr: String,
-    /// UUID of the program, `None` if the [`BuildRequest`] includes `uuid`
-    uuid: Option<String>,
-    /// Anchor IDL of the program, `None` for native programs
-    idl: Option<Idl>,
-}
-
 /// Build state
 #[derive(Clone)]
 pub struct BuildState {
@@ -65,12 +28,37 @@ impl BuildState {
             ids: Arc::new(Mutex::new(vec![false; concurrency])),
         }
     }
+
+    /// Apply the new config.
+    ///
+    /// The ids are never removed in order to not invalidate the ones in use, only the amount of
+    /// permits decreases when the concurrency decreases.
+    pub fn reload(&self, old: &Config, new: &Config) {
+        let (from, to) = (old.build.concurrency, new.build.concurrency);
+        if let Ok(mut ids) = self.ids.lock() {
+            if to > ids.len() {
+                ids.resize(to, false);
+            }
+        }
+
+        super::resize_semaphore(&self.sem, from, to);
+    }
 }
 
 /// Build the program.
+#[utoipa::path(
+    post,
+    path = "/build",
+    tag = "build",
+    request_body = BuildRequest,
+    responses(
+        (status = 200, description = "Build output", body = BuildResponse),
+        (status = 500, description = "Invalid request or server error", body = String),
+    ),
+)]
 pub async fn build(
     State(state): State<BuildState>,
-    Json(payload): Json<BuildRequest>,
+    Json(mut payload): Json<BuildRequest>,
 ) -> Result<impl IntoResponse> {
     let (uuid, respond_with_uuid) = match payload.uuid {
         Some(uuid) => Uuid::try_parse(&uuid)
@@ -78,6 +66,22 @@ pub async fn build(
             .map_err(|_| anyhow!("Invalid UUID"))?,
         None => (Uuid::new_v4().to_string(), true),
     };
+    let previous_idl = payload
+        .previous_idl
+        .take()
+        .map(serde_json::from_value::<Idl>)
+        .transpose()
+        .map_err(|e| anyhow!("Invalid previous IDL: {e}"))?;
+    let toolchain = {
+        let selector = payload.toolchain.as_ref();
+        Config::current()
+            .build
+            .toolchain(
+                selector.and_then(|t| t.platform_tools.as_deref()),
+                selector.and_then(|t| t.anchor.as_deref()),
+            )?
+            .clone()
+    };
 
     // Only permit a certain number of builds concurrently
     let permit = concurrent::Permit::acquire(state).await?;
@@ -86,11 +90,13 @@ pub async fn build(
     // Spawn a blocking `tokio::task` to avoid blocking the thread
     let (build_result, uuid) = task::spawn_blocking(move || {
         let flags = payload.flags.as_ref();
+        let files = payload.files.into_iter().collect::<Files>();
         (
             program::build(
                 concurrency_id,
                 &uuid,
-                &payload.files,
+                &files,
+                &toolchain,
                 flags.and_then(|f| f.seeds_feature).unwrap_or_default(),
                 flags.and_then(|f| f.no_docs).unwrap_or(true),
                 flags.and_then(|f| f.safety_checks).unwrap_or_default(),
@@ -101,11 +107,20 @@ pub async fn build(
     .await
     .map_err(|e| anyhow!("Failed to run `spawn_blocking`: {e}"))?;
     let (stderr, idl) = build_result?;
+    let compatibility = previous_idl
+        .zip(idl.as_ref())
+        .map(|(previous_idl, idl)| idl::compare(&previous_idl, idl));
+
+    let idl = idl
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| anyhow!("Failed to serialize IDL: {e}"))?;
 
     Ok(Json(BuildResponse {
         stderr,
         uuid: if respond_with_uuid { Some(uuid) } else { None },
         idl,
+        compatibility,
     }))
 }
 
@@ -1,39 +1,264 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
 use anyhow::anyhow;
-use axum::{response::IntoResponse, Json};
-use serde::{Deserialize, Serialize};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 use solpg_server::{
-    package::{get_out_path, BUNDLE_FILE, LOCK_FILE, MANIFEST_FILE, PACKAGES_DIR, TYPES_FILE},
-    utils::Files,
-    Result, Sandbox,
+    log::{debug, error, info, warn},
+    package::{get_in_path, get_out_path, ARCHIVE_FILE, MANIFEST_FILE},
+    BundleConfig, Config, Result, Sandbox, SandboxEvent, SandboxPool,
 };
-use tokio::{fs, process::Command};
+use solpg_server_types::{BundleRequest, LockFormat};
+use tokio::{
+    fs,
+    process::Command,
+    sync::{mpsc, Semaphore},
+};
+use tokio_util::io::ReaderStream;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Deserialize)]
-pub struct BundleRequest {
-    /// Package manifest (`package.json`)
-    manifest: String,
-    /// Lock file
-    lock: Option<String>,
+/// Compressed archive (`.tar.gz`) of the bundle
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+struct BundleArchive(#[allow(unused)] Vec<u8>);
+
+/// Bundle state
+#[derive(Clone)]
+pub struct BundleState {
+    /// Semaphore to limit concurrent requests
+    sem: Arc<Semaphore>,
+    /// Pool of pre-started sandbox containers
+    pool: Arc<RwLock<Option<SandboxPool>>>,
 }
 
-#[derive(Serialize)]
-struct BundleResponse {
-    /// Bundle files
-    bundle: Files,
-    /// Type declaration files
-    types: Files,
-    /// Package manifest (`package.json`)
-    manifest: String,
-    /// Lock file
-    lock: String,
+impl BundleState {
+    /// Create a new value from the server config.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            sem: Arc::new(Semaphore::new(config.bundle.concurrency)),
+            pool: Arc::new(RwLock::new(Self::create_pool(config))),
+        }
+    }
+
+    /// Apply the new config.
+    ///
+    /// The sandbox pool is recreated if the sandbox configuration has changed.
+    pub fn reload(&self, old: &Config, new: &Config) {
+        super::resize_semaphore(&self.sem, old.bundle.concurrency, new.bundle.concurrency);
+
+        let sandbox_config = |c: &Config| {
+            let b = &c.bundle;
+            let limits = (b.cpu_limit, b.memory_limit, b.process_limit, b.timeout);
+            (c.sandbox.clone(), limits, b.npm_network.clone())
+        };
+        if sandbox_config(old) != sandbox_config(new) {
+            let pool = Self::create_pool(new);
+            let old_pool = match self.pool.write() {
+                Ok(mut current) => std::mem::replace(&mut *current, pool),
+                Err(e) => return error!("Failed to replace the sandbox pool: {e}"),
+            };
+            if let Some(old_pool) = old_pool {
+                tokio::spawn(async move { old_pool.close().await });
+            }
+        }
+    }
+
+    /// Stop the pooled sandbox containers.
+    pub async fn close(&self) {
+        let pool = self.pool.write().ok().and_then(|mut pool| pool.take());
+        if let Some(pool) = pool {
+            pool.close().await;
+        }
+    }
+
+    /// Create a sandbox to bundle the packages in, from the pool if it's enabled.
+    fn sandbox<'a>(&self, config: &Config) -> Sandbox<'a> {
+        let pool = self.pool.read().ok().and_then(|pool| pool.clone());
+        match pool {
+            Some(pool) => pool.sandbox(),
+            None => Self::create_sandbox(config),
+        }
+    }
+
+    /// Create the sandbox pool if pooling is enabled.
+    fn create_pool(config: &Config) -> Option<SandboxPool> {
+        (config.sandbox.pool_size != 0).then(|| {
+            SandboxPool::new(
+                Self::create_sandbox(config),
+                config.sandbox.pool_size,
+                config.sandbox.pool_max_uses,
+            )
+        })
+    }
+
+    /// Create the sandbox template from the sandbox and the bundle config.
+    ///
+    /// The sandbox is read-only (the packages are installed in the `tmpfs` home directory), which
+    /// allows pooled containers to be reused. `tmpfs` mounts count towards the memory limit, so
+    /// their size is the same as the memory limit.
+    fn create_sandbox<'a>(config: &Config) -> Sandbox<'a> {
+        const USER: &str = "solpg";
+
+        let (sandbox, config) = (&config.sandbox, &config.bundle);
+        Sandbox::new()
+            .backend(sandbox.backend)
+            .local_images_dir(&sandbox.local_images_dir)
+            .image("solpg-server-sandbox-bundle")
+            .user(USER)
+            .cpu_limit(config.cpu_limit)
+            .memory_limit(config.memory_limit)
+            .process_limit(config.process_limit)
+            .timeout(config.timeout)
+            .read_only()
+            .tmpfs(format!("/home/{USER}"), config.memory_limit)
+            .tmpfs("/tmp", config.memory_limit)
+            .network(&config.npm_network)
+    }
 }
 
 /// Bundle ESM packages.
-//
-// TODO: Concurrency limit
-// TODO: Cache
-pub async fn bundle(Json(payload): Json<BundleRequest>) -> Result<impl IntoResponse> {
+///
+/// The response is a compressed archive (`.tar.gz`) that includes the bundle files, module mapping
+/// (package name to module name and entry file), type declaration files, type dependency graph,
+/// package manifest and the lock file. The lock file of the request is only used if its format
+/// matches the server's package manager, otherwise a new lock file is generated. The format of the
+/// lock file in the archive is sent with the `X-Lock-Format` header.
+///
+/// Bundles are cached based on the manifest and the lock file, meaning requests with the same
+/// dependencies are only bundled once. The cache key is also used as the `ETag` of the response.
+///
+/// Requests that accept `text/event-stream` get the progress of the bundle steps as server-sent
+/// events instead (`started`, `stdout`, `stderr` and `exited`), followed by either a `done` event
+/// with the `ETag` of the bundle or an `error` event. The archive can then be requested again
+/// without bundling it, since it's cached.
+#[utoipa::path(
+    post,
+    path = "/unstable/bundle",
+    tag = "bundle",
+    request_body = BundleRequest,
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a previous bundle"),
+        ("Accept" = Option<String>, Header, description = "`text/event-stream` for progress events"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Bundle archive or progress events",
+            content((BundleArchive = "application/gzip"), (String = "text/event-stream")),
+            headers(
+                ("ETag" = String, description = "Cache key of the bundle"),
+                ("X-Lock-Format" = LockFormat, description = "Format of the lock file in the archive"),
+            ),
+        ),
+        (status = 304, description = "Bundle has not changed"),
+        (status = 500, description = "Invalid request or bundle error", body = String),
+    ),
+)]
+pub async fn bundle(
+    State(state): State<BundleState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<BundleRequest>,
+) -> Result<Response> {
+    let config = Config::current();
+    let lock_format = config.bundle.package_manager.lock_format();
+    if payload.lock_format != lock_format {
+        // Lock files can't be converted between the formats, the lock file is generated instead
+        payload.lock = None;
+    }
+
+    let key = cache::key(&payload.manifest, payload.lock.as_deref(), &config.bundle)?;
+    let etag = format!(r#""{key}""#);
+    let is_fresh = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+    if is_fresh {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let wants_progress = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains(progress::CONTENT_TYPE));
+    if wants_progress {
+        return Ok(progress::stream(payload, state, config, key, etag).into_response());
+    }
+
+    let archive = get_or_generate_bundle(payload, &state, &config, &key, None).await?;
+    let len = archive
+        .metadata()
+        .await
+        .map_err(|e| anyhow!("Failed to get archive metadata: {e}"))?
+        .len();
+    Ok((
+        [
+            (header::CONTENT_TYPE, ARCHIVE_CONTENT_TYPE.to_owned()),
+            (header::CONTENT_LENGTH, len.to_string()),
+            (header::ETAG, etag),
+            (
+                HeaderName::from_static(LockFormat::HEADER),
+                lock_format.to_string(),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(archive)),
+    )
+        .into_response())
+}
+
+/// Get the cached bundle archive, or generate it if it's not cached.
+///
+/// The sandbox events are sent to `progress` while generating the bundle.
+async fn get_or_generate_bundle(
+    payload: BundleRequest,
+    state: &BundleState,
+    config: &Config,
+    key: &str,
+    progress: Option<mpsc::UnboundedSender<SandboxEvent>>,
+) -> Result<fs::File> {
+    if let Some(archive) = cache::get(key).await? {
+        return Ok(archive);
+    }
+
+    // Only permit a certain number of bundles concurrently
+    let _permit = state
+        .sem
+        .acquire()
+        .await
+        .map_err(|e| anyhow!("Failed to acquire `Semaphore`: {e}"))?;
+
+    // The same bundle might have been cached while waiting for the permit
+    if let Some(archive) = cache::get(key).await? {
+        return Ok(archive);
+    }
+
+    generate_bundle(payload, state, config, key, progress).await?;
+    cache::get(key)
+        .await?
+        .ok_or_else(|| anyhow!("Bundle is not cached").into())
+}
+
+/// Content type of the response archive
+const ARCHIVE_CONTENT_TYPE: &str = "application/gzip";
+
+/// Generate the bundle in a sandboxed environment and save the output archive to the cache.
+///
+/// Only the package installation has network access (limited to the registry).
+async fn generate_bundle(
+    payload: BundleRequest,
+    state: &BundleState,
+    config: &Config,
+    key: &str,
+    progress: Option<mpsc::UnboundedSender<SandboxEvent>>,
+) -> Result<()> {
+    let bundle_config = &config.bundle;
     let uuid = Uuid::new_v4();
     let container_path = get_out_path();
     let host_path = container_path.join(uuid.to_string());
@@ -41,72 +266,317 @@ pub async fn bundle(Json(payload): Json<BundleRequest>) -> Result<impl IntoRespo
         .await
         .map_err(|e| anyhow!("Failed to create host dir: {host_path:?}: {e}"))?;
 
-    let manifest_path = host_path.join(MANIFEST_FILE);
-    fs::write(&manifest_path, &payload.manifest)
-        .await
-        .map_err(|e| anyhow!("Failed to write manifest file: {e}"))?;
+    let result = async {
+        let manifest_path = host_path.join(MANIFEST_FILE);
+        fs::write(&manifest_path, &payload.manifest)
+            .await
+            .map_err(|e| anyhow!("Failed to write manifest file: {e}"))?;
+
+        let lock_path = host_path.join(bundle_config.package_manager.lock_file());
+        if let Some(lock) = &payload.lock {
+            fs::write(&lock_path, lock)
+                .await
+                .map_err(|e| anyhow!("Failed to write lock file: {e}"))?;
+        }
+
+        let mut install_cmd = Command::new("bundle");
+        install_cmd
+            .arg("install")
+            .arg("--registry")
+            .arg(&bundle_config.npm_registry)
+            .arg("--package-manager")
+            .arg(bundle_config.package_manager.to_string());
+        let mut build_cmd = Command::new("bundle");
+        build_cmd
+            .arg("build")
+            .arg("--bundler")
+            .arg(bundle_config.bundler.to_string())
+            .arg("--package-manager")
+            .arg(bundle_config.package_manager.to_string());
+
+        let start = Instant::now();
+        let (events, mut events_rx) = mpsc::unbounded_channel();
+        let sandbox = state
+            .sandbox(config)
+            .copy(
+                format!("{}/.", host_path.display()),
+                format!("container:{}", get_in_path().display()),
+            )
+            .command(&install_cmd)
+            .isolate()
+            .command(&build_cmd)
+            .copy(
+                format!("container:{}/.", container_path.display()),
+                &host_path,
+            )
+            .run_streaming(events);
+
+        // Report the progress of the bundle steps
+        let progress = async {
+            while let Some(event) = events_rx.recv().await {
+                match &event {
+                    SandboxEvent::Started { command, .. } => {
+                        info!("Bundle `{key}`: Running `{command}`")
+                    }
+                    SandboxEvent::Stdout { chunk, .. } | SandboxEvent::Stderr { chunk, .. } => {
+                        debug!(
+                            "Bundle `{key}`: {}",
+                            String::from_utf8_lossy(chunk).trim_end()
+                        )
+                    }
+                    SandboxEvent::Exited {
+                        status, duration, ..
+                    } => info!("Bundle `{key}`: Exited with {status} in {duration:?}"),
+                }
+                if let Some(progress) = &progress {
+                    progress.send(event).ok();
+                }
+            }
+        };
+        let (output, _) = tokio::join!(sandbox, progress);
+        let output = output?;
+        info!(
+            "Bundle `{key}` with {}/{} took {:?}",
+            bundle_config.package_manager,
+            bundle_config.bundler,
+            start.elapsed()
+        );
+
+        if !output.status.success() {
+            let step = match output.commands.len() {
+                1 => "install",
+                _ => "build",
+            };
+            return Err(anyhow!(
+                "Failed to {step} packages ({}): {}\n{}",
+                output.status,
+                str::from_utf8(&output.stdout)
+                    .map_err(|e| anyhow!("Invalid bundle stdout: {e}"))?,
+                str::from_utf8(&output.stderr)
+                    .map_err(|e| anyhow!("Invalid bundle stderr: {e}"))?
+            ))?;
+        }
 
-    let lock_path = host_path.join(LOCK_FILE);
-    if let Some(lock) = &payload.lock {
-        fs::write(&lock_path, lock)
+        cache::set(key, &host_path.join(ARCHIVE_FILE)).await?;
+        cache::prune(key, bundle_config.cache_max_bytes).await
+    }
+    .await;
+
+    // The output archive is moved to the cache, the host dir is no longer needed
+    if let Err(e) = fs::remove_dir_all(&host_path).await {
+        warn!("Failed to remove host dir: {host_path:?}: {e}");
+    }
+
+    result
+}
+
+/// Bundle progress as server-sent events
+mod progress {
+    use std::{convert::Infallible, sync::Arc};
+
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures_util::{stream, Stream};
+    use serde_json::json;
+
+    use super::*;
+
+    /// Content type of the progress events
+    pub(super) const CONTENT_TYPE: &str = "text/event-stream";
+
+    /// Generate the bundle in the background and stream its progress.
+    ///
+    /// The bundle keeps being generated (and cached) even if the client disconnects.
+    pub(super) fn stream(
+        payload: BundleRequest,
+        state: BundleState,
+        config: Arc<Config>,
+        key: String,
+        etag: String,
+    ) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>>> {
+        let (events, events_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (progress, mut progress_rx) = mpsc::unbounded_channel();
+            let bundle = get_or_generate_bundle(payload, &state, &config, &key, Some(progress));
+            let forward = async {
+                while let Some(event) = progress_rx.recv().await {
+                    events.send(to_event(event)).ok();
+                }
+            };
+            let (result, _) = tokio::join!(bundle, forward);
+
+            let event = match result {
+                Ok(_) => Event::default().event("done").data(etag),
+                Err(e) => Event::default().event("error").data(e.to_string()),
+            };
+            events.send(event).ok();
+        });
+
+        let stream = stream::unfold(events_rx, |mut events_rx| async move {
+            let event = events_rx.recv().await?;
+            Some((Ok(event), events_rx))
+        });
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// Convert the sandbox event to a server-sent event with JSON data.
+    fn to_event(event: SandboxEvent) -> Event {
+        let (name, data) = match event {
+            SandboxEvent::Started { action, command } => {
+                ("started", json!({ "action": action, "command": command }))
+            }
+            SandboxEvent::Stdout { action, chunk } => (
+                "stdout",
+                json!({ "action": action, "chunk": String::from_utf8_lossy(&chunk) }),
+            ),
+            SandboxEvent::Stderr { action, chunk } => (
+                "stderr",
+                json!({ "action": action, "chunk": String::from_utf8_lossy(&chunk) }),
+            ),
+            SandboxEvent::Exited {
+                action,
+                status,
+                duration,
+            } => (
+                "exited",
+                json!({
+                    "action": action,
+                    "code": status.code(),
+                    "durationMs": duration.as_millis(),
+                }),
+            ),
+        };
+
+        Event::default().event(name).data(data.to_string())
+    }
+}
+
+/// Content-addressed bundle cache
+mod cache {
+    use std::{
+        collections::BTreeMap,
+        io,
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+    use solpg_server::package::get_cache_path;
+
+    use super::*;
+
+    /// Get the cache key of the given manifest, lock file and the bundle tooling.
+    ///
+    /// The manifest is normalized before hashing so that formatting and key order differences
+    /// don't result in separate cache entries.
+    pub(super) fn key(manifest: &str, lock: Option<&str>, config: &BundleConfig) -> Result<String> {
+        let manifest = serde_json::from_str(manifest)
+            .map(normalize)
+            .map_err(|e| anyhow!("Invalid manifest: {e}"))?
+            .to_string();
+
+        let mut hasher = Sha256::new();
+        hasher.update(manifest);
+        hasher.update([0]);
+        hasher.update(lock.map(str::trim).unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(format!("{}/{}", config.package_manager, config.bundler));
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// Open the cached archive, `None` if it doesn't exist.
+    ///
+    /// The modification time of the archive is updated in order to keep the recently used
+    /// archives in the cache (see [`prune`]).
+    pub(super) async fn get(key: &str) -> Result<Option<fs::File>> {
+        let archive = match fs::File::open(get_entry_path(key)).await {
+            Ok(archive) => archive.into_std().await,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow!("Failed to read cache: {e}").into()),
+        };
+        if let Err(e) = archive.set_modified(SystemTime::now()) {
+            warn!("Failed to update the modification time of cache entry `{key}`: {e}");
+        }
+
+        Ok(Some(fs::File::from_std(archive)))
+    }
+
+    /// Move the archive at the given path to the cache.
+    ///
+    /// Renaming is atomic, meaning partially written archives are never served.
+    pub(super) async fn set(key: &str, archive_path: &Path) -> Result<()> {
+        fs::create_dir_all(get_cache_path())
             .await
-            .map_err(|e| anyhow!("Failed to write lock file: {e}"))?;
-    }
-
-    let output = Sandbox::new()
-        .image("solpg-server-sandbox-bundle")
-        .user("solpg")
-        // TODO: Set limits from config
-        .cpu_limit(4) // diminishing returns after 4
-        .memory_limit(4 * 1024 * 1024 * 1024) // 4 GiB (also affects speed)
-        .process_limit(64)
-        .timeout(300)
-        .copy(
-            format!("{}/.", host_path.display()),
-            format!("container:{PACKAGES_DIR}"),
-        )
-        .command(&Command::new("bundle"))
-        .copy(
-            format!("container:{}/.", container_path.display()),
-            &host_path,
-        )
-        .run()
-        .await?;
-
-    if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to generate bundle: {}\n{}",
-            str::from_utf8(&output.stdout).map_err(|e| anyhow!("Invalid bundle stdout: {e}"))?,
-            str::from_utf8(&output.stderr).map_err(|e| anyhow!("Invalid bundle stderr: {e}"))?
-        ))?;
-    }
-
-    let bundle_path = host_path.join(BUNDLE_FILE);
-    let bundle = fs::read(bundle_path)
-        .await
-        .map_err(|e| anyhow!("Could not get bundle: {e}"))
-        .map(|b| serde_json::from_slice::<Files>(&b))?
-        .map_err(|e| anyhow!("Unexpected files for bundle: {e}"))?;
+            .map_err(|e| anyhow!("Failed to create cache dir: {e}"))?;
+        fs::rename(archive_path, get_entry_path(key))
+            .await
+            .map_err(|e| anyhow!("Failed to cache archive: {e}"))?;
+        Ok(())
+    }
 
-    let types_path = host_path.join(TYPES_FILE);
-    let types = fs::read(types_path)
-        .await
-        .map_err(|e| anyhow!("Could not get files: {e}"))
-        .map(|b| serde_json::from_slice::<Files>(&b))?
-        .map_err(|e| anyhow!("Unexpected files for types: {e}"))?;
+    /// Remove the least recently used archives until the cache size is at most `max_bytes`.
+    ///
+    /// The archive of the given key is kept regardless of its size, since it's about to be
+    /// served. Archives that are being served are not affected, as their files are already open.
+    pub(super) async fn prune(key: &str, max_bytes: u64) -> Result<()> {
+        let mut dir = fs::read_dir(get_cache_path())
+            .await
+            .map_err(|e| anyhow!("Failed to read cache dir: {e}"))?;
+        let keep_path = get_entry_path(key);
+        let mut entries = vec![];
+        let mut total_bytes = 0;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(|e| anyhow!("Failed to read cache dir: {e}"))?
+        {
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            total_bytes += metadata.len();
+            if entry.path() != keep_path {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((modified, metadata.len(), entry.path()));
+            }
+        }
 
-    let manifest = fs::read_to_string(manifest_path)
-        .await
-        .map_err(|e| anyhow!("Could not get manifest: {e}"))?;
+        entries.sort_unstable();
+        for (_, len, path) in entries {
+            if total_bytes <= max_bytes {
+                break;
+            }
 
-    let lock = fs::read_to_string(lock_path)
-        .await
-        .map_err(|e| anyhow!("Could not get lock file: {e}"))?;
-
-    Ok(Json(BundleResponse {
-        bundle,
-        types,
-        manifest,
-        lock,
-    }))
+            match fs::remove_file(&path).await {
+                Ok(()) => total_bytes -= len,
+                Err(e) => warn!("Failed to remove cache entry: {path:?}: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the path of the cache entry from its key.
+    fn get_entry_path(key: &str) -> PathBuf {
+        get_cache_path()
+            .join(key)
+            .with_extension(ARCHIVE_FILE_EXTENSION)
+    }
+
+    /// Archive file extension
+    const ARCHIVE_FILE_EXTENSION: &str = "tar.gz";
+
+    /// Recursively sort the object keys.
+    fn normalize(value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, normalize(v)))
+                    .collect::<BTreeMap<_, _>>()
+                    .into_iter()
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+            value => value,
+        }
+    }
 }
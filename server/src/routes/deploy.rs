@@ -7,6 +7,16 @@ use tokio::io;
 ///
 /// Program deployments are not done in the server, the server is only responsible for sending the
 /// program binary to the client.
+#[utoipa::path(
+    get,
+    path = "/deploy/{uuid}",
+    tag = "deploy",
+    params(("uuid" = String, Path, description = "UUID of the program")),
+    responses(
+        (status = 200, description = "Program binary (ELF)", body = String, content_type = "application/octet-stream"),
+        (status = 500, description = "Program is not built or server error", body = String),
+    ),
+)]
 pub async fn deploy(Path(uuid): Path<String>) -> Result<impl IntoResponse> {
     program::get_binary(&uuid)
         .await
@@ -1,9 +1,34 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
 mod build;
 mod bundle;
 mod deploy;
+mod openapi;
 mod share;
+mod toolchains;
 
 pub use build::{build, BuildState};
-pub use bundle::bundle;
+pub use bundle::{bundle, BundleState};
 pub use deploy::deploy;
+pub use openapi::openapi;
 pub use share::{share_get, share_new};
+pub use toolchains::toolchains;
+
+/// Resize the semaphore from `from` to `to` permits.
+///
+/// Shrinking waits for the excess permits to be released in the background, meaning requests in
+/// progress are not affected.
+fn resize_semaphore(sem: &Arc<Semaphore>, from: usize, to: usize) {
+    if to > from {
+        sem.add_permits(to - from);
+    } else if to < from {
+        let sem = Arc::clone(sem);
+        tokio::spawn(async move {
+            if let Ok(permits) = sem.acquire_many_owned((from - to) as u32).await {
+                permits.forget();
+            }
+        });
+    }
+}
@@ -0,0 +1,30 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+/// OpenAPI document of the stable routes
+#[derive(OpenApi)]
+#[openapi(paths(
+    super::build::build,
+    super::deploy::deploy,
+    super::share::share_get,
+    super::share::share_new,
+    super::toolchains::toolchains,
+))]
+struct StableApi;
+
+/// OpenAPI document of the unstable routes
+#[derive(OpenApi)]
+#[openapi(paths(super::bundle::bundle))]
+struct UnstableApi;
+
+/// Get the OpenAPI document of the server.
+///
+/// The document is generated from the route handlers and their request/response types.
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    let mut doc = StableApi::openapi();
+    if cfg!(feature = "unstable") {
+        doc.merge(UnstableApi::openapi());
+    }
+
+    Json(doc)
+}
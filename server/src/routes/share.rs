@@ -1,13 +1,22 @@
 use anyhow::anyhow;
 use axum::{extract::Path, response::IntoResponse, Json};
-use serde::Deserialize;
-use serde_json::Value;
 use solpg_server::{db, Result};
+use solpg_server_types::ShareNewRequest;
 
 /// Collection name of shares in database
 const COLLECTION: &str = "share";
 
 /// Get the share from its id.
+#[utoipa::path(
+    get,
+    path = "/share/{id}",
+    tag = "share",
+    params(("id" = String, Path, description = "Share id")),
+    responses(
+        (status = 200, description = "Share", body = Object),
+        (status = 500, description = "Share not found or server error", body = String),
+    ),
+)]
 pub async fn share_get(Path(id): Path<String>) -> Result<impl IntoResponse> {
     db::find_by_id(&id, COLLECTION)
         .await?
@@ -16,14 +25,17 @@ pub async fn share_get(Path(id): Path<String>) -> Result<impl IntoResponse> {
         .map_err(Into::into)
 }
 
-/// Share new request
-#[derive(Deserialize)]
-pub struct ShareNewRequest {
-    /// Explorer contains all file related data about the share
-    explorer: Value,
-}
-
 /// Create a new share.
+#[utoipa::path(
+    post,
+    path = "/new",
+    tag = "share",
+    request_body = ShareNewRequest,
+    responses(
+        (status = 200, description = "Id of the created share", body = String),
+        (status = 500, description = "Invalid request or server error", body = String),
+    ),
+)]
 pub async fn share_new(Json(payload): Json<ShareNewRequest>) -> Result<impl IntoResponse> {
     db::insert(payload.explorer, COLLECTION)
         .await
@@ -0,0 +1,26 @@
+use axum::Json;
+use solpg_server::Config;
+use solpg_server_types::Toolchain;
+
+/// Get the available program build toolchains.
+#[utoipa::path(
+    get,
+    path = "/toolchains",
+    tag = "build",
+    responses((status = 200, description = "Available toolchains", body = Vec<Toolchain>)),
+)]
+pub async fn toolchains() -> Json<Vec<Toolchain>> {
+    let config = Config::current();
+    let toolchains = config
+        .build
+        .toolchains
+        .iter()
+        .enumerate()
+        .map(|(i, toolchain)| Toolchain {
+            platform_tools: toolchain.platform_tools.clone(),
+            anchor: toolchain.anchor.clone(),
+            default: i == 0,
+        })
+        .collect();
+    Json(toolchains)
+}
@@ -0,0 +1,243 @@
+use std::{process, time::Duration};
+
+use anyhow::{anyhow, Result};
+use tokio::{process::Command, time::timeout};
+use uuid::Uuid;
+
+use super::{run_cmd, Backend, Config, Environment};
+
+/// Container environment that is managed by a Docker compatible CLI (Docker or Podman).
+#[derive(Debug)]
+pub(super) struct Container {
+    /// Sandbox configuration
+    cfg: Config,
+    /// Container engine CLI
+    cli: &'static str,
+    /// Container name
+    name: String,
+    /// Network the container is connected to
+    network: Option<String>,
+    /// Whether the network is created specifically for this container
+    is_internal_network: bool,
+    /// Whether the container is disconnected from its network
+    is_isolated: bool,
+}
+
+impl Container {
+    /// Create a new command of the container engine CLI.
+    fn cli(&self) -> Command {
+        Command::new(self.cli)
+    }
+
+    /// Check whether the container is running and responsive.
+    pub(super) async fn is_healthy(&self) -> bool {
+        const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+        let mut cmd = self.cli();
+        cmd.arg("exec").arg(&self.name).arg("true");
+        matches!(
+            timeout(HEALTH_CHECK_TIMEOUT, run_cmd(&mut cmd)).await,
+            Ok(Ok(()))
+        )
+    }
+
+    /// Reset the container in order to reuse it for another sandbox.
+    ///
+    /// Remaining processes are killed, `tmpfs` mounts (including the default `/dev/shm` mount) are
+    /// emptied and the network is reconnected. Changes outside of the `tmpfs` mounts are not
+    /// reverted, meaning only read-only containers can be fully reset.
+    pub(super) async fn reset(&mut self) -> Result<()> {
+        // Kill all processes except `init` and empty `/dev/shm` and the given directories
+        const RESET_SCRIPT: &str = r#"kill -9 -1 2>/dev/null
+for p in /dev/shm "$@"; do find "$p" -mindepth 1 -delete || exit; done"#;
+        run_cmd(
+            self.cli()
+                .arg("exec")
+                .arg(&self.name)
+                .args(["sh", "-c", RESET_SCRIPT, "sh"])
+                .args(self.cfg.tmpfs.iter().map(|(path, _)| path)),
+        )
+        .await?;
+
+        if let (Some(network), true) = (&self.network, self.is_isolated) {
+            run_cmd(
+                self.cli()
+                    .arg("network")
+                    .arg("connect")
+                    .arg(network)
+                    .arg(&self.name),
+            )
+            .await?;
+            self.is_isolated = false;
+        }
+
+        Ok(())
+    }
+}
+
+impl Environment for Container {
+    fn new(cfg: &Config) -> Self {
+        const NAME_PREFIX: &str = concat!(env!("CARGO_PKG_NAME"), "-sandbox");
+        let name = format!("{NAME_PREFIX}-{}", Uuid::new_v4());
+        let is_internal_network = !cfg.network_allowlist.is_empty();
+        let network = match is_internal_network {
+            true => Some(format!("{name}-network")),
+            false => cfg.network.to_owned(),
+        };
+
+        Self {
+            cfg: cfg.to_owned(),
+            cli: match cfg.backend {
+                Backend::Podman => "podman",
+                _ => "docker",
+            },
+            name,
+            network,
+            is_internal_network,
+            is_isolated: false,
+        }
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        // Create an internal network for the allowlisted containers
+        if self.is_internal_network {
+            if self.cfg.network.is_some() {
+                return Err(anyhow!(
+                    "`network` and `network_allowlist` can't be used together"
+                ));
+            }
+
+            let network = self.network.as_deref().expect("Network must exist");
+            run_cmd(
+                self.cli()
+                    .arg("network")
+                    .arg("create")
+                    .arg("--internal")
+                    .arg(network),
+            )
+            .await?;
+            for allowed in &self.cfg.network_allowlist {
+                run_cmd(
+                    self.cli()
+                        .arg("network")
+                        .arg("connect")
+                        .arg(network)
+                        .arg(allowed),
+                )
+                .await?;
+            }
+        }
+
+        let mut cmd = self.cli();
+        cmd.arg("run")
+            .arg("--name")
+            .arg(&self.name)
+            .arg("--detach")
+            .arg("--rm")
+            .arg("--cap-drop=ALL")
+            .arg("--memory-swap=-1")
+            .arg("--network")
+            .arg(self.network.as_deref().unwrap_or("none"))
+            .arg("--oom-score-adj=1000") // Make the container easily killable when OOM
+            .arg("--security-opt=no-new-privileges");
+
+        if let Some(user) = &self.cfg.user {
+            cmd.arg("--user");
+            cmd.arg(user);
+        }
+        if let Some(cpu) = self.cfg.cpu_limit {
+            cmd.arg("--cpus");
+            cmd.arg(cpu.to_string());
+        }
+        if let Some(mem) = self.cfg.memory_limit {
+            cmd.arg("--memory");
+            cmd.arg(format!("{mem}b"));
+        }
+        if let Some(pids) = self.cfg.process_limit {
+            cmd.arg("--pids-limit");
+            cmd.arg(pids.to_string());
+        }
+        if let Some(storage) = self.cfg.storage_limit {
+            cmd.arg("--storage-opt");
+            cmd.arg(format!("size={storage}"));
+        }
+        if self.cfg.read_only {
+            cmd.arg("--read-only");
+        }
+        for (path, size) in &self.cfg.tmpfs {
+            cmd.arg("--mount");
+            cmd.arg(format!(
+                "type=tmpfs,destination={path},tmpfs-size={size},tmpfs-mode=1777"
+            ));
+        }
+
+        match &self.cfg.image {
+            Some(image) => cmd.arg(image),
+            _ => return Err(anyhow!("Image not specified")),
+        };
+
+        // `exec` makes `sleep` the init process, which is not killed when resetting pooled containers
+        cmd.args(["sh", "-lc", "exec sleep infinity"]);
+        run_cmd(&mut cmd).await
+    }
+
+    fn command(&self, cmd: &process::Command) -> Result<Command> {
+        // Variables are passed with `--env` because the container doesn't inherit the environment
+        // of the CLI, which must keep its own environment (e.g. `XDG_RUNTIME_DIR` for Podman)
+        let mut exec = self.cli();
+        exec.arg("exec").arg("--interactive");
+        for (key, value) in cmd.get_envs() {
+            if let Some(value) = value {
+                let mut env = key.to_owned();
+                env.push("=");
+                env.push(value);
+                exec.arg("--env").arg(env);
+            }
+        }
+        exec.arg(&self.name)
+            .arg(cmd.get_program())
+            .args(cmd.get_args());
+        Ok(exec)
+    }
+
+    async fn isolate(&mut self) -> Result<()> {
+        if let Some(network) = &self.network {
+            run_cmd(
+                self.cli()
+                    .arg("network")
+                    .arg("disconnect")
+                    .arg("--force")
+                    .arg(network)
+                    .arg(&self.name),
+            )
+            .await?;
+            self.is_isolated = true;
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) {
+        // Killing is enough for cleanup because of `--rm` during creation
+        run_cmd(self.cli().arg("kill").arg(&self.name)).await.ok();
+
+        // Cleanup the internal network
+        if let (Some(network), true) = (&self.network, self.is_internal_network) {
+            for allowed in &self.cfg.network_allowlist {
+                run_cmd(
+                    self.cli()
+                        .arg("network")
+                        .arg("disconnect")
+                        .arg("--force")
+                        .arg(network)
+                        .arg(allowed),
+                )
+                .await
+                .ok();
+            }
+            run_cmd(self.cli().arg("network").arg("rm").arg(network))
+                .await
+                .ok();
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    env,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{anyhow, Result};
+use tokio::{fs, process::Command};
+use uuid::Uuid;
+
+use super::{seccomp, Config, Environment};
+use crate::log::warn;
+
+/// `PATH` inside the environment (same as the Docker default)
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Local environment that isolates processes with Linux namespaces via `bwrap`.
+///
+/// The root filesystem of the image is mounted as an overlay (or read-only with
+/// [`Sandbox::read_only`](super::Sandbox::read_only)) whose changes are stored in a host
+/// directory, meaning the changes persist between commands similar to containers. Since images
+/// don't include their metadata, commands run in the home directory of the user with the default
+/// `PATH`.
+pub(super) struct Local {
+    /// Sandbox configuration
+    cfg: Config,
+    /// Host directory that stores the changes of the environment
+    dir: PathBuf,
+    /// Root filesystem of the image (resolved on start)
+    rootfs: PathBuf,
+    /// User to run the commands as (resolved on start)
+    user: Option<User>,
+    /// Whether the host network is shared
+    has_network: bool,
+}
+
+impl Local {
+    /// Get the host path of the overlay upper directory.
+    fn upper_path(&self) -> PathBuf {
+        self.dir.join("upper")
+    }
+
+    /// Get the host path of the overlay work directory.
+    fn work_path(&self) -> PathBuf {
+        self.dir.join("work")
+    }
+
+    /// Get the host path of the `tmpfs` mount at the given index.
+    fn tmpfs_path(&self, index: usize) -> PathBuf {
+        self.dir.join("tmpfs").join(index.to_string())
+    }
+
+    /// Get the host path of the compiled seccomp filter.
+    fn seccomp_path(&self) -> PathBuf {
+        self.dir.join("seccomp.bpf")
+    }
+
+    /// Resolve the root filesystem of the image.
+    ///
+    /// Image names are resolved to the directories in the local images directory, while paths are
+    /// used as is. The host root filesystem is used if the image is not specified.
+    fn resolve_rootfs(&self) -> Result<PathBuf> {
+        match &self.cfg.image {
+            Some(image) if image.contains('/') => Ok(PathBuf::from(image)),
+            Some(image) => self
+                .cfg
+                .local_images_dir
+                .as_ref()
+                .map(|dir| dir.join(image))
+                .ok_or_else(|| anyhow!("Local images directory is not set for image `{image}`")),
+            None => Ok(PathBuf::from("/")),
+        }
+    }
+}
+
+impl Environment for Local {
+    fn new(cfg: &Config) -> Self {
+        const NAME_PREFIX: &str = concat!(env!("CARGO_PKG_NAME"), "-sandbox");
+        Self {
+            cfg: cfg.to_owned(),
+            dir: env::temp_dir().join(format!("{NAME_PREFIX}-{}", Uuid::new_v4())),
+            rootfs: Default::default(),
+            user: None,
+            has_network: cfg.network.is_some(),
+        }
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        if !self.cfg.network_allowlist.is_empty() {
+            return Err(anyhow!(
+                "`network_allowlist` is not supported by the local backend"
+            ));
+        }
+        if self.cfg.storage_limit.is_some() || !self.cfg.tmpfs.is_empty() {
+            warn!("Storage and `tmpfs` size limits are not enforced by the local backend");
+        }
+
+        self.rootfs = self.resolve_rootfs()?;
+        if !fs::metadata(&self.rootfs).await.is_ok_and(|m| m.is_dir()) {
+            return Err(anyhow!(
+                "Image root filesystem not found: {:?}",
+                self.rootfs
+            ));
+        }
+        let user = self.cfg.user.as_deref().unwrap_or("0");
+        self.user = Some(User::find(&self.rootfs, user).await?);
+
+        fs::create_dir_all(self.upper_path()).await?;
+        fs::create_dir_all(self.work_path()).await?;
+        for i in 0..self.cfg.tmpfs.len() {
+            fs::create_dir_all(self.tmpfs_path(i)).await?;
+        }
+        fs::write(self.seccomp_path(), seccomp::filter()?).await?;
+
+        Ok(())
+    }
+
+    fn command(&self, cmd: &process::Command) -> Result<Command> {
+        let user = self
+            .user
+            .as_ref()
+            .ok_or_else(|| anyhow!("Environment is not started"))?;
+
+        // Resource limits are set with a cgroup (via a transient systemd scope), which also
+        // applies to the processes that are created inside the environment
+        let mut limits = vec![];
+        if let Some(mem) = self.cfg.memory_limit {
+            limits.push(format!("MemoryMax={mem}"));
+            limits.push("MemorySwapMax=0".to_owned());
+        }
+        if let Some(pids) = self.cfg.process_limit {
+            limits.push(format!("TasksMax={pids}"));
+        }
+        if let Some(cpu) = self.cfg.cpu_limit {
+            limits.push(format!("CPUQuota={}%", cpu * 100));
+        }
+        let mut bwrap = match limits.is_empty() {
+            true => Command::new("bwrap"),
+            false => {
+                let mut scope = Command::new("systemd-run");
+                scope.args(["--user", "--scope", "--quiet"]);
+                for limit in limits {
+                    scope.arg("--property").arg(limit);
+                }
+                scope.arg("--").arg("bwrap");
+                scope
+            }
+        };
+
+        bwrap
+            .arg("--die-with-parent")
+            .arg("--new-session")
+            .arg("--unshare-all")
+            .arg("--unshare-user")
+            .arg("--disable-userns")
+            .args(["--cap-drop", "ALL"])
+            .args(["--uid", &user.uid.to_string()])
+            .args(["--gid", &user.gid.to_string()]);
+
+        if self.cfg.read_only {
+            bwrap.arg("--ro-bind").arg(&self.rootfs).arg("/");
+        } else {
+            bwrap
+                .arg("--overlay-src")
+                .arg(&self.rootfs)
+                .arg("--overlay")
+                .arg(self.upper_path())
+                .arg(self.work_path())
+                .arg("/");
+        }
+        bwrap.args(["--dev", "/dev"]).args(["--proc", "/proc"]);
+
+        if self.has_network {
+            bwrap.arg("--share-net");
+        }
+        if !self.cfg.tmpfs.iter().any(|(path, _)| path == "/tmp") {
+            bwrap.args(["--tmpfs", "/tmp"]);
+        }
+        // `tmpfs` mounts are host directories in order to persist between commands
+        for (i, (path, _)) in self.cfg.tmpfs.iter().enumerate() {
+            bwrap.arg("--bind").arg(self.tmpfs_path(i)).arg(path);
+        }
+
+        bwrap
+            .args(["--chdir", &user.home])
+            .arg("--clearenv")
+            .args(["--setenv", "PATH", DEFAULT_PATH])
+            .args(["--setenv", "HOME", &user.home])
+            .args(["--setenv", "USER", &user.name]);
+        for (key, value) in cmd.get_envs() {
+            if let Some(value) = value {
+                bwrap.arg("--setenv").arg(key).arg(value);
+            }
+        }
+
+        // The filter is passed by its file descriptor, which must be inherited by `bwrap`
+        let seccomp = std::fs::File::open(self.seccomp_path())?;
+        let fd = seccomp.as_raw_fd();
+        bwrap.args(["--seccomp", &fd.to_string()]);
+        // SAFETY: `fcntl` is async-signal-safe
+        unsafe {
+            bwrap.pre_exec(move || {
+                if libc::fcntl(seccomp.as_raw_fd(), libc::F_SETFD, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        bwrap.arg("--").arg(cmd.get_program()).args(cmd.get_args());
+        Ok(bwrap)
+    }
+
+    async fn isolate(&mut self) -> Result<()> {
+        self.has_network = false;
+        Ok(())
+    }
+
+    async fn stop(&mut self) {
+        fs::remove_dir_all(&self.dir).await.ok();
+    }
+}
+
+/// User of the image
+#[derive(Debug)]
+struct User {
+    /// User name
+    name: String,
+    /// User id
+    uid: u32,
+    /// Primary group id
+    gid: u32,
+    /// Home directory
+    home: String,
+}
+
+impl User {
+    /// Find the user from its name or id in the `/etc/passwd` of the given root filesystem.
+    ///
+    /// Similar to Docker, numeric ids don't need to exist in the image.
+    async fn find(rootfs: &Path, user: &str) -> Result<Self> {
+        let passwd = fs::read_to_string(rootfs.join("etc").join("passwd"))
+            .await
+            .unwrap_or_default();
+        let found = passwd
+            .lines()
+            .map(|line| line.split(':').collect::<Vec<_>>())
+            .filter_map(|fields| match fields[..] {
+                [name, _, uid, gid, _, home, ..] => Some(Self {
+                    name: name.to_owned(),
+                    uid: uid.parse().ok()?,
+                    gid: gid.parse().ok()?,
+                    home: home.to_owned(),
+                }),
+                _ => None,
+            })
+            .find(|found| found.name == user || found.uid.to_string() == user);
+        match (found, user.parse::<u32>()) {
+            (Some(found), _) => Ok(found),
+            (None, Ok(id)) => Ok(Self {
+                name: user.to_owned(),
+                uid: id,
+                gid: id,
+                home: "/".to_owned(),
+            }),
+            (None, Err(_)) => Err(anyhow!("User not found in the image: `{user}`")),
+        }
+    }
+}
@@ -0,0 +1,666 @@
+mod container;
+mod local;
+mod pool;
+mod seccomp;
+
+use std::{
+    fmt,
+    future::Future,
+    path::{Path, PathBuf},
+    process::{self, ExitStatus, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::mpsc::{self, UnboundedSender},
+    task,
+    time::timeout,
+};
+
+pub use self::pool::Pool;
+
+use self::{container::Container, local::Local};
+
+/// Sandbox manager
+#[derive(Debug, Default)]
+pub struct Sandbox<'a> {
+    /// Configuration
+    cfg: Config,
+    /// Actions to run sequentially
+    actions: Vec<Action<'a>>,
+    /// Pool to get the container from
+    pool: Option<Pool>,
+}
+
+impl<'a> Sandbox<'a> {
+    /// Create a new [`Sandbox`] instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the backend to run the sandbox with, defaults to [`Backend::Docker`].
+    #[must_use]
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.cfg.backend = backend;
+        self
+    }
+
+    /// Set the timeout limit for the overall process.
+    #[must_use]
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.cfg.timeout.replace(timeout);
+        self
+    }
+
+    /// Set the image.
+    ///
+    /// For the [`Backend::Local`] backend, this is either the name of the image in
+    /// [`Sandbox::local_images_dir`] or the path to the root filesystem directory of the image
+    /// (defaults to the host root filesystem).
+    #[must_use]
+    pub fn image(mut self, image: impl ToString) -> Self {
+        self.cfg.image.replace(image.to_string());
+        self
+    }
+
+    /// Set the directory of the image root filesystems for the [`Backend::Local`] backend, where
+    /// each image is a directory with the image name, e.g. exported with:
+    ///
+    /// ```sh
+    /// mkdir -p <dir>/<image>
+    /// docker export $(docker create <image>) | tar -x -C <dir>/<image>
+    /// ```
+    #[must_use]
+    pub fn local_images_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cfg.local_images_dir.replace(dir.into());
+        self
+    }
+
+    /// Set the image user (inside the container) by its name or id.
+    // TODO: Make it default to what the image sets `USER` to (Docker defaults to `root`).
+    #[must_use]
+    pub fn user(mut self, user: impl ToString) -> Self {
+        self.cfg.user.replace(user.to_string());
+        self
+    }
+
+    /// Set the CPU (cores) limit.
+    #[must_use]
+    pub fn cpu_limit(mut self, cpu_limit: usize) -> Self {
+        self.cfg.cpu_limit.replace(cpu_limit);
+        self
+    }
+
+    /// Set the memory limit.
+    #[must_use]
+    pub fn memory_limit(mut self, memory_limit: usize) -> Self {
+        self.cfg.memory_limit.replace(memory_limit);
+        self
+    }
+
+    /// Set the process (PIDs) limit.
+    #[must_use]
+    pub fn process_limit(mut self, process_limit: usize) -> Self {
+        self.cfg.process_limit.replace(process_limit);
+        self
+    }
+
+    /// Set the storage (disk) limit in bytes.
+    ///
+    /// This uses `--storage-opt`, which is only supported by some storage drivers (e.g. `overlay2`
+    /// on `xfs` with `pquota`). Use [`Sandbox::read_only`] with [`Sandbox::tmpfs`] to limit the
+    /// storage independently of the storage driver.
+    #[must_use]
+    pub fn storage_limit(mut self, storage_limit: usize) -> Self {
+        self.cfg.storage_limit.replace(storage_limit);
+        self
+    }
+
+    /// Make the root filesystem of the container read-only.
+    ///
+    /// Writable paths can be added with [`Sandbox::tmpfs`].
+    #[must_use]
+    pub fn read_only(mut self) -> Self {
+        self.cfg.read_only = true;
+        self
+    }
+
+    /// Mount a writable in-memory filesystem at the given container `path` with a `size` limit in
+    /// bytes.
+    ///
+    /// Note that the existing files of the image at `path` are not accessible after mounting.
+    #[must_use]
+    pub fn tmpfs(mut self, path: impl ToString, size: usize) -> Self {
+        self.cfg.tmpfs.push((path.to_string(), size));
+        self
+    }
+
+    /// Set the network to connect the container to.
+    ///
+    /// Containers don't have network access by default. For the [`Backend::Local`] backend, the
+    /// value is ignored and the host network is shared.
+    #[must_use]
+    pub fn network(mut self, network: impl ToString) -> Self {
+        self.cfg.network.replace(network.to_string());
+        self
+    }
+
+    /// Only allow network access to the given containers (e.g. a registry mirror).
+    ///
+    /// An internal network (without external access) is created for the sandbox and the given
+    /// containers are connected to it, meaning they're accessible via their container names.
+    ///
+    /// This can't be used together with [`Sandbox::network`], and it's not supported by the
+    /// [`Backend::Local`] backend.
+    #[must_use]
+    pub fn network_allowlist<I, S>(mut self, containers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        self.cfg
+            .network_allowlist
+            .extend(containers.into_iter().map(|c| c.to_string()));
+        self
+    }
+
+    /// Command to run in a sandboxed environment.
+    #[must_use]
+    pub fn command(mut self, cmd: &'a Command) -> Self {
+        self.actions.push(Action::Run(cmd));
+        self
+    }
+
+    /// Copy the files from or to the container.
+    ///
+    /// Unlike Docker, relative paths default to the one set by the image `WORKDIR`.
+    ///
+    /// Files are copied via `tar` (must exist in the image) rather than `docker cp` in order to
+    /// support read-only containers and `tmpfs` mounts.
+    ///
+    /// # Arguments
+    ///
+    /// Regular paths with container being special-cased as: `container:<path>`. Exactly one of the
+    /// paths must be a container path.
+    ///
+    /// `src` is copied into the `dst` directory (created if it doesn't exist). Similar to Docker,
+    /// `<src>/.` copies the contents of the `src` directory rather than the directory itself.
+    #[must_use]
+    pub fn copy<P1, P2>(mut self, src: P1, dst: P2) -> Self
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        self.actions.push(Action::Copy(
+            src.as_ref().to_path_buf(),
+            dst.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    /// Disconnect the container from the network for the remaining actions.
+    ///
+    /// This is useful when only some of the actions need network access (e.g. installing packages
+    /// before building them).
+    #[must_use]
+    pub fn isolate(mut self) -> Self {
+        self.actions.push(Action::Isolate);
+        self
+    }
+
+    /// Start the sandboxed process.
+    pub async fn run(self) -> Result<Output> {
+        // Events are discarded when the receiver is dropped
+        let (events, _) = mpsc::unbounded_channel();
+        self.run_streaming(events).await
+    }
+
+    /// Start the sandboxed process and send the [`Event`]s of its commands to `events` as they
+    /// happen (e.g. to report progress).
+    ///
+    /// The channel is closed when the run completes.
+    pub async fn run_streaming(self, events: UnboundedSender<Event>) -> Result<Output> {
+        match (self.pool.clone(), self.cfg.backend) {
+            (Some(pool), Backend::Docker | Backend::Podman) => self.run_pooled(pool, events).await,
+            (_, Backend::Docker | Backend::Podman) => self.run_in::<Container>(events).await,
+            (_, Backend::Local) => self.run_in::<Local>(events).await,
+        }
+    }
+
+    /// Run the actions in a new [`Environment`].
+    async fn run_in<E: Environment>(self, events: UnboundedSender<Event>) -> Result<Output> {
+        let mut env = E::new(&self.cfg);
+        let result = self
+            .with_timeout(async {
+                env.start().await?;
+                self.run_actions(&mut env, &events).await
+            })
+            .await;
+
+        // Cleanup (also runs when the environment was only partially started)
+        env.stop().await;
+
+        result
+    }
+
+    /// Run the actions in a container from the pool.
+    async fn run_pooled(self, pool: Pool, events: UnboundedSender<Event>) -> Result<Output> {
+        let mut pooled = None;
+        let result = self
+            .with_timeout(async {
+                let pooled = pooled.insert(pool.get().await?);
+                self.run_actions(&mut pooled.container, &events).await
+            })
+            .await;
+
+        // Containers are only reused if all of the actions have completed
+        if let Some(pooled) = pooled {
+            pool.put(pooled, result.is_ok());
+        }
+
+        result
+    }
+
+    /// Wait for the run to complete within the timeout limit.
+    async fn with_timeout(&self, fut: impl Future<Output = Result<Output>>) -> Result<Output> {
+        match self.cfg.timeout {
+            Some(to) => match timeout(Duration::from_secs(to), fut).await {
+                Ok(res) => res,
+                Err(_) => Err(anyhow!("Timed out")),
+            },
+            _ => fut.await,
+        }
+    }
+
+    /// Run the actions sequentially in the given started environment.
+    async fn run_actions<E: Environment>(
+        &self,
+        env: &mut E,
+        events: &UnboundedSender<Event>,
+    ) -> Result<Output> {
+        let mut all_output = Output {
+            status: Default::default(),
+            stderr: Default::default(),
+            stdout: Default::default(),
+            commands: Default::default(),
+        };
+        for (i, action) in self.actions.iter().enumerate() {
+            match action {
+                Action::Copy(src, dst) => {
+                    match (Action::container_path(src), Action::container_path(dst)) {
+                        (None, Some(dst)) => env.copy_in(src, dst).await?,
+                        (Some(src), None) => env.copy_out(src, dst).await?,
+                        _ => {
+                            return Err(anyhow!(
+                                "Exactly one of the copy paths must be a container path"
+                            ))
+                        }
+                    }
+                }
+                Action::Isolate => env.isolate().await?,
+                Action::Run(cmd) => {
+                    let start = Instant::now();
+                    events
+                        .send(Event::Started {
+                            action: i,
+                            command: display_cmd(cmd.as_std()),
+                        })
+                        .ok();
+                    let status =
+                        exec(env.command(cmd.as_std())?, i, events, &mut all_output).await?;
+                    let duration = start.elapsed();
+                    events
+                        .send(Event::Exited {
+                            action: i,
+                            status,
+                            duration,
+                        })
+                        .ok();
+
+                    all_output.status = status;
+                    all_output.commands.push(CommandOutput {
+                        action: i,
+                        status,
+                        duration,
+                    });
+                    if !status.success() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(all_output)
+    }
+}
+
+/// Output of a sandbox run
+#[derive(Debug)]
+pub struct Output {
+    /// Exit status of the last command
+    pub status: ExitStatus,
+    /// Stdout of all commands
+    pub stdout: Vec<u8>,
+    /// Stderr of all commands
+    pub stderr: Vec<u8>,
+    /// Results of the commands that have run, in order
+    pub commands: Vec<CommandOutput>,
+}
+
+/// Result of a single command
+#[derive(Clone, Copy, Debug)]
+pub struct CommandOutput {
+    /// Index of the action (as added to the [`Sandbox`])
+    pub action: usize,
+    /// Exit status
+    pub status: ExitStatus,
+    /// How long the command took
+    pub duration: Duration,
+}
+
+/// Event of a sandbox command
+///
+/// `action` is the index of the action (as added to the [`Sandbox`]) the event belongs to.
+#[derive(Debug)]
+pub enum Event {
+    /// Command has started
+    Started { action: usize, command: String },
+    /// Chunk of the command's stdout
+    Stdout { action: usize, chunk: Vec<u8> },
+    /// Chunk of the command's stderr
+    Stderr { action: usize, chunk: Vec<u8> },
+    /// Command has exited
+    Exited {
+        action: usize,
+        status: ExitStatus,
+        duration: Duration,
+    },
+}
+
+/// Sandbox backend
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Docker containers
+    #[default]
+    Docker,
+    /// Podman containers (works rootless and without a daemon)
+    Podman,
+    /// Local processes isolated with Linux namespaces and a seccomp filter via [`bubblewrap`]
+    /// (`0.10` or later).
+    ///
+    /// This backend doesn't require a container engine, but only supports a subset of the
+    /// features, e.g. storage limits are not enforced. CPU, memory and process limits are set
+    /// with a cgroup via `systemd-run --user`, meaning they require a systemd user instance.
+    ///
+    /// [`bubblewrap`]: https://github.com/containers/bubblewrap
+    Local,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Docker => write!(f, "docker"),
+            Self::Podman => write!(f, "podman"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docker" => Ok(Self::Docker),
+            "podman" => Ok(Self::Podman),
+            "local" => Ok(Self::Local),
+            _ => Err(anyhow!("Unknown sandbox backend: `{s}`")),
+        }
+    }
+}
+
+/// An isolated environment created by a [`Backend`].
+///
+/// Creating the value must not allocate any resources, resources should only be allocated in
+/// [`Environment::start`] and they must be freed in [`Environment::stop`].
+trait Environment {
+    /// Create a new environment from the sandbox config.
+    fn new(cfg: &Config) -> Self;
+
+    /// Start the environment.
+    async fn start(&mut self) -> Result<()>;
+
+    /// Create a host command that runs `cmd` inside the environment.
+    fn command(&self, cmd: &process::Command) -> Result<Command>;
+
+    /// Copy the host path `src` into the environment directory `dst`.
+    async fn copy_in(&self, src: &Path, dst: &str) -> Result<()> {
+        let archive = pack(src).await?;
+        let mut cmd = process::Command::new("sh");
+        cmd.args(["-c", r#"mkdir -p "$1" && tar -xf - -C "$1""#, "sh", dst]);
+        let mut child = self
+            .command(&cmd)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("`stdin` is piped");
+        stdin.write_all(&archive).await?;
+        drop(stdin);
+
+        if !child.wait().await?.success() {
+            return Err(anyhow!("Failed to copy to container: `{dst}`"));
+        }
+
+        Ok(())
+    }
+
+    /// Copy the environment path `src` into the host directory `dst`.
+    async fn copy_out(&self, src: &str, dst: &Path) -> Result<()> {
+        let (dir, name) = match src.strip_suffix("/.") {
+            Some(dir) => (dir, "."),
+            None => {
+                let path = Path::new(src);
+                let dir = path
+                    .parent()
+                    .and_then(Path::to_str)
+                    .filter(|dir| !dir.is_empty())
+                    .unwrap_or(".");
+                let name = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or_else(|| anyhow!("Invalid copy path: {src}"))?;
+                (dir, name)
+            }
+        };
+
+        let mut cmd = process::Command::new("tar");
+        cmd.args(["-cf", "-", "-C", dir, name]);
+        let output = self
+            .command(&cmd)?
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow!("Failed to copy from container: `{src}`"));
+        }
+
+        unpack(output.stdout, dst).await
+    }
+
+    /// Disconnect the environment from the network.
+    async fn isolate(&mut self) -> Result<()>;
+
+    /// Stop the environment and free all of its resources.
+    async fn stop(&mut self);
+}
+
+/// Sandbox configuration
+#[derive(Clone, Debug, Default)]
+struct Config {
+    /// Backend to run the sandbox with
+    backend: Backend,
+    /// Timeout limit
+    timeout: Option<u64>,
+    /// Image
+    image: Option<String>,
+    /// Directory of the image root filesystems for the local backend
+    local_images_dir: Option<PathBuf>,
+    /// Image user
+    user: Option<String>,
+    /// CPU (cores) limit
+    cpu_limit: Option<usize>,
+    /// Memory limit (in bytes)
+    memory_limit: Option<usize>,
+    /// Process (PIDs) limit
+    process_limit: Option<usize>,
+    /// Storage limit (in bytes)
+    storage_limit: Option<usize>,
+    /// Whether the root filesystem is read-only
+    read_only: bool,
+    /// `tmpfs` mounts (path and size in bytes)
+    tmpfs: Vec<(String, usize)>,
+    /// Network (no network access if not specified)
+    network: Option<String>,
+    /// Containers to allow network access to
+    network_allowlist: Vec<String>,
+}
+
+/// Sandbox action
+#[derive(Debug)]
+enum Action<'a> {
+    /// Run a command
+    Run(&'a Command),
+    /// Copy from or to the container
+    Copy(PathBuf, PathBuf),
+    /// Disconnect from the network
+    Isolate,
+}
+
+impl Action<'_> {
+    /// Get the container path from the custom `container:<path>` syntax, `None` if the path is not
+    /// a container path.
+    fn container_path(path: &Path) -> Option<&str> {
+        path.to_str()
+            .and_then(|path| path.strip_prefix("container:"))
+    }
+}
+
+/// Create a `tar` archive from the given path.
+///
+/// Similar to Docker, `<path>/.` archives the contents of the directory rather than the directory
+/// itself. Symlinks are archived as is (not followed) in order to not leak files outside of `path`.
+async fn pack(path: &Path) -> Result<Vec<u8>> {
+    let path = path.to_owned();
+    task::spawn_blocking(move || {
+        let mut builder = tar::Builder::new(vec![]);
+        builder.follow_symlinks(false);
+        if path.to_str().is_some_and(|path| path.ends_with("/.")) {
+            builder.append_dir_all(".", &path)?;
+        } else {
+            let name = path
+                .file_name()
+                .ok_or_else(|| anyhow!("Invalid copy path: {path:?}"))?;
+            if path.is_dir() {
+                builder.append_dir_all(name, &path)?;
+            } else {
+                builder.append_path_with_name(&path, name)?;
+            }
+        }
+
+        builder.into_inner().map_err(Into::into)
+    })
+    .await?
+}
+
+/// Unpack the `tar` archive into the `dst` directory (created if it doesn't exist).
+async fn unpack(archive: Vec<u8>, dst: &Path) -> Result<()> {
+    let dst = dst.to_owned();
+    task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dst)?;
+        tar::Archive::new(archive.as_slice()).unpack(&dst)
+    })
+    .await?
+    .map_err(Into::into)
+}
+
+/// Run the command of the given action, streaming its output as [`Event`]s while also appending
+/// it to `all_output`.
+async fn exec(
+    mut cmd: Command,
+    action: usize,
+    events: &UnboundedSender<Event>,
+    all_output: &mut Output,
+) -> Result<ExitStatus> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdout = child.stdout.take().expect("`stdout` is piped");
+    let mut stderr = child.stderr.take().expect("`stderr` is piped");
+
+    let mut stdout_buf = [0; 8192];
+    let mut stderr_buf = [0; 8192];
+    let (mut stdout_done, mut stderr_done) = (false, false);
+    while !(stdout_done && stderr_done) {
+        tokio::select! {
+            n = stdout.read(&mut stdout_buf), if !stdout_done => match n? {
+                0 => stdout_done = true,
+                n => {
+                    let chunk = stdout_buf[..n].to_vec();
+                    all_output.stdout.extend_from_slice(&chunk);
+                    events.send(Event::Stdout { action, chunk }).ok();
+                }
+            },
+            n = stderr.read(&mut stderr_buf), if !stderr_done => match n? {
+                0 => stderr_done = true,
+                n => {
+                    let chunk = stderr_buf[..n].to_vec();
+                    all_output.stderr.extend_from_slice(&chunk);
+                    events.send(Event::Stderr { action, chunk }).ok();
+                }
+            },
+        }
+    }
+
+    child.wait().await.map_err(Into::into)
+}
+
+/// Run a command and error with its stderr if it fails.
+async fn run_cmd(cmd: &mut Command) -> Result<()> {
+    let output = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to run `{}`: {}",
+            display_cmd(cmd.as_std()),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the displayable form of the command, e.g. `bundle build --bundler webpack`.
+fn display_cmd(cmd: &process::Command) -> String {
+    [cmd.get_program()]
+        .into_iter()
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
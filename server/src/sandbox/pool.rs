@@ -0,0 +1,192 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::Result;
+
+use super::{container::Container, Config, Environment, Sandbox};
+use crate::log::warn;
+
+/// Pool of pre-started containers for sandboxes with the same configuration.
+///
+/// Pooled containers are reset and reused after each run until they reach the maximum amount of
+/// uses. Only read-only containers can be reset (see [`Sandbox::read_only`]), containers with a
+/// writable root filesystem are always used once.
+///
+/// The pool is ignored by the [`Backend::Local`](super::Backend::Local) backend, since it doesn't
+/// have a startup cost.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Configuration of the pooled containers
+    cfg: Config,
+    /// Amount of idle containers to keep
+    size: usize,
+    /// Maximum amount of uses per container
+    max_uses: usize,
+    /// Idle containers
+    idle: Mutex<Vec<Pooled>>,
+    /// Amount of containers that are being started or reset
+    pending: AtomicUsize,
+    /// Whether the pool is closed
+    is_closed: AtomicBool,
+}
+
+/// Pooled container
+#[derive(Debug)]
+pub(super) struct Pooled {
+    /// Container
+    pub(super) container: Container,
+    /// Amount of times the container has been used
+    uses: usize,
+}
+
+impl Pool {
+    /// Create a new pool that keeps `size` idle containers configured from the `sandbox` template.
+    ///
+    /// Actions of the template are ignored. Containers are recycled after `max_uses` runs.
+    pub fn new(sandbox: Sandbox<'_>, size: usize, max_uses: usize) -> Self {
+        let max_uses = match sandbox.cfg.read_only {
+            true => max_uses.max(1),
+            false => {
+                if max_uses > 1 {
+                    warn!("Pooled containers are used once because the sandbox is not read-only");
+                }
+                1
+            }
+        };
+        let pool = Self {
+            inner: Arc::new(Inner {
+                cfg: sandbox.cfg,
+                size,
+                max_uses,
+                idle: Default::default(),
+                pending: Default::default(),
+                is_closed: Default::default(),
+            }),
+        };
+        pool.fill();
+        pool
+    }
+
+    /// Create a new [`Sandbox`] that runs in a pooled container.
+    ///
+    /// Container options (e.g. image and limits) are set by the pool and can't be changed.
+    #[must_use]
+    pub fn sandbox<'a>(&self) -> Sandbox<'a> {
+        Sandbox {
+            cfg: self.inner.cfg.clone(),
+            actions: vec![],
+            pool: Some(self.clone()),
+        }
+    }
+
+    /// Close the pool and stop all of its idle containers.
+    pub async fn close(&self) {
+        self.inner.is_closed.store(true, Ordering::Relaxed);
+        let idle = std::mem::take(&mut *self.inner.idle.lock().expect("Poisoned lock"));
+        for mut pooled in idle {
+            pooled.container.stop().await;
+        }
+    }
+
+    /// Get a healthy container from the pool, or start a new one if there are no idle containers.
+    pub(super) async fn get(&self) -> Result<Pooled> {
+        let pooled = loop {
+            let pooled = self.inner.idle.lock().expect("Poisoned lock").pop();
+            match pooled {
+                Some(mut pooled) => {
+                    if pooled.container.is_healthy().await {
+                        break pooled;
+                    }
+
+                    warn!("Removing unhealthy pooled container");
+                    pooled.container.stop().await;
+                }
+                None => break self.start().await?,
+            }
+        };
+
+        self.fill();
+        Ok(pooled)
+    }
+
+    /// Return the container to the pool after use.
+    ///
+    /// The container is reset in the background if it's reusable, and stopped otherwise.
+    pub(super) fn put(&self, mut pooled: Pooled, is_reusable: bool) {
+        pooled.uses += 1;
+
+        let pool = self.clone();
+        pool.inner.pending.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(async move {
+            if is_reusable && pooled.uses < pool.inner.max_uses {
+                match pooled.container.reset().await {
+                    Ok(()) => {
+                        pool.add_idle(pooled).await;
+                        pool.inner.pending.fetch_sub(1, Ordering::Relaxed);
+                        return;
+                    }
+                    Err(e) => warn!("Failed to reset pooled container: {e}"),
+                }
+            }
+
+            pooled.container.stop().await;
+            pool.inner.pending.fetch_sub(1, Ordering::Relaxed);
+            pool.fill();
+        });
+    }
+
+    /// Start new containers in the background until the pool is full.
+    fn fill(&self) {
+        loop {
+            let idle = self.inner.idle.lock().expect("Poisoned lock").len();
+            let pending = self.inner.pending.load(Ordering::Relaxed);
+            if idle + pending >= self.inner.size || self.inner.is_closed.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let pool = self.clone();
+            pool.inner.pending.fetch_add(1, Ordering::Relaxed);
+            tokio::spawn(async move {
+                match pool.start().await {
+                    Ok(pooled) => pool.add_idle(pooled).await,
+                    Err(e) => warn!("Failed to start pooled container: {e}"),
+                }
+                pool.inner.pending.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// Start a new container.
+    async fn start(&self) -> Result<Pooled> {
+        let mut container = Container::new(&self.inner.cfg);
+        if let Err(e) = container.start().await {
+            container.stop().await;
+            return Err(e);
+        }
+
+        Ok(Pooled { container, uses: 0 })
+    }
+
+    /// Add the container to the idle containers, or stop it if the pool is full or closed.
+    async fn add_idle(&self, pooled: Pooled) {
+        let excess = {
+            let mut idle = self.inner.idle.lock().expect("Poisoned lock");
+            if idle.len() < self.inner.size && !self.inner.is_closed.load(Ordering::Relaxed) {
+                idle.push(pooled);
+                None
+            } else {
+                Some(pooled)
+            }
+        };
+        if let Some(mut pooled) = excess {
+            pooled.container.stop().await;
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+
+/// Syscalls that are denied (with `EPERM`) inside the sandbox as `(name, x86_64, aarch64)`.
+///
+/// Similar to the default profiles of Docker and Flatpak, these are the syscalls that are either
+/// privileged or commonly used for escaping the sandbox, while the rest are allowed.
+const DENIED_SYSCALLS: &[(&str, u32, u32)] = &[
+    // Namespaces and mounts
+    ("mount", 165, 40),
+    ("umount2", 166, 39),
+    ("pivot_root", 155, 41),
+    ("unshare", 272, 97),
+    ("setns", 308, 268),
+    ("open_tree", 428, 428),
+    ("move_mount", 429, 429),
+    ("fsopen", 430, 430),
+    ("fsconfig", 431, 431),
+    ("fsmount", 432, 432),
+    ("fspick", 433, 433),
+    // Other processes
+    ("ptrace", 101, 117),
+    ("process_vm_readv", 310, 270),
+    ("process_vm_writev", 311, 271),
+    // Kernel
+    ("bpf", 321, 280),
+    ("perf_event_open", 298, 241),
+    ("userfaultfd", 323, 282),
+    ("io_uring_setup", 425, 425),
+    ("io_uring_enter", 426, 426),
+    ("io_uring_register", 427, 427),
+    ("init_module", 175, 105),
+    ("finit_module", 313, 273),
+    ("delete_module", 176, 106),
+    ("kexec_load", 246, 104),
+    ("syslog", 103, 116),
+    ("acct", 163, 89),
+    ("quotactl", 179, 60),
+    ("swapon", 167, 224),
+    ("swapoff", 168, 225),
+    ("reboot", 169, 142),
+    // Kernel keyring
+    ("add_key", 248, 217),
+    ("request_key", 249, 218),
+    ("keyctl", 250, 219),
+    // File handles (can open files outside of the mount namespace)
+    ("name_to_handle_at", 303, 264),
+    ("open_by_handle_at", 304, 265),
+];
+
+/// `AUDIT_ARCH_*` value of the target architecture
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+
+/// Offsets of `struct seccomp_data`
+const DATA_NR_OFFSET: u32 = 0;
+const DATA_ARCH_OFFSET: u32 = 4;
+
+/// Classic BPF instruction codes
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+
+/// Seccomp return values
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const EPERM: u32 = 1;
+
+/// Start of the x32 ABI syscall numbers (which would bypass the x86_64 numbers)
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// Create the compiled seccomp filter (`struct sock_filter` array) that denies the
+/// [`DENIED_SYSCALLS`], in the format `bwrap --seccomp` expects.
+///
+/// Syscalls of other architectures (e.g. 32-bit syscalls on a 64-bit host) kill the process.
+pub(super) fn filter() -> Result<Vec<u8>> {
+    let arch =
+        AUDIT_ARCH.ok_or_else(|| anyhow!("Seccomp is not supported on this architecture"))?;
+    let is_x86_64 = cfg!(target_arch = "x86_64");
+    let denied = DENIED_SYSCALLS
+        .iter()
+        .map(|&(_, x86_64, aarch64)| if is_x86_64 { x86_64 } else { aarch64 });
+
+    let mut insts = vec![
+        (BPF_LD_W_ABS, 0, 0, DATA_ARCH_OFFSET),
+        (BPF_JMP_JEQ_K, 1, 0, arch),
+        (BPF_RET_K, 0, 0, SECCOMP_RET_KILL_PROCESS),
+        (BPF_LD_W_ABS, 0, 0, DATA_NR_OFFSET),
+    ];
+
+    // Jumps to the deny instruction (the last one) are resolved after all checks are added
+    let checks_start = insts.len();
+    if is_x86_64 {
+        insts.push((BPF_JMP_JGE_K, 0, 0, X32_SYSCALL_BIT));
+    }
+    insts.extend(denied.map(|nr| (BPF_JMP_JEQ_K, 0, 0, nr)));
+    insts.push((BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW));
+    insts.push((BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | EPERM));
+
+    let deny_index = insts.len() - 1;
+    for (i, inst) in insts.iter_mut().enumerate().skip(checks_start) {
+        if inst.0 != BPF_RET_K {
+            inst.1 = u8::try_from(deny_index - (i + 1))?;
+        }
+    }
+
+    Ok(insts
+        .into_iter()
+        .flat_map(|(code, jt, jf, k)| {
+            [&code.to_ne_bytes()[..], &[jt, jf], &k.to_ne_bytes()].concat()
+        })
+        .collect())
+}
@@ -0,0 +1,310 @@
+use std::{collections::HashSet, fs};
+
+use axum::http::StatusCode;
+use solpg_server_client::{BuildRequest, BuildResponse, ChangeKind, Error, ToolchainSelector};
+
+use super::TestServer;
+
+/// Anchor program with an account of the given field type
+fn anchor_program(count_ty: &str) -> String {
+    format!(
+        r#"
+use anchor_lang::prelude::*;
+
+declare_id!("11111111111111111111111111111111");
+
+#[program]
+mod counter {{
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {{
+        Ok(())
+    }}
+}}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {{
+    #[account(mut)]
+    pub signer: Signer<'info>,
+}}
+
+#[account]
+pub struct Counter {{
+    pub count: {count_ty},
+}}
+"#
+    )
+}
+
+/// Create a build request of the given `/src/lib.rs` content.
+fn request(lib: &str) -> BuildRequest {
+    BuildRequest {
+        files: vec![("/src/lib.rs".to_owned(), lib.to_owned())],
+        ..Default::default()
+    }
+}
+
+/// Create a build request of the given `/src/lib.rs` content with the toolchain that supports the
+/// IDL generation (Anchor `0.29`).
+fn anchor_request(lib: &str) -> BuildRequest {
+    BuildRequest {
+        toolchain: Some(ToolchainSelector {
+            platform_tools: None,
+            anchor: Some("0.29".to_owned()),
+        }),
+        ..request(lib)
+    }
+}
+
+/// Get the status and the message of the server error.
+fn server_error<T: std::fmt::Debug>(result: Result<T, Error>) -> (StatusCode, String) {
+    match result.unwrap_err() {
+        Error::Server { status, message } => (status, message),
+        e => panic!("Unexpected error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn build_and_deploy() {
+    let server = TestServer::start().await;
+    let resp = server.client.build(&request("// v1")).await.unwrap();
+    assert!(resp.stderr.contains("Finished"));
+    assert!(resp.idl.is_none());
+    let uuid = resp.uuid.unwrap();
+    assert_eq!(server.client.deploy(&uuid).await.unwrap(), b"// v1");
+
+    // Rebuilding the same program replaces the files
+    let mut req = request("// v2");
+    req.files
+        .push(("/src/other.rs".to_owned(), "// other".to_owned()));
+    req.uuid = Some(uuid.clone());
+    let resp = server.client.build(&req).await.unwrap();
+    assert!(resp.uuid.is_none());
+    let binary = server.client.deploy(&uuid).await.unwrap();
+    assert_eq!(binary, b"// v2// other");
+
+    req.files.pop();
+    server.client.build(&req).await.unwrap();
+    assert_eq!(server.client.deploy(&uuid).await.unwrap(), b"// v2");
+}
+
+#[tokio::test]
+async fn compile_error() {
+    let server = TestServer::start().await;
+    let resp = server
+        .client
+        .build(&request("compile_error!(\"\");"))
+        .await
+        .unwrap();
+    assert!(resp.stderr.contains("error: could not compile"));
+    assert!(resp.idl.is_none());
+
+    let result = server.client.deploy(&resp.uuid.unwrap()).await;
+    assert_eq!(
+        server_error(result),
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Program is not built".to_owned()
+        )
+    );
+}
+
+#[tokio::test]
+async fn rebuild_compile_error() {
+    let server = TestServer::start().await;
+    let resp = server.client.build(&request("// v1")).await.unwrap();
+    let uuid = resp.uuid.unwrap();
+    assert_eq!(server.client.deploy(&uuid).await.unwrap(), b"// v1");
+
+    // The binary of the previous build must not be returned after a failed build
+    let mut req = request("compile_error!(\"\");");
+    req.uuid = Some(uuid.clone());
+    let resp = server.client.build(&req).await.unwrap();
+    assert!(resp.stderr.contains("error: could not compile"));
+    let (_, message) = server_error(server.client.deploy(&uuid).await);
+    assert_eq!(message, "Program is not built");
+}
+
+#[tokio::test]
+async fn invalid_paths() {
+    let server = TestServer::start().await;
+    let long_path = format!("/src/{}.rs", "a".repeat(128));
+    for path in [
+        "/src/../lib.rs",
+        "/src//lib.rs",
+        "/src/lib.txt",
+        "/Cargo.toml",
+        "src/lib.rs",
+        "/src/lib.rs/../../Cargo.toml",
+        &long_path,
+    ] {
+        let req = BuildRequest {
+            files: vec![(path.to_owned(), String::new())],
+            ..Default::default()
+        };
+        let (status, message) = server_error(server.client.build(&req).await);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(message, format!("Invalid path: {path}"));
+    }
+
+    // Valid paths in nested directories
+    let mut req = request("");
+    req.files
+        .push(("/src/instructions/mod-1.rs".to_owned(), String::new()));
+    server.client.build(&req).await.unwrap();
+}
+
+#[tokio::test]
+async fn too_many_files() {
+    let server = TestServer::start().await;
+    let req = BuildRequest {
+        files: (0..65)
+            .map(|i| (format!("/src/{i}.rs"), String::new()))
+            .collect(),
+        ..Default::default()
+    };
+    let (_, message) = server_error(server.client.build(&req).await);
+    assert_eq!(message, "Exceeded maximum file amount: 65 > 64");
+}
+
+#[tokio::test]
+async fn invalid_uuid() {
+    let server = TestServer::start().await;
+    let mut req = request("");
+    req.uuid = Some("../programs".to_owned());
+    let (_, message) = server_error(server.client.build(&req).await);
+    assert_eq!(message, "Invalid UUID");
+}
+
+#[tokio::test]
+async fn payload_limit() {
+    let server = TestServer::start_with(|config| config.build.payload_limit = Some(64)).await;
+    let (status, _) = server_error(server.client.build(&request(&"a".repeat(64))).await);
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    server.client.build(&request("")).await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrency_ids() {
+    let server = TestServer::start_with(|config| config.build.concurrency = 2).await;
+    let builds = (0..4)
+        .map(|_| {
+            let client = server.client.clone();
+            tokio::spawn(async move { client.build(&request("sleep!();")).await.unwrap() })
+        })
+        .collect::<Vec<_>>();
+    let mut resps = vec![];
+    for build in builds {
+        resps.push(build.await.unwrap());
+    }
+
+    // Concurrent builds must never share a concurrency directory
+    let concurrency_id = |resp: &BuildResponse| {
+        let uuid = resp.uuid.as_ref().unwrap();
+        let path = server.env.program(uuid).join("concurrency_id");
+        fs::read_to_string(path).unwrap().trim().to_owned()
+    };
+    let ids = resps
+        .iter()
+        .map(|resp| {
+            assert!(resp.stderr.contains("Finished"), "{}", resp.stderr);
+            concurrency_id(resp)
+        })
+        .collect::<HashSet<_>>();
+    assert_eq!(ids, HashSet::from(["0".to_owned(), "1".to_owned()]));
+    assert!(!server.env.toolchain.join("2").exists());
+
+    // Ids are released after the builds
+    let resp = server.client.build(&request("")).await.unwrap();
+    assert_eq!(concurrency_id(&resp), "0");
+}
+
+#[tokio::test]
+async fn toolchains() {
+    let server = TestServer::start().await;
+    let toolchains = server.client.toolchains().await.unwrap();
+    let toolchains = toolchains
+        .iter()
+        .map(|t| (t.platform_tools.as_str(), t.anchor.as_str(), t.default))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        toolchains,
+        [("1.41", "0.30.1", true), ("1.37", "0.29.0", false)]
+    );
+
+    let mut req = request("");
+    req.toolchain = Some(ToolchainSelector {
+        platform_tools: None,
+        anchor: Some("0.29".to_owned()),
+    });
+    let resp = server.client.build(&req).await.unwrap();
+    assert!(resp.stderr.contains("Finished"));
+    assert!(server.env.other_toolchain.join("0").join("ready").exists());
+
+    req.toolchain = Some(ToolchainSelector {
+        platform_tools: Some("1.41".to_owned()),
+        anchor: Some("0.29".to_owned()),
+    });
+    let (_, message) = server_error(server.client.build(&req).await);
+    assert_eq!(
+        message,
+        "Unsupported toolchain: platform-tools 1.41, Anchor 0.29"
+    );
+}
+
+#[tokio::test]
+async fn idl_unsupported_toolchain() {
+    let server = TestServer::start().await;
+    let resp = server
+        .client
+        .build(&anchor_request(&anchor_program("u64")))
+        .await
+        .unwrap();
+
+    let mut req = request(&anchor_program("u64"));
+    req.previous_idl = resp.idl;
+    let resp = server.client.build(&req).await.unwrap();
+    assert!(resp.stderr.contains("Finished"));
+    assert!(resp.stderr.contains(
+        "IDL error: IDL generation is only supported with Anchor 0.29 (toolchain Anchor version: \
+         0.30.1)"
+    ));
+    assert!(resp.idl.is_none());
+    assert!(resp.compatibility.is_none());
+}
+
+#[tokio::test]
+async fn idl_compatibility() {
+    let server = TestServer::start().await;
+    let resp = server
+        .client
+        .build(&anchor_request(&anchor_program("u64")))
+        .await
+        .unwrap();
+    let idl = resp.idl.unwrap();
+    assert!(resp.compatibility.is_none());
+
+    let mut req = anchor_request(&anchor_program("u64"));
+    req.previous_idl = Some(idl.clone());
+    let report = server.client.build(&req).await.unwrap().compatibility;
+    let report = report.unwrap();
+    assert!(!report.is_breaking);
+    assert!(report.changes.is_empty());
+
+    let mut req = anchor_request(&anchor_program("u128"));
+    req.previous_idl = Some(idl);
+    let report = server.client.build(&req).await.unwrap().compatibility;
+    let report = report.unwrap();
+    assert!(report.is_breaking);
+    let changes = report
+        .changes
+        .iter()
+        .map(|change| (change.kind, change.path.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(changes, [(ChangeKind::Resized, "accounts.Counter.count")]);
+
+    req.previous_idl = Some(serde_json::json!({ "name": "invalid" }));
+    let (_, message) = server_error(server.client.build(&req).await);
+    assert!(message.starts_with("Invalid previous IDL"));
+}
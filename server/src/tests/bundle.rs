@@ -0,0 +1,128 @@
+use solpg_server::package::PackageManager;
+use solpg_server_client::{BundleRequest, Error, LockFormat};
+use uuid::Uuid;
+
+use super::TestServer;
+
+#[tokio::test]
+async fn progress() {
+    let server = TestServer::start().await;
+    let req = request(&[]);
+    let mut events = vec![];
+    let etag = server
+        .client
+        .bundle_progress(&req, |event| events.push(event))
+        .await
+        .unwrap();
+
+    let names = events
+        .iter()
+        .map(|event| event.event.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["started", "stdout", "exited", "started", "stderr", "exited"]
+    );
+    assert!(events[0].data.contains("bundle install"));
+    assert!(events[1].data.contains("Installed packages"));
+    assert!(events[2].data.contains(r#""code":0"#));
+
+    // The bundle is cached
+    let bundle = server.client.bundle(&req, None).await.unwrap().unwrap();
+    assert_eq!(bundle.etag.as_deref(), Some(etag.as_str()));
+    assert!(!bundle.archive.is_empty());
+    assert!(server
+        .client
+        .bundle(&req, Some(&etag))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn progress_error() {
+    let server = TestServer::start().await;
+    let mut events = vec![];
+    let err = server
+        .client
+        .bundle_progress(&request(&["fail"]), |event| events.push(event))
+        .await
+        .unwrap_err();
+    match err {
+        Error::Server { message, .. } => {
+            assert!(message.contains("Failed to install"), "{message}")
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+
+    let stderr = events.iter().find(|event| event.event == "stderr").unwrap();
+    assert!(stderr.data.contains("Failed to install"));
+}
+
+#[tokio::test]
+async fn cache_eviction() {
+    // Only the latest bundle fits in the cache
+    let server = TestServer::start_with(|config| config.bundle.cache_max_bytes = 1).await;
+    let is_bundled = |req| {
+        let client = server.client.clone();
+        async move {
+            let mut events = vec![];
+            client
+                .bundle_progress(&req, |event| events.push(event))
+                .await
+                .unwrap();
+            !events.is_empty()
+        }
+    };
+
+    let (first, second) = (request(&[]), request(&[]));
+    assert!(is_bundled(first.clone()).await);
+    assert!(!is_bundled(first.clone()).await);
+    assert!(is_bundled(second.clone()).await);
+    assert!(!is_bundled(second).await);
+    assert!(is_bundled(first).await);
+}
+
+#[tokio::test]
+async fn lock_format() {
+    let server = TestServer::start_with(|config| {
+        config.bundle.package_manager = PackageManager::Pnpm;
+    })
+    .await;
+    let is_lock_used = |lock_format| {
+        let client = server.client.clone();
+        async move {
+            let req = BundleRequest {
+                lock: Some("lock".into()),
+                lock_format,
+                ..request(&[])
+            };
+            let mut events = vec![];
+            client
+                .bundle_progress(&req, |event| events.push(event))
+                .await
+                .unwrap();
+
+            let bundle = client.bundle(&req, None).await.unwrap().unwrap();
+            assert_eq!(bundle.lock_format, Some(LockFormat::Pnpm));
+            events[1].data.contains(LockFormat::Pnpm.file_name())
+        }
+    };
+
+    // Lock files with a different format are not used
+    assert!(!is_lock_used(LockFormat::Yarn).await);
+    assert!(is_lock_used(LockFormat::Pnpm).await);
+}
+
+/// Create a bundle request with a unique manifest (in order to not hit the cache of other tests)
+/// that has the given keywords.
+fn request(keywords: &[&str]) -> BundleRequest {
+    BundleRequest {
+        manifest: serde_json::json!({
+            "name": Uuid::new_v4().to_string(),
+            "keywords": keywords,
+        })
+        .to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,266 @@
+//! In-process tests of the routes.
+//!
+//! The server runs with fake versions of its external dependencies in order to make the tests work
+//! offline:
+//!
+//! - A `cargo-build-sbf` shim that "builds" the program by concatenating its source files
+//! - An in-memory share store instead of MongoDB
+//! - A `docker` shim that runs the container commands on the host
+//! - A `bundle` shim that "bundles" the packages by archiving the manifest
+//!
+//! Tests run one at a time because they share the current config, working directory and shims.
+
+mod build;
+#[cfg(feature = "unstable")]
+mod bundle;
+mod openapi;
+mod sandbox;
+mod share;
+
+use std::{
+    env, fs,
+    future::IntoFuture,
+    iter,
+    net::Ipv4Addr,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use solpg_server::{db, BuildConfig, Config, ToolchainConfig};
+use solpg_server_client::Client;
+use tokio::{
+    net::TcpListener,
+    sync::{Mutex, MutexGuard},
+};
+use uuid::Uuid;
+
+use crate::{
+    app,
+    routes::{BuildState, BundleState},
+};
+
+/// Fake `cargo-build-sbf` that writes the concatenated source files as the program binary.
+///
+/// Sources that include `compile_error!` fail to compile, and sources that include `sleep!` take a
+/// second to build. The concurrency directory is locked during the build in order to detect
+/// concurrent builds in the same directory.
+const CARGO_BUILD_SBF: &str = r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --manifest-path) manifest="$2"; shift ;;
+    --sbf-out-dir) out="$2"; shift ;;
+  esac
+  shift
+done
+
+dir=$(dirname "$manifest")
+mkdir "$dir/lock" || { echo "error: concurrency directory is in use" >&2; exit 1; }
+basename "$dir" > "$out/concurrency_id"
+if grep -rq 'sleep!' "$out/src"; then sleep 1; fi
+if grep -rq 'compile_error!' "$out/src"; then
+  echo 'error: could not compile `solpg`' >&2
+else
+  cat "$out"/src/*.rs > "$out/solpg.so"
+  echo 'Finished release [optimized] target(s)' >&2
+fi
+rmdir "$dir/lock"
+"#;
+
+/// Fake `docker` that runs the container commands on the host, inside the container directory.
+///
+/// Only the commands that are used by the sandbox are supported. Running containers are stored
+/// in `{STATE}/containers` and networks in `{STATE}/networks`.
+const DOCKER: &str = r#"#!/bin/sh
+state="{STATE}"
+case "$1" in
+  run)
+    while [ $# -gt 0 ] && [ "$1" != "--name" ]; do shift; done
+    mkdir -p "$state/containers/$2/root" && echo "$2"
+    ;;
+  exec)
+    shift
+    [ "$1" = "--interactive" ] && shift
+    while [ "$1" = "--env" ]; do export "$2"; shift 2; done
+    cd "$state/containers/$1/root" 2>/dev/null || { echo "No such container: $1" >&2; exit 1; }
+    shift
+    # Killing all processes would kill the processes of the host
+    case "$*" in *"kill -9 -1"*) echo "Unsupported command: $*" >&2; exit 1 ;; esac
+    exec "$@"
+    ;;
+  kill)
+    rm -rf "$state/containers/$2"
+    ;;
+  network)
+    case "$2" in
+      create) touch "$state/networks/$4" ;;
+      rm) rm "$state/networks/$3" ;;
+    esac
+    ;;
+  *)
+    echo "Unsupported command: $1" >&2
+    exit 1
+    ;;
+esac
+"#;
+
+/// Fake `bundle` process that archives the input manifest instead of bundling the packages.
+///
+/// Installation fails if the manifest includes `"fail"`, and it outputs the input files otherwise.
+const BUNDLE: &str = r#"#!/bin/sh
+case "$1" in
+  install)
+    grep -q '"fail"' packages/in/package.json && { echo 'Failed to install' >&2; exit 1; }
+    echo "Installed packages with: $(ls packages/in | tr '\n' ' ')"
+    ;;
+  build)
+    mkdir -p packages/out
+    tar -czf packages/out/bundle.tar.gz -C packages/in package.json
+    echo 'Bundled packages' >&2
+    ;;
+esac
+"#;
+
+/// Lock that makes the tests run one at a time
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Test environment that is shared between the tests
+static ENV: LazyLock<TestEnv> = LazyLock::new(TestEnv::new);
+
+/// Paths of the test environment
+pub struct TestEnv {
+    /// Directory of the default toolchain
+    pub toolchain: PathBuf,
+    /// Directory of the other toolchain (Anchor `0.29`)
+    pub other_toolchain: PathBuf,
+    /// Directory of the shims
+    pub bin: PathBuf,
+    /// Directory of the running fake containers
+    pub containers: PathBuf,
+    /// Directory of the fake networks
+    pub networks: PathBuf,
+}
+
+impl TestEnv {
+    /// Create the test environment in a new temporary directory.
+    ///
+    /// The working directory is changed to the new directory (for the programs directory), the
+    /// shims are added to `PATH` and the database is initialized with an in-memory store.
+    fn new() -> Self {
+        let root = env::temp_dir().join(format!("solpg-server-test-{}", Uuid::new_v4()));
+        let env = Self {
+            toolchain: root.join("toolchain"),
+            other_toolchain: root.join("other-toolchain"),
+            bin: root.join("bin"),
+            containers: root.join("containers"),
+            networks: root.join("networks"),
+        };
+
+        for toolchain in [&env.toolchain, &env.other_toolchain] {
+            fs::create_dir_all(toolchain).unwrap();
+            fs::write(
+                toolchain.join("Cargo.toml"),
+                "[package]\nname = \"solpg\"\n\n[lib]\npath = \"default/src/lib.rs\"\n",
+            )
+            .unwrap();
+            fs::write(toolchain.join("Cargo.lock"), "").unwrap();
+        }
+        fs::create_dir_all(&env.containers).unwrap();
+        fs::create_dir_all(&env.networks).unwrap();
+
+        fs::create_dir_all(&env.bin).unwrap();
+        let docker = DOCKER.replace("{STATE}", root.to_str().unwrap());
+        let shims = [
+            ("cargo-build-sbf", CARGO_BUILD_SBF),
+            ("docker", &docker),
+            ("bundle", BUNDLE),
+        ];
+        for (name, content) in shims {
+            let path = env.bin.join(name);
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let path = env::var_os("PATH").unwrap_or_default();
+        let path = env::join_paths(iter::once(env.bin.clone()).chain(env::split_paths(&path)));
+        env::set_var("PATH", path.unwrap());
+        env::set_current_dir(&root).unwrap();
+        db::init_memory().unwrap();
+
+        env
+    }
+
+    /// Get the default test config.
+    pub fn config(&self) -> Config {
+        Config {
+            build: BuildConfig {
+                toolchains: vec![
+                    ToolchainConfig {
+                        platform_tools: "1.41".to_owned(),
+                        anchor: "0.30.1".to_owned(),
+                        dir: self.toolchain.clone(),
+                        solana_bin: Some(self.bin.clone()),
+                    },
+                    ToolchainConfig {
+                        platform_tools: "1.37".to_owned(),
+                        anchor: "0.29.0".to_owned(),
+                        dir: self.other_toolchain.clone(),
+                        solana_bin: None,
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Get the path of the program directory.
+    pub fn program(&self, uuid: &str) -> PathBuf {
+        Path::new("programs").join(uuid)
+    }
+}
+
+/// Lock the test environment for the current test.
+pub async fn setup() -> (MutexGuard<'static, ()>, &'static TestEnv) {
+    // `tokio` mutexes are not poisoned, meaning a failed test doesn't fail the next tests
+    let guard = LOCK.lock().await;
+    (guard, &ENV)
+}
+
+/// Server that runs in the background of the current test
+pub struct TestServer {
+    /// Client of the server
+    pub client: Client,
+    /// Test environment
+    pub env: &'static TestEnv,
+    /// Guard that prevents other tests from running at the same time
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestServer {
+    /// Start a new server with the default test config.
+    pub async fn start() -> Self {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Start a new server with the default test config modified by `f`.
+    pub async fn start_with(f: impl FnOnce(&mut Config)) -> Self {
+        let (guard, env) = setup().await;
+        let mut config = env.config();
+        f(&mut config);
+
+        let build_state = BuildState::new(config.build.concurrency);
+        let bundle_state = cfg!(feature = "unstable").then(|| BundleState::new(&config));
+        Config::set_current(config);
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, app(build_state, bundle_state)).into_future());
+
+        Self {
+            client: Client::new(url),
+            env,
+            _guard: guard,
+        }
+    }
+}
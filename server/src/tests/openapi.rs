@@ -0,0 +1,37 @@
+use std::{env, fs, path::Path};
+
+use super::TestServer;
+
+/// Check that the OpenAPI document matches its snapshot, i.e. the API doesn't change
+/// unintentionally.
+///
+/// Run with `UPDATE_SNAPSHOTS=1` to update the snapshot after an intentional change.
+#[tokio::test]
+async fn snapshot() {
+    let server = TestServer::start().await;
+    let doc = server.client.openapi().await.unwrap();
+    let doc = serde_json::to_string_pretty(&doc).unwrap() + "\n";
+
+    // Unstable routes are only included with the `unstable` feature
+    let name = match cfg!(feature = "unstable") {
+        true => "openapi.unstable.json",
+        false => "openapi.json",
+    };
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("tests")
+        .join("snapshots")
+        .join(name);
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, doc).unwrap();
+        return;
+    }
+
+    let snapshot = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        doc == snapshot,
+        "OpenAPI document doesn't match the snapshot `{name}` (run with `UPDATE_SNAPSHOTS=1` to \
+         update it):\n{doc}"
+    );
+}
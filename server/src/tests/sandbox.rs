@@ -0,0 +1,101 @@
+use std::fs;
+
+use solpg_server::{Sandbox, SandboxEvent};
+use tokio::{process::Command, sync::mpsc};
+
+use super::{setup, TestEnv};
+
+/// Image of the fake containers (not used by the `docker` shim)
+const IMAGE: &str = "solpg-test";
+
+#[tokio::test]
+async fn streaming_events() {
+    let (_guard, env) = setup().await;
+    let (events, mut events_rx) = mpsc::unbounded_channel();
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .command(&sh("echo out && echo err >&2"))
+        .isolate()
+        .command(&sh("exit 3"))
+        .command(&sh("echo unreachable"))
+        .run_streaming(events)
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+
+    let commands = output
+        .commands
+        .iter()
+        .map(|cmd| (cmd.action, cmd.status.code()))
+        .collect::<Vec<_>>();
+    assert_eq!(commands, [(0, Some(0)), (2, Some(3))]);
+
+    let mut started = vec![];
+    let mut stdout = vec![];
+    let mut exits = vec![];
+    while let Some(event) = events_rx.recv().await {
+        match event {
+            SandboxEvent::Started { action, command } => started.push((action, command)),
+            SandboxEvent::Stdout { action, chunk } => {
+                assert_eq!(action, 0);
+                stdout.extend(chunk);
+            }
+            SandboxEvent::Exited { action, status, .. } => exits.push((action, status.code())),
+            _ => {}
+        }
+    }
+    assert_eq!(started[1], (2, "sh -c exit 3".to_owned()));
+    assert_eq!(stdout, b"out\n");
+    assert_eq!(exits, commands);
+    assert_no_resources(env);
+}
+
+#[tokio::test]
+async fn timeout() {
+    let (_guard, env) = setup().await;
+    let result = Sandbox::new()
+        .image(IMAGE)
+        .timeout(1)
+        .command(&sh("sleep 10"))
+        .run()
+        .await;
+    assert_eq!(result.unwrap_err().to_string(), "Timed out");
+    assert_no_resources(env);
+}
+
+#[tokio::test]
+async fn network_allowlist() {
+    let (_guard, env) = setup().await;
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .network_allowlist(["registry"])
+        .command(&sh("true"))
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+    assert_no_resources(env);
+}
+
+#[tokio::test]
+async fn start_error() {
+    let (_guard, env) = setup().await;
+    let result = Sandbox::new().command(&sh("true")).run().await;
+    assert_eq!(result.unwrap_err().to_string(), "Image not specified");
+    assert_no_resources(env);
+}
+
+/// Assert that all of the containers and networks are cleaned up.
+fn assert_no_resources(env: &TestEnv) {
+    assert_eq!(fs::read_dir(&env.containers).unwrap().count(), 0);
+    assert_eq!(fs::read_dir(&env.networks).unwrap().count(), 0);
+}
+
+/// Create a shell command.
+fn sh(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    cmd
+}
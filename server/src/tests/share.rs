@@ -0,0 +1,43 @@
+use serde_json::json;
+use solpg_server_client::{Error, ShareNewRequest};
+
+use super::TestServer;
+
+#[tokio::test]
+async fn round_trip() {
+    let server = TestServer::start().await;
+    let explorer = json!({ "files": { "src/lib.rs": { "content": "// share" } } });
+    let id = server
+        .client
+        .share_new(&ShareNewRequest {
+            explorer: explorer.clone(),
+        })
+        .await
+        .unwrap();
+
+    let share = server.client.share_get(&id).await.unwrap();
+    assert_eq!(share["files"], explorer["files"]);
+    assert_eq!(share["_id"]["$oid"], id);
+}
+
+#[tokio::test]
+async fn not_found() {
+    let server = TestServer::start().await;
+    for (id, message) in [
+        ("000000000000000000000000", Some("Share not found")),
+        ("invalid", None),
+    ] {
+        match server.client.share_get(id).await.unwrap_err() {
+            Error::Server {
+                status,
+                message: msg,
+            } => {
+                assert_eq!(status, 500);
+                if let Some(message) = message {
+                    assert_eq!(msg, message);
+                }
+            }
+            e => panic!("Unexpected error: {e}"),
+        }
+    }
+}
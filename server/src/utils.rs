@@ -5,10 +5,12 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// A vector of [`FileEntry`]
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
 #[serde(transparent)]
+#[schema(value_type = Vec<Vec<String>>)]
 pub struct Files(Vec<FileEntry>);
 
 /// (Path, Content)
@@ -0,0 +1,303 @@
+//! Sandbox integration tests.
+//!
+//! These tests require a local Docker daemon (or Podman, or `bwrap` for the local backend), run
+//! them with:
+//!
+//! ```sh
+//! cargo test --test sandbox -- --ignored
+//! ```
+
+use std::{env, fs, path::PathBuf};
+
+use solpg_server::{Sandbox, SandboxBackend, SandboxPool};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Image to run the tests in (must include `sh`, `tar` and `wget`)
+const IMAGE: &str = "alpine:3.20";
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn read_only() {
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .read_only()
+        .command(&sh("touch /file"))
+        .run()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn tmpfs_size_limit() {
+    let sandbox = || {
+        Sandbox::new()
+            .image(IMAGE)
+            .read_only()
+            .tmpfs("/work", 1024 * 1024)
+    };
+
+    let output = sandbox()
+        .command(&sh("head -c 512000 /dev/zero > /work/file"))
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = sandbox()
+        .command(&sh("head -c 2048000 /dev/zero > /work/file"))
+        .run()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn copy_tmpfs() {
+    let host_path = create_host_dir();
+    fs::write(host_path.join("in.txt"), "solpg").unwrap();
+
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .read_only()
+        .tmpfs("/work", 1024 * 1024)
+        .copy(format!("{}/.", host_path.display()), "container:/work")
+        .command(&sh("cp /work/in.txt /work/out.txt"))
+        .copy("container:/work/.", &host_path)
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(host_path.join("out.txt")).unwrap(),
+        "solpg"
+    );
+
+    fs::remove_dir_all(host_path).unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn pool() {
+    let template = Sandbox::new()
+        .image(IMAGE)
+        .read_only()
+        .tmpfs("/work", 1024 * 1024);
+    let pool = SandboxPool::new(template, 1, 2);
+
+    let output = pool
+        .sandbox()
+        .command(&sh("touch /work/file /dev/shm/file && (sleep 60 &)"))
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+
+    // Files and processes of the previous runs must not be visible
+    for _ in 0..2 {
+        let output = pool
+            .sandbox()
+            .command(&sh(
+                "[ -z \"$(ls -A /work)\" ] && [ -z \"$(ls -A /dev/shm)\" ] && \
+                 ! pgrep -f \"slee[p] 60\"",
+            ))
+            .run()
+            .await
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn no_network() {
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .command(&sh("wget -q -T 2 -O - http://example.com"))
+        .run()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn network_allowlist() {
+    // Start a server to allow
+    let server = format!("solpg-server-test-{}", Uuid::new_v4());
+    let status = std::process::Command::new("docker")
+        .args(["run", "--detach", "--rm", "--name", &server, IMAGE])
+        .args([
+            "sh",
+            "-c",
+            "echo solpg > /tmp/index.html && httpd -f -p 8080 -h /tmp",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = Sandbox::new()
+        .image(IMAGE)
+        .network_allowlist([&server])
+        .command(&sh(&format!("wget -q -T 2 -O - http://{server}:8080")))
+        .command(&sh("! wget -q -T 2 -O - http://example.com"))
+        .run()
+        .await;
+
+    std::process::Command::new("docker")
+        .args(["kill", &server])
+        .status()
+        .unwrap();
+
+    let output = output.unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), "solpg");
+}
+
+#[tokio::test]
+#[ignore = "requires a local Docker daemon"]
+async fn network_and_network_allowlist() {
+    let result = Sandbox::new()
+        .image(IMAGE)
+        .network("bridge")
+        .network_allowlist(["registry"])
+        .command(&sh("true"))
+        .run()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires `bwrap`"]
+async fn local_copy() {
+    let host_path = create_host_dir();
+    fs::write(host_path.join("in.txt"), "solpg").unwrap();
+    let host_file = env::current_dir()
+        .unwrap()
+        .join(format!("solpg-server-test-{}", Uuid::new_v4()));
+
+    // Changes persist between commands without affecting the host
+    let output = Sandbox::new()
+        .backend(SandboxBackend::Local)
+        .copy(format!("{}/.", host_path.display()), "container:work")
+        .command(&sh(&format!("touch {}", host_file.display())))
+        .command(&sh("cp work/in.txt work/out.txt"))
+        .copy("container:work/.", &host_path)
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(host_path.join("out.txt")).unwrap(),
+        "solpg"
+    );
+    assert!(!host_file.exists());
+
+    fs::remove_dir_all(host_path).unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires `bwrap`"]
+async fn local_seccomp() {
+    let output = Sandbox::new()
+        .backend(SandboxBackend::Local)
+        .command(&sh("! unshare --mount true"))
+        .run()
+        .await
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[tokio::test]
+#[ignore = "requires `bwrap`"]
+async fn local_image_not_found() {
+    let result = Sandbox::new()
+        .backend(SandboxBackend::Local)
+        .local_images_dir(env::temp_dir())
+        .image(format!("solpg-server-test-{}", Uuid::new_v4()))
+        .command(&sh("true"))
+        .run()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires `bwrap`, `systemd-run --user` and the exported bundle image"]
+async fn bundle_image_local() {
+    bundle_image(SandboxBackend::Local).await;
+}
+
+#[tokio::test]
+#[ignore = "requires Podman and the bundle image"]
+async fn bundle_image_podman() {
+    bundle_image(SandboxBackend::Podman).await;
+}
+
+/// Run the bundle image with the same options as the bundle route, and check that the bundle
+/// process can run in it.
+///
+/// The image is built with `docker build -f images/Dockerfile.bundle -t <IMAGE> .` (or
+/// `podman build`), and exported to `images/rootfs/<IMAGE>` for the local backend (see
+/// [`Sandbox::local_images_dir`]).
+async fn bundle_image(backend: SandboxBackend) {
+    const IMAGE: &str = "solpg-server-sandbox-bundle";
+
+    let host_path = create_host_dir();
+    fs::write(host_path.join("package.json"), "{}").unwrap();
+
+    let output = Sandbox::new()
+        .backend(backend)
+        .local_images_dir("images/rootfs")
+        .image(IMAGE)
+        .user("solpg")
+        .cpu_limit(1)
+        .memory_limit(1024 * 1024 * 1024)
+        .process_limit(64)
+        .timeout(60)
+        .read_only()
+        .tmpfs("/home/solpg", 1024 * 1024 * 1024)
+        .tmpfs("/tmp", 1024 * 1024 * 1024)
+        .copy(
+            format!("{}/.", host_path.display()),
+            "container:packages/in",
+        )
+        .command(&sh(r#"[ "$(id -un)" = solpg ] && [ "$HOME" = "$PWD" ]"#))
+        .command(&sh(
+            "command -v node yarn pnpm bundle && [ -d /opt/solpg/packages/node_modules ]",
+        ))
+        // V8 reserves much more virtual memory than it uses, which must not count towards the
+        // memory limit
+        .command(&sh(r#"node -e "Buffer.alloc(256 * 1024 * 1024, 1)""#))
+        .copy("container:packages/in/.", host_path.join("out"))
+        .run()
+        .await
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.commands.len(), 3);
+    assert!(host_path.join("out").join("package.json").exists());
+
+    fs::remove_dir_all(host_path).unwrap();
+}
+
+/// Create a shell command.
+fn sh(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    cmd
+}
+
+/// Create a unique directory in the host to copy files from/to.
+fn create_host_dir() -> PathBuf {
+    let path = env::temp_dir().join(format!("solpg-server-test-{}", Uuid::new_v4()));
+    fs::create_dir_all(&path).unwrap();
+    path
+}
@@ -0,0 +1,215 @@
+//! Request and response types of the Solana Playground server.
+//!
+//! The types are shared between the server and its client. The `schema` feature derives the
+//! OpenAPI schemas of the types (used by the server's `/openapi.json`).
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Build request
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRequest {
+    /// Program files to build as (path, content), e.g. `("/src/lib.rs", "...")`
+    #[cfg_attr(feature = "schema", schema(value_type = Vec<Vec<String>>))]
+    pub files: Vec<(String, String)>,
+    /// UUID of the program.
+    ///
+    /// In the first ever request from a client, this will not exist and [`BuildResponse`] will
+    /// return a `uuid`. Client is responsible for saving the `uuid` and using it with every
+    /// subseqent requests in order to save resources not re-creating the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// Build flags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<BuildFlags>,
+    /// Toolchain to build with, defaults to the default toolchain (see `/toolchains`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<ToolchainSelector>,
+    /// IDL of the previous build to check the compatibility of the new IDL with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schema(value_type = Option<Object>))]
+    pub previous_idl: Option<Value>,
+}
+
+/// Build flags
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BuildFlags {
+    /// Enable Anchor `seeds` feature, defaults to `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seeds_feature: Option<bool>,
+    /// Remove doc comments from the IDL, defaults to `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_docs: Option<bool>,
+    /// Enable safety checks, defaults to `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_checks: Option<bool>,
+}
+
+/// Toolchain selector
+///
+/// Versions match by prefix, e.g. `0.29` matches `0.29.0`. Unset versions match any version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ToolchainSelector {
+    /// Solana platform-tools version, e.g. `1.41`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform_tools: Option<String>,
+    /// Anchor version, e.g. `0.30.1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+}
+
+/// Build response
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+pub struct BuildResponse {
+    /// Solana build tools output to `stderr` regardless of the compilation status
+    pub stderr: String,
+    /// UUID of the program, `None` if the [`BuildRequest`] includes `uuid`
+    pub uuid: Option<String>,
+    /// Anchor IDL of the program, `None` for native programs and for toolchains whose Anchor
+    /// version is not supported by the IDL generation (Anchor `0.29` only)
+    #[cfg_attr(feature = "schema", schema(value_type = Option<Object>))]
+    pub idl: Option<Value>,
+    /// Compatibility report of the IDL, `None` if the request doesn't include `previousIdl` or
+    /// the program is not an Anchor program
+    pub compatibility: Option<CompatibilityReport>,
+}
+
+/// Compatibility report of a program upgrade
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CompatibilityReport {
+    /// Whether any of the changes is breaking
+    pub is_breaking: bool,
+    /// Changes compared to the previous IDL
+    pub changes: Vec<IdlChange>,
+}
+
+/// IDL change
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct IdlChange {
+    /// Kind of the change
+    pub kind: ChangeKind,
+    /// Path of the changed item, e.g. `accounts.Counter.count`
+    pub path: String,
+    /// Human-readable description of the change
+    pub description: String,
+    /// Whether the change breaks existing clients or accounts
+    pub is_breaking: bool,
+}
+
+/// Kind of an IDL change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    /// Item is added
+    Added,
+    /// Item is removed
+    Removed,
+    /// Type changed without changing its size
+    TypeChanged,
+    /// Type changed along with its size
+    Resized,
+    /// Order of the items changed
+    Reordered,
+    /// Discriminator changed because the item is renamed
+    DiscriminatorChanged,
+}
+
+/// Program build toolchain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Toolchain {
+    /// Solana platform-tools version
+    pub platform_tools: String,
+    /// Anchor version
+    pub anchor: String,
+    /// Whether the toolchain is used when `/build` requests don't select a toolchain
+    pub default: bool,
+}
+
+/// Share new request
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+pub struct ShareNewRequest {
+    /// Explorer contains all file related data about the share
+    #[cfg_attr(feature = "schema", schema(value_type = Object))]
+    pub explorer: Value,
+}
+
+/// Bundle request
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BundleRequest {
+    /// Package manifest (`package.json`)
+    pub manifest: String,
+    /// Lock file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock: Option<String>,
+    /// Format of the lock file.
+    ///
+    /// The lock file is ignored if its format is not the format of the server's package manager,
+    /// and the lock file of the response is generated from the manifest instead.
+    #[serde(default)]
+    pub lock_format: LockFormat,
+}
+
+/// Lock file format of a JS package manager
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum LockFormat {
+    /// `yarn.lock`
+    #[default]
+    Yarn,
+    /// `pnpm-lock.yaml`
+    Pnpm,
+}
+
+impl LockFormat {
+    /// Name of the response header that has the lock file format of the bundle archive
+    pub const HEADER: &'static str = "x-lock-format";
+
+    /// Get the lock file name of the format.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Yarn => "yarn.lock",
+            Self::Pnpm => "pnpm-lock.yaml",
+        }
+    }
+}
+
+impl fmt::Display for LockFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yarn => write!(f, "yarn"),
+            Self::Pnpm => write!(f, "pnpm"),
+        }
+    }
+}
+
+impl FromStr for LockFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yarn" => Ok(Self::Yarn),
+            "pnpm" => Ok(Self::Pnpm),
+            _ => Err(format!("Unknown lock format: `{s}`")),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+// Native Playnet host that serves the Solana JSON-RPC API over HTTP on localhost.
+//
+// Playnet is a lot lighter than `solana-test-validator`, which makes it a good fit for running
+// tests in CI. `Playnet` is not thread-safe, so the instance lives in the main thread and the
+// server threads send the requests to it via a channel.
+
+mod ws;
+
+use std::{
+    error::Error,
+    fs,
+    io::Read,
+    net::TcpListener,
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use playnet::Playnet;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+/// Minimum interval between saving the bank state
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser)]
+#[clap(
+    version,
+    about = "Run Playnet natively with a JSON-RPC server on localhost"
+)]
+struct Cli {
+    /// Port of the JSON-RPC HTTP server
+    #[clap(long, default_value = "8899")]
+    rpc_port: u16,
+    /// Serve websocket subscriptions on `rpc_port + 1`
+    #[clap(long)]
+    ws: bool,
+    /// File to load the bank state from and save it to
+    #[clap(long)]
+    ledger: Option<PathBuf>,
+    /// Fee per transaction signature
+    #[clap(long)]
+    lamports_per_signature: Option<u64>,
+    /// Interval of sealing blocks in milliseconds, 0 to disable
+    #[clap(long, default_value = "400")]
+    block_interval_ms: u64,
+    /// Amount of slots in each epoch, epochs don't have warmup
+    #[clap(long)]
+    slots_per_epoch: Option<u64>,
+    /// Add a program from an `.so` file at the given address, can be specified multiple times
+    #[clap(long, number_of_values = 2, value_names = &["ADDRESS", "PATH"])]
+    bpf_program: Vec<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    // Create Playnet from the saved bank state if it exists
+    let maybe_bank_string = match &cli.ledger {
+        Some(ledger) if ledger.exists() => Some(fs::read_to_string(ledger)?),
+        _ => None,
+    };
+    let playnet = Playnet::new(maybe_bank_string, cli.lamports_per_signature);
+    let block_interval = match cli.block_interval_ms {
+        0 => None,
+        block_interval_ms => Some(Duration::from_millis(block_interval_ms)),
+    };
+    playnet.set_block_interval(block_interval.map(|interval| interval.as_millis() as u64));
+    if let Some(slots_per_epoch) = cli.slots_per_epoch {
+        playnet.set_epoch_schedule(slots_per_epoch, false)?;
+    }
+
+    // Preload programs
+    for program in cli.bpf_program.chunks(2) {
+        let program_id = Pubkey::from_str(&program[0])
+            .map_err(|e| format!("Invalid program address `{}`: {e}", program[0]))?;
+        let program_bytes = fs::read(&program[1])
+            .map_err(|e| format!("Failed to read program `{}`: {e}", program[1]))?;
+        playnet.add_program(program_id, program_bytes);
+    }
+
+    let save = |playnet: &Playnet| -> Result<(), Box<dyn Error>> {
+        if let Some(ledger) = &cli.ledger {
+            fs::write(ledger, playnet.get_save_data())?;
+        }
+        Ok(())
+    };
+    save(&playnet)?;
+
+    // Start the servers
+    let (sender, receiver) = mpsc::channel();
+    let client = RpcClient { sender };
+
+    let server = tiny_http::Server::http(("127.0.0.1", cli.rpc_port))?;
+    println!("JSON-RPC URL: http://127.0.0.1:{}", cli.rpc_port);
+    thread::spawn({
+        let client = client.clone();
+        move || serve_http(server, client)
+    });
+
+    if cli.ws {
+        let ws_port = cli
+            .rpc_port
+            .checked_add(1)
+            .ok_or("`--ws` requires `--rpc-port` to be less than 65535")?;
+        let listener = TcpListener::bind(("127.0.0.1", ws_port))?;
+        println!("WebSocket URL: ws://127.0.0.1:{ws_port}");
+        thread::spawn(move || ws::serve(listener, client));
+    }
+
+    // Handle the requests, seal the blocks when they are due and save the bank state after
+    // requests, at most once per `SAVE_INTERVAL`
+    let poll_interval =
+        block_interval.map_or(SAVE_INTERVAL, |interval| interval.min(SAVE_INTERVAL));
+    let mut is_dirty = false;
+    let mut last_save = Instant::now();
+    loop {
+        match receiver.recv_timeout(poll_interval) {
+            Ok(Request {
+                body,
+                response_sender,
+            }) => {
+                let _ = response_sender.send(playnet.rpc.handle_request(&body));
+                is_dirty = true;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        playnet.tick();
+
+        if is_dirty && last_save.elapsed() >= SAVE_INTERVAL {
+            save(&playnet)?;
+            is_dirty = false;
+            last_save = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+/// Request to the thread that owns the Playnet instance
+struct Request {
+    /// JSON-RPC request body
+    body: String,
+    /// Sender of the JSON-RPC response body
+    response_sender: mpsc::Sender<String>,
+}
+
+/// Client to send JSON-RPC requests to the Playnet thread
+#[derive(Clone)]
+struct RpcClient {
+    sender: mpsc::Sender<Request>,
+}
+
+impl RpcClient {
+    /// Send the JSON-RPC request body and return the response body.
+    ///
+    /// `None` is returned if the Playnet thread has stopped.
+    fn send(&self, body: String) -> Option<String> {
+        let (response_sender, response_receiver) = mpsc::channel();
+        self.sender
+            .send(Request {
+                body,
+                response_sender,
+            })
+            .ok()?;
+        response_receiver.recv().ok()
+    }
+
+    /// Call the method and return its result, `None` is returned on errors.
+    fn call(&self, method: &str, params: Value) -> Option<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 0, "method": method, "params": params });
+        let response = self.send(request.to_string())?;
+        serde_json::from_str::<Value>(&response)
+            .ok()?
+            .get_mut("result")
+            .map(Value::take)
+    }
+}
+
+/// Serve the JSON-RPC requests over HTTP.
+fn serve_http(server: tiny_http::Server, client: RpcClient) {
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap();
+
+    for mut request in server.incoming_requests() {
+        let method = request.method().clone();
+        let response = match (method, request.url() == "/health") {
+            (tiny_http::Method::Get, true) => tiny_http::Response::from_string("ok"),
+            (tiny_http::Method::Post, _) => {
+                let mut body = String::new();
+                if request.as_reader().read_to_string(&mut body).is_err() {
+                    let _ = request.respond(
+                        tiny_http::Response::from_string("Invalid request body")
+                            .with_status_code(400),
+                    );
+                    continue;
+                }
+
+                let Some(response_body) = client.send(body) else {
+                    return;
+                };
+                tiny_http::Response::from_string(response_body).with_header(content_type.clone())
+            }
+            _ => tiny_http::Response::from_string("Method not allowed").with_status_code(405),
+        };
+        let _ = request.respond(response);
+    }
+}
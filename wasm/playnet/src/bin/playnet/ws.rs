@@ -0,0 +1,232 @@
+// Websocket subscriptions of the Solana JSON-RPC API.
+//
+// Playnet doesn't have notifications, so the subscriptions are implemented by polling the RPC.
+
+use std::{
+    collections::HashMap,
+    io, mem,
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::RpcClient;
+
+/// Interval between polling the RPC for the subscription updates
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Maximum limit of `getBlocksWithLimit`
+const MAX_BLOCKS_LIMIT: u64 = 500_000;
+
+/// Serve the subscriptions, each connection is handled in a separate thread.
+pub fn serve(listener: TcpListener, client: RpcClient) {
+    for stream in listener.incoming().flatten() {
+        let client = client.clone();
+        thread::spawn(move || {
+            let Ok(ws) = tungstenite::accept(stream) else {
+                return;
+            };
+
+            // Reads time out in order to be able to poll the subscriptions
+            if ws.get_ref().set_read_timeout(Some(POLL_INTERVAL)).is_ok() {
+                Connection::new(ws, client).run();
+            }
+        });
+    }
+}
+
+enum Subscription {
+    Account { params: Value, value: Value },
+    Signature { signature: Value },
+    Slot { slot: Value },
+}
+
+impl Subscription {
+    /// Get the notification method name.
+    fn notification_method(&self) -> &'static str {
+        match self {
+            Self::Account { .. } => "accountNotification",
+            Self::Signature { .. } => "signatureNotification",
+            Self::Slot { .. } => "slotNotification",
+        }
+    }
+}
+
+struct Connection {
+    ws: WebSocket<TcpStream>,
+    client: RpcClient,
+    subscriptions: HashMap<u64, Subscription>,
+    next_subscription_id: u64,
+}
+
+impl Connection {
+    fn new(ws: WebSocket<TcpStream>, client: RpcClient) -> Self {
+        Self {
+            ws,
+            client,
+            subscriptions: HashMap::new(),
+            next_subscription_id: 0,
+        }
+    }
+
+    /// Handle the requests and send the notifications until the connection is closed.
+    fn run(mut self) {
+        loop {
+            match self.ws.read() {
+                Ok(Message::Text(text)) => {
+                    let response = self.handle_request(text.as_str());
+                    if self.ws.send(Message::text(response.to_string())).is_err() {
+                        return;
+                    }
+                }
+                Ok(Message::Close(_)) => return,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(_) => return,
+            }
+
+            if self.poll().is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Handle the subscribe and unsubscribe requests.
+    fn handle_request(&mut self, request: &str) -> Value {
+        let request = match serde_json::from_str::<Value>(request) {
+            Ok(request) => request,
+            Err(_) => return error_response(Value::Null, -32700, "Parse error"),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or_else(|| json!([]));
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error_response(id, -32600, "Invalid request");
+        };
+
+        let subscription = match method {
+            "accountSubscribe" => {
+                // Default to base64 encoding like the real RPC
+                let mut params = params;
+                if let Some(params) = params.as_array_mut().filter(|params| params.len() == 1) {
+                    params.push(json!({ "encoding": "base64" }));
+                }
+                match self.client.call("getAccountInfo", params.clone()) {
+                    Some(result) => Subscription::Account {
+                        params,
+                        value: result["value"].clone(),
+                    },
+                    None => return error_response(id, -32602, "Invalid params"),
+                }
+            }
+            "signatureSubscribe" => Subscription::Signature {
+                signature: params[0].clone(),
+            },
+            "slotSubscribe" => Subscription::Slot {
+                slot: self.client.call("getSlot", json!([])).unwrap_or_default(),
+            },
+            "accountUnsubscribe" | "signatureUnsubscribe" | "slotUnsubscribe" => {
+                let removed = params[0]
+                    .as_u64()
+                    .and_then(|id| self.subscriptions.remove(&id))
+                    .is_some();
+                return json!({ "jsonrpc": "2.0", "result": removed, "id": id });
+            }
+            _ => return error_response(id, -32601, &format!("Method not found: {method}")),
+        };
+
+        let subscription_id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        self.subscriptions.insert(subscription_id, subscription);
+        json!({ "jsonrpc": "2.0", "result": subscription_id, "id": id })
+    }
+
+    /// Send the notifications of the changed subscriptions.
+    ///
+    /// `None` is returned if the connection or the RPC is closed.
+    fn poll(&mut self) -> Option<()> {
+        let mut notifications = vec![];
+        let mut finished_subscription_ids = vec![];
+        for (id, subscription) in self.subscriptions.iter_mut() {
+            let result = match subscription {
+                Subscription::Account { params, value } => {
+                    let result = self.client.call("getAccountInfo", params.clone())?;
+                    if result["value"] == *value {
+                        continue;
+                    }
+                    *value = result["value"].clone();
+                    result
+                }
+                Subscription::Signature { signature } => {
+                    let result = self
+                        .client
+                        .call("getSignatureStatuses", json!([[signature]]))?;
+                    let status = &result["value"][0];
+                    if status.is_null() {
+                        continue;
+                    }
+
+                    // Signature subscriptions are removed after the first notification
+                    finished_subscription_ids.push(*id);
+                    json!({ "context": result["context"], "value": { "err": status["err"] } })
+                }
+                Subscription::Slot { slot } => {
+                    let new_slot = self.client.call("getSlot", json!([]))?;
+                    if new_slot == *slot {
+                        continue;
+                    }
+                    let old_slot = mem::replace(slot, new_slot.clone())
+                        .as_u64()
+                        .unwrap_or_default();
+                    let new_slot = new_slot.as_u64().unwrap_or_default();
+
+                    // The parent is the last sealed block (slots can be skipped), which is sealed
+                    // since the previous notification
+                    let parent = self
+                        .client
+                        .call("getBlocksWithLimit", json!([old_slot, MAX_BLOCKS_LIMIT]))
+                        .and_then(|slots| {
+                            slots
+                                .as_array()?
+                                .iter()
+                                .filter_map(Value::as_u64)
+                                .filter(|slot| *slot < new_slot)
+                                .last()
+                        })
+                        .unwrap_or(old_slot);
+                    json!({
+                        "parent": parent,
+                        "root": new_slot,
+                        "slot": new_slot,
+                    })
+                }
+            };
+
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": subscription.notification_method(),
+                "params": { "result": result, "subscription": id },
+            }));
+        }
+
+        for id in finished_subscription_ids {
+            self.subscriptions.remove(&id);
+        }
+        for notification in notifications {
+            self.ws.send(Message::text(notification.to_string())).ok()?;
+        }
+
+        Some(())
+    }
+}
+
+/// Create a JSON-RPC error response.
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id })
+}
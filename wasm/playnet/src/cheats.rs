@@ -0,0 +1,212 @@
+// Cheat codes to arrange the Playnet state directly rather than with transactions.
+//
+// The changes don't go through the runtime, which means they are not validated e.g. the data of
+// an account can be set to anything regardless of its owner.
+
+use std::{
+    rc::Rc,
+    str::FromStr,
+    sync::{RwLock, RwLockWriteGuard},
+};
+
+use solana_sdk::{
+    account::Account, clock::UnixTimestamp, pubkey, pubkey::Pubkey, slot_history::Slot,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    rpc::filter::{
+        TOKEN_2022_PROGRAM_ID, TOKEN_ACCOUNT_AMOUNT_INDEX, TOKEN_ACCOUNT_LEN,
+        TOKEN_ACCOUNT_STATE_INDEX, TOKEN_ACCOUNT_STATE_INITIALIZED, TOKEN_PROGRAM_ID,
+    },
+    runtime::bank::PgBank,
+};
+
+/// SPL Associated Token Account program id
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Native mints of SPL Token and Token 2022 (wrapped SOL)
+const NATIVE_MINTS: [Pubkey; 2] = [
+    pubkey!("So11111111111111111111111111111111111111112"),
+    pubkey!("9pan9bMn5HatX4EJdBwg9VgCa7Uz5HL8N1m5D3NdXejP"),
+];
+
+/// Mint layout
+const MINT_LEN: usize = 82;
+const MINT_SUPPLY_INDEX: usize = 36;
+const MINT_IS_INITIALIZED_INDEX: usize = 45;
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PgCheats {
+    bank: Rc<RwLock<PgBank>>,
+}
+
+impl PgCheats {
+    pub fn new(bank: Rc<RwLock<PgBank>>) -> Self {
+        Self { bank }
+    }
+
+    fn get_bank_mut(&self) -> RwLockWriteGuard<'_, PgBank> {
+        self.bank.write().unwrap()
+    }
+}
+
+#[wasm_bindgen]
+impl PgCheats {
+    /// Set or override the account
+    #[wasm_bindgen(js_name = setAccount)]
+    pub fn set_account(
+        &self,
+        pubkey_str: &str,
+        lamports: u64,
+        data: Vec<u8>,
+        owner_str: &str,
+        executable: bool,
+    ) -> Result<(), String> {
+        let pubkey = parse_pubkey(pubkey_str)?;
+        let owner = parse_pubkey(owner_str)?;
+        self.get_bank_mut().set_account(
+            pubkey,
+            Account {
+                lamports,
+                data,
+                owner,
+                executable,
+                rent_epoch: 0,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Set the lamports of the account, a system account is created if it doesn't exist
+    #[wasm_bindgen(js_name = setLamports)]
+    pub fn set_lamports(&self, pubkey_str: &str, lamports: u64) -> Result<(), String> {
+        let pubkey = parse_pubkey(pubkey_str)?;
+        let mut bank = self.get_bank_mut();
+        let mut account = bank.get_account_default(&pubkey);
+        account.lamports = lamports;
+        bank.set_account(pubkey, account);
+
+        Ok(())
+    }
+
+    /// Set the token balance of the owner's associated token account of the mint.
+    ///
+    /// The token account is created if it doesn't exist and the supply of the mint is adjusted
+    /// by the difference. Returns the address of the token account.
+    ///
+    /// Native mints (wrapped SOL) and Token 2022 mints with extensions are not supported, as
+    /// their token accounts need lamports and extensions that are not set.
+    #[wasm_bindgen(js_name = setTokenBalance)]
+    pub fn set_token_balance(
+        &self,
+        owner_str: &str,
+        mint_str: &str,
+        amount: u64,
+    ) -> Result<String, String> {
+        let owner = parse_pubkey(owner_str)?;
+        let mint = parse_pubkey(mint_str)?;
+        let mut bank = self.get_bank_mut();
+
+        let mut mint_account = bank
+            .get_account(&mint)
+            .cloned()
+            .ok_or_else(|| format!("Mint `{mint}` not found"))?;
+        let token_program_id = mint_account.owner;
+        let is_mint = (token_program_id == TOKEN_PROGRAM_ID
+            || token_program_id == TOKEN_2022_PROGRAM_ID)
+            && mint_account.data.len() >= MINT_LEN
+            && mint_account.data[MINT_IS_INITIALIZED_INDEX] != 0;
+        if !is_mint {
+            return Err(format!("`{mint}` is not an initialized mint"));
+        }
+        if NATIVE_MINTS.contains(&mint) {
+            return Err(format!("Native mint `{mint}` is not supported"));
+        }
+        if mint_account.data.len() > MINT_LEN {
+            return Err(format!(
+                "Mint `{mint}` has extensions, which are not supported"
+            ));
+        }
+
+        let (token_account_pk, _) = Pubkey::find_program_address(
+            &[owner.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+            &ASSOCIATED_TOKEN_PROGRAM_ID,
+        );
+        let mut token_account = match bank.get_account(&token_account_pk) {
+            Some(account)
+                if account.owner == token_program_id && account.data.len() >= TOKEN_ACCOUNT_LEN =>
+            {
+                account.to_owned()
+            }
+            _ => {
+                let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+                data[..32].copy_from_slice(mint.as_ref());
+                data[32..64].copy_from_slice(owner.as_ref());
+                data[TOKEN_ACCOUNT_STATE_INDEX] = TOKEN_ACCOUNT_STATE_INITIALIZED;
+                Account {
+                    lamports: bank.get_minimum_balance_for_rent_exemption(TOKEN_ACCOUNT_LEN),
+                    data,
+                    owner: token_program_id,
+                    executable: false,
+                    rent_epoch: 0,
+                }
+            }
+        };
+
+        let previous_amount = read_u64(&token_account.data, TOKEN_ACCOUNT_AMOUNT_INDEX);
+        write_u64(&mut token_account.data, TOKEN_ACCOUNT_AMOUNT_INDEX, amount);
+        let supply = read_u64(&mint_account.data, MINT_SUPPLY_INDEX)
+            .saturating_sub(previous_amount)
+            .saturating_add(amount);
+        write_u64(&mut mint_account.data, MINT_SUPPLY_INDEX, supply);
+
+        bank.set_account(mint, mint_account);
+        bank.set_account(token_account_pk, token_account);
+
+        Ok(token_account_pk.to_string())
+    }
+
+    /// Deploy the program at the given address without loader transactions.
+    ///
+    /// The program is owned by the BPF loader, which means it's not upgradeable.
+    #[wasm_bindgen(js_name = deployProgram)]
+    pub fn deploy_program(
+        &self,
+        program_id_str: &str,
+        program_bytes: Vec<u8>,
+    ) -> Result<(), String> {
+        let program_id = parse_pubkey(program_id_str)?;
+        self.get_bank_mut().add_program(program_id, program_bytes);
+
+        Ok(())
+    }
+
+    /// Set the unix timestamp of the clock in seconds, unlike warping it can go backwards
+    #[wasm_bindgen(js_name = setUnixTimestamp)]
+    pub fn set_unix_timestamp(&self, unix_timestamp: UnixTimestamp) {
+        self.get_bank_mut().set_clock_unix_timestamp(unix_timestamp);
+    }
+
+    /// Seal the current block and skip to the given slot.
+    ///
+    /// The slot can only move forward because the blocks of the previous slots are kept.
+    #[wasm_bindgen(js_name = setSlot)]
+    pub fn set_slot(&self, slot: Slot) -> Result<(), String> {
+        self.get_bank_mut().warp_to_slot(slot)
+    }
+}
+
+fn parse_pubkey(pubkey_str: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(pubkey_str).map_err(|e| format!("Invalid pubkey `{pubkey_str}`: {e}"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn write_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
@@ -2,7 +2,9 @@
 // ./playnet -> Starting point. Lifecycle starts when a Playnet instance gets created.
 // ./runtime -> Where all internal logic for Playnet runtime lives.
 // ./rpc     -> Methods for clients to interact with the Playnet.
+// ./cheats  -> Methods for tests to arrange the Playnet state directly.
 
+mod cheats;
 mod playnet;
 mod rpc;
 mod runtime;
@@ -10,5 +12,9 @@ mod serde;
 mod types;
 mod utils;
 
+pub use cheats::PgCheats;
+pub use playnet::Playnet;
+pub use rpc::PgRpc;
+
 #[cfg(test)]
 pub mod test_programs;
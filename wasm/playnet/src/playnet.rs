@@ -6,9 +6,10 @@
 
 use std::{rc::Rc, sync::RwLock};
 
+use solana_sdk::{clock::UnixTimestamp, pubkey::Pubkey, slot_history::Slot};
 use wasm_bindgen::prelude::*;
 
-use crate::{rpc::PgRpc, runtime::bank::PgBank};
+use crate::{cheats::PgCheats, rpc::PgRpc, runtime::bank::PgBank};
 
 #[wasm_bindgen]
 pub struct Playnet {
@@ -16,23 +17,36 @@ pub struct Playnet {
     #[wasm_bindgen(getter_with_clone)]
     pub rpc: PgRpc,
 
+    /// Cheat codes to arrange the Playnet state directly, meant for tests
+    #[wasm_bindgen(getter_with_clone)]
+    pub cheats: PgCheats,
+
     /// Reference to the bank
     bank: Rc<RwLock<PgBank>>,
 }
 
 #[wasm_bindgen]
 impl Playnet {
-    /// Playnet lifecycle starts after constructing a Playnet instance
+    /// Playnet lifecycle starts after constructing a Playnet instance.
+    ///
+    /// The fee per signature defaults to the saved fee, or 5000 lamports for new banks.
     #[wasm_bindgen(constructor)]
-    pub fn new(maybe_bank_string: Option<String>) -> Self {
+    pub fn new(
+        maybe_bank_string: Option<String>,
+        maybe_lamports_per_signature: Option<u64>,
+    ) -> Self {
         // Get WASM errors in console
         console_error_panic_hook::set_once();
 
         // Create the bank
-        let bank = Rc::new(RwLock::new(PgBank::new(maybe_bank_string)));
+        let bank = Rc::new(RwLock::new(PgBank::new(
+            maybe_bank_string,
+            maybe_lamports_per_signature,
+        )));
 
         Self {
             rpc: PgRpc::new(Rc::clone(&bank)),
+            cheats: PgCheats::new(Rc::clone(&bank)),
             bank: Rc::clone(&bank),
         }
     }
@@ -42,6 +56,71 @@ impl Playnet {
     pub fn get_save_data(&self) -> String {
         serde_json::to_string(&*self.bank.read().unwrap()).unwrap()
     }
+
+    /// Seal the current block if the block interval has passed since the block started.
+    ///
+    /// This should be called periodically for slots to progress while there are no
+    /// transactions. Returns whether the block was sealed.
+    pub fn tick(&self) -> bool {
+        self.bank.write().unwrap().tick()
+    }
+
+    /// Seal the current block on demand and return its slot
+    #[wasm_bindgen(js_name = sealBlock)]
+    pub fn seal_block(&self) -> Slot {
+        self.bank.write().unwrap().seal_block()
+    }
+
+    /// Set the interval of sealing blocks, blocks are only sealed on demand if not specified or
+    /// `0`
+    #[wasm_bindgen(js_name = setBlockInterval)]
+    pub fn set_block_interval(&self, maybe_block_interval_ms: Option<u64>) {
+        self.bank
+            .write()
+            .unwrap()
+            .set_block_interval_ms(maybe_block_interval_ms);
+    }
+
+    /// Seal the current block and skip the slots until the given slot
+    #[wasm_bindgen(js_name = warpToSlot)]
+    pub fn warp_to_slot(&self, slot: Slot) -> Result<(), String> {
+        self.bank.write().unwrap().warp_to_slot(slot)
+    }
+
+    /// Move the clock forward to the given unix timestamp in seconds
+    #[wasm_bindgen(js_name = warpToTimestamp)]
+    pub fn warp_to_timestamp(&self, unix_timestamp: UnixTimestamp) -> Result<(), String> {
+        self.bank.write().unwrap().warp_to_timestamp(unix_timestamp)
+    }
+
+    /// Set the milliseconds the clock advances per slot, the clock follows the real time if
+    /// not specified
+    #[wasm_bindgen(js_name = setClockMsPerSlot)]
+    pub fn set_clock_ms_per_slot(&self, maybe_ms_per_slot: Option<u64>) {
+        self.bank
+            .write()
+            .unwrap()
+            .set_clock_ms_per_slot(maybe_ms_per_slot);
+    }
+
+    /// Set the epoch schedule, all epochs have the same amount of slots if `warmup` is `false`
+    #[wasm_bindgen(js_name = setEpochSchedule)]
+    pub fn set_epoch_schedule(&self, slots_per_epoch: u64, warmup: bool) -> Result<(), String> {
+        self.bank
+            .write()
+            .unwrap()
+            .set_epoch_schedule(slots_per_epoch, warmup)
+    }
+}
+
+impl Playnet {
+    /// Add the program at the given address without deploying it with transactions.
+    pub fn add_program(&self, program_id: Pubkey, program_bytes: Vec<u8>) {
+        self.bank
+            .write()
+            .unwrap()
+            .add_program(program_id, program_bytes);
+    }
 }
 
 #[cfg(test)]
@@ -51,23 +130,39 @@ wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 pub mod test {
     use std::str::FromStr;
 
+    use solana_address_lookup_table_program::instruction::{
+        close_lookup_table, create_lookup_table_signed, deactivate_lookup_table,
+        extend_lookup_table,
+    };
     use solana_sdk::{
+        account::{from_account, Account},
+        address_lookup_table_account::AddressLookupTableAccount,
         bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        clock::Clock,
+        compute_budget::ComputeBudgetInstruction,
+        epoch_schedule::EpochSchedule,
         hash::Hash,
         instruction::{AccountMeta, Instruction},
-        message::Message,
+        message::{v0, Message, VersionedMessage},
         native_token::LAMPORTS_PER_SOL,
+        nonce,
         packet::PACKET_DATA_SIZE,
         pubkey::Pubkey,
         signature::Keypair,
         signer::Signer,
         signers::Signers,
-        system_instruction, system_program,
-        transaction::Transaction,
+        stake_history::StakeHistory,
+        system_instruction, system_program, sysvar,
+        transaction::{Transaction, TransactionError, VersionedTransaction},
     };
     use wasm_bindgen_test::*;
 
-    use crate::test_programs;
+    use crate::{
+        runtime::sysvars::{EPOCH_REWARDS_ID, LAST_RESTART_SLOT_ID},
+        test_programs,
+        types::WasmEncoding,
+        utils::get_current_timestamp_ms,
+    };
 
     use super::Playnet;
 
@@ -75,7 +170,7 @@ pub mod test {
     #[test]
     #[wasm_bindgen_test]
     fn transfer() {
-        let playnet = Playnet::new(None);
+        let playnet = Playnet::new(None, None);
 
         let sender_kp = get_payer(&playnet);
         let sender_pk = sender_kp.pubkey();
@@ -90,16 +185,25 @@ pub mod test {
             &receiver_pk,
             send_amount,
         )];
-        send_tx(ixs, &sender_pk, [&sender_kp], &playnet);
+        let tx = create_tx(ixs, &sender_pk, [&sender_kp], &playnet);
+        let result = playnet.rpc.send_transaction(&tx, None).unwrap();
+        assert_eq!(result.error(), None);
+
+        // Resending the same transaction doesn't transfer again
+        let result = playnet.rpc.send_transaction(&tx, None).unwrap();
+        assert_eq!(
+            result.error(),
+            Some(TransactionError::AlreadyProcessed.to_string())
+        );
 
         // Get fee for message
         let fee = playnet
             .rpc
             .get_fee_for_message(
-                serde_json::to_string(&Message::new(ixs, Some(&sender_pk)))
-                    .unwrap()
-                    .as_bytes(),
+                &bincode::serialize(&Message::new(ixs, Some(&sender_pk))).unwrap(),
+                None,
             )
+            .unwrap()
             .unwrap();
 
         let sender_balance = playnet
@@ -115,11 +219,925 @@ pub mod test {
         assert_eq!(receiver_balance, send_amount);
     }
 
+    /// Tests whether fees are charged from the fee payer, including for failed transactions
+    #[test]
+    #[wasm_bindgen_test]
+    fn fees() {
+        let playnet = Playnet::new(None, Some(10_000));
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let receiver_pk = Keypair::new().pubkey();
+        let get_fee = |ixs: &[Instruction]| {
+            playnet
+                .rpc
+                .get_fee_for_message(
+                    &bincode::serialize(&Message::new(ixs, Some(&payer_pk))).unwrap(),
+                    None,
+                )
+                .unwrap()
+                .unwrap()
+        };
+        let get_balance = || playnet.rpc.get_account_info(&payer_pk.to_string()).lamports;
+
+        // Signature fee
+        let ixs = &[system_instruction::transfer(&payer_pk, &receiver_pk, 1)];
+        assert_eq!(get_fee(ixs), 10_000);
+
+        // Prioritization fee, 1000 CU * 1 lamport
+        let priority_ixs = &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+            ixs[0].clone(),
+        ];
+        assert_eq!(get_fee(priority_ixs), 11_000);
+
+        // Failed transactions are charged the fee
+        let ixs = &[system_instruction::transfer(
+            &payer_pk,
+            &receiver_pk,
+            self::AIRDROP_AMOUNT,
+        )];
+        let result = playnet
+            .rpc
+            .send_transaction(&create_tx(ixs, &payer_pk, [&payer_kp], &playnet), None)
+            .unwrap();
+        assert!(result.error().is_some());
+        assert_eq!(get_balance(), self::AIRDROP_AMOUNT - 10_000);
+        assert_eq!(
+            playnet
+                .rpc
+                .get_account_info(&receiver_pk.to_string())
+                .lamports,
+            0
+        );
+
+        // Nonce accounts can pay the fee as long as they stay rent-exempt
+        let nonce_kp = Keypair::new();
+        let nonce_pk = nonce_kp.pubkey();
+        let nonce_rent = playnet
+            .rpc
+            .get_minimum_balance_for_rent_exemption(nonce::State::size());
+        // Enough for the fee of a single transaction with 2 signatures
+        let ixs = system_instruction::create_nonce_account(
+            &payer_pk,
+            &nonce_pk,
+            &payer_pk,
+            nonce_rent + 20_000,
+        );
+        send_tx(&ixs, &payer_pk, [&payer_kp, &nonce_kp], &playnet);
+
+        let send_with_nonce_payer = |lamports| {
+            let ixs = &[system_instruction::transfer(
+                &payer_pk,
+                &receiver_pk,
+                lamports,
+            )];
+            playnet
+                .rpc
+                .send_transaction(
+                    &create_tx(ixs, &nonce_pk, [&nonce_kp, &payer_kp], &playnet),
+                    None,
+                )
+                .unwrap()
+                .error()
+        };
+        assert_eq!(send_with_nonce_payer(1), None);
+        assert_eq!(
+            send_with_nonce_payer(2),
+            Some(TransactionError::InsufficientFundsForFee.to_string())
+        );
+        assert_eq!(
+            playnet.rpc.get_account_info(&nonce_pk.to_string()).lamports,
+            nonce_rent
+        );
+    }
+
+    /// Tests whether compute budget instructions are applied to the transaction
+    #[test]
+    #[wasm_bindgen_test]
+    fn compute_budget() {
+        let playnet = Playnet::new(None, None);
+
+        let owner_kp = get_payer(&playnet);
+        let owner_pk = owner_kp.pubkey();
+
+        let program_id = deploy_program(
+            test_programs::hello_world::PROGRAM_BYTES,
+            &owner_kp,
+            &playnet,
+        )
+        .unwrap();
+        let hello_world_ix = Instruction::new_with_bytes(program_id, &[], vec![]);
+
+        // Not enough compute units
+        let result = playnet
+            .rpc
+            .simulate_transaction(
+                &create_tx(
+                    &[
+                        ComputeBudgetInstruction::set_compute_unit_limit(1),
+                        hello_world_ix.clone(),
+                    ],
+                    &owner_pk,
+                    [&owner_kp],
+                    &playnet,
+                ),
+                None,
+            )
+            .unwrap();
+        assert!(result.error().is_some());
+        assert!(result.units_consumed() <= 1);
+
+        // Prioritization fee is charged
+        let balance = || playnet.rpc.get_account_info(&owner_pk.to_string()).lamports;
+        let pre_balance = balance();
+        let tx_hash = send_tx(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+                ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+                hello_world_ix,
+            ],
+            &owner_pk,
+            [&owner_kp],
+            &playnet,
+        );
+        let meta = playnet.rpc.get_transaction(&tx_hash).meta();
+        assert!(meta.err().is_none());
+        assert_eq!(meta.fee(), 5000 + 10_000);
+        assert_eq!(balance(), pre_balance - meta.fee());
+    }
+
+    /// Tests whether address lookup tables work with v0 transactions
+    #[test]
+    #[wasm_bindgen_test]
+    fn address_lookup_table() {
+        let playnet = Playnet::new(None, None);
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let receiver_pk = Keypair::new().pubkey();
+
+        // Create and extend the lookup table, the recent slot must be in the slot hashes
+        playnet.seal_block();
+        let recent_slot = playnet.rpc.get_slot() - 1;
+        let (create_ix, table_pk) = create_lookup_table_signed(payer_pk, payer_pk, recent_slot);
+        let extend_ix = extend_lookup_table(table_pk, payer_pk, Some(payer_pk), vec![receiver_pk]);
+        send_tx(&[create_ix, extend_ix], &payer_pk, [&payer_kp], &playnet);
+
+        // Extended addresses can only be used starting from the next slot
+        playnet.seal_block();
+
+        // Transfer to the address from the lookup table
+        let msg = v0::Message::try_compile(
+            &payer_pk,
+            &[system_instruction::transfer(
+                &payer_pk,
+                &receiver_pk,
+                LAMPORTS_PER_SOL,
+            )],
+            &[AddressLookupTableAccount {
+                key: table_pk,
+                addresses: vec![receiver_pk],
+            }],
+            Hash::from_str(&playnet.rpc.get_latest_blockhash().blockhash()).unwrap(),
+        )
+        .unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&payer_kp]).unwrap();
+        let result = playnet
+            .rpc
+            .send_transaction(&bincode::serialize(&tx).unwrap(), None)
+            .unwrap();
+
+        let tx = playnet.rpc.get_transaction(&result.tx_hash());
+        assert_eq!(tx.version(), Some(0));
+        let loaded_addresses = tx.meta().loaded_addresses.unwrap();
+        assert_eq!(loaded_addresses.writable, vec![receiver_pk]);
+        assert!(loaded_addresses.readonly.is_empty());
+        assert_eq!(
+            playnet
+                .rpc
+                .get_account_info(&receiver_pk.to_string())
+                .lamports,
+            LAMPORTS_PER_SOL
+        );
+
+        // Deactivated tables can't be closed until the deactivation cooldown ends
+        send_tx(
+            &[deactivate_lookup_table(table_pk, payer_pk)],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        );
+        let result = playnet
+            .rpc
+            .send_transaction(
+                &create_tx(
+                    &[close_lookup_table(table_pk, payer_pk, payer_pk)],
+                    &payer_pk,
+                    [&payer_kp],
+                    &playnet,
+                ),
+                None,
+            )
+            .unwrap();
+        assert!(result.error().is_some());
+    }
+
+    /// Tests whether transactions are accepted in all encodings and malformed input is rejected
+    #[test]
+    #[wasm_bindgen_test]
+    fn encodings() {
+        let playnet = Playnet::new(None, None);
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let receiver_pk = Keypair::new().pubkey();
+
+        let create_transfer_tx = |lamports| {
+            Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer_pk,
+                    &receiver_pk,
+                    lamports,
+                )],
+                Some(&payer_pk),
+                &[&payer_kp],
+                Hash::from_str(&playnet.rpc.get_latest_blockhash().blockhash()).unwrap(),
+            )
+        };
+        let send = |serialized_tx: &[u8], encoding| {
+            playnet
+                .rpc
+                .send_transaction(serialized_tx, Some(encoding))
+                .map(|result| result.error())
+        };
+
+        // Each transaction has a different amount to get a unique signature
+        let tx = bincode::serialize(&create_transfer_tx(1)).unwrap();
+        assert_eq!(send(&tx, WasmEncoding::Binary), Ok(None));
+        let tx = base64::encode(bincode::serialize(&create_transfer_tx(2)).unwrap());
+        assert_eq!(send(tx.as_bytes(), WasmEncoding::Base64), Ok(None));
+        let tx = serde_json::to_vec(&create_transfer_tx(3)).unwrap();
+        assert_eq!(send(&tx, WasmEncoding::Json), Ok(None));
+        assert_eq!(
+            playnet
+                .rpc
+                .get_account_info(&receiver_pk.to_string())
+                .lamports,
+            6
+        );
+
+        // Malformed input
+        assert!(send(&[1, 2, 3], WasmEncoding::Binary).is_err());
+        assert!(send(b"not base64", WasmEncoding::Base64).is_err());
+        assert!(send(b"{}", WasmEncoding::Json).is_err());
+        assert!(playnet.rpc.get_fee_for_message(&[], None).is_err());
+
+        // Trailing bytes are ignored (same as the real RPC)
+        let mut tx = bincode::serialize(&create_transfer_tx(4)).unwrap();
+        tx.push(0);
+        assert_eq!(send(&tx, WasmEncoding::Binary), Ok(None));
+    }
+
+    /// Tests whether the JSON-RPC front works as expected
+    #[test]
+    #[wasm_bindgen_test]
+    fn json_rpc() {
+        let playnet = Playnet::new(None, None);
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let receiver_pk = Keypair::new().pubkey();
+
+        let request = |request: serde_json::Value| {
+            let response = playnet.rpc.handle_request(&request.to_string());
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()
+        };
+
+        // Single request
+        let response = request(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getBalance",
+            "params": [payer_pk.to_string()],
+        }));
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["value"], AIRDROP_AMOUNT);
+
+        // Send a base64 encoded transaction
+        let tx = create_tx(
+            &[system_instruction::transfer(&payer_pk, &receiver_pk, 1)],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        );
+        let response = request(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "send",
+            "method": "sendTransaction",
+            "params": [base64::encode(tx), { "encoding": "base64" }],
+        }));
+        let signature = response["result"].as_str().unwrap().to_owned();
+
+        // Batch
+        let response = request(serde_json::json!([
+            {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getSignatureStatuses",
+                "params": [[signature]],
+            },
+            {
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "getAccountInfo",
+                "params": [receiver_pk.to_string(), { "encoding": "base64" }],
+            },
+            { "jsonrpc": "2.0", "id": 3, "method": "unknownMethod" },
+            { "jsonrpc": "2.0", "id": 4, "method": "getBalance", "params": ["invalid"] },
+            { "jsonrpc": "2.0", "method": "getSlot" },
+        ]));
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(
+            responses[0]["result"]["value"][0]["err"],
+            serde_json::Value::Null
+        );
+        assert_eq!(responses[1]["result"]["value"]["lamports"], 1);
+        assert_eq!(responses[2]["error"]["code"], -32601);
+        assert_eq!(responses[3]["error"]["code"], -32602);
+
+        // Execution errors fail the preflight, and they are ignored when the preflight is skipped
+        let tx = base64::encode(create_tx(
+            &[system_instruction::transfer(
+                &payer_pk,
+                &receiver_pk,
+                AIRDROP_AMOUNT,
+            )],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        ));
+        let send = |tx: &str, skip_preflight| {
+            rpc_request(
+                "sendTransaction",
+                serde_json::json!([tx, { "encoding": "base64", "skipPreflight": skip_preflight }]),
+                &playnet,
+            )
+        };
+        let response = send(&tx, false);
+        assert_eq!(response["error"]["code"], -32002);
+        assert!(response["error"]["data"]["logs"].is_array());
+        let signature = send(&tx, true)["result"].as_str().unwrap().to_owned();
+        let get_status = |signature: &str| {
+            rpc_request(
+                "getSignatureStatuses",
+                serde_json::json!([[signature]]),
+                &playnet,
+            )["result"]["value"][0]
+                .clone()
+        };
+        assert!(!get_status(&signature)["err"].is_null());
+
+        // Already processed transactions fail the preflight
+        let tx = base64::encode(create_tx(
+            &[system_instruction::transfer(&payer_pk, &receiver_pk, 2)],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        ));
+        let signature = send(&tx, false)["result"].as_str().unwrap().to_owned();
+        assert!(get_status(&signature)["err"].is_null());
+        let response = send(&tx, false);
+        assert_eq!(response["error"]["code"], -32002);
+        assert_eq!(response["error"]["data"]["err"], "AlreadyProcessed");
+
+        // Transactions that couldn't be processed are dropped when the preflight is skipped
+        let unfunded_kp = Keypair::new();
+        let tx = base64::encode(create_tx(
+            &[system_instruction::transfer(
+                &unfunded_kp.pubkey(),
+                &receiver_pk,
+                1,
+            )],
+            &unfunded_kp.pubkey(),
+            [&unfunded_kp],
+            &playnet,
+        ));
+        assert_eq!(send(&tx, false)["error"]["code"], -32002);
+        let signature = send(&tx, true)["result"].as_str().unwrap().to_owned();
+        assert!(get_status(&signature).is_null());
+
+        // Invalid signature
+        let mut tx = create_tx(
+            &[system_instruction::transfer(&payer_pk, &receiver_pk, 1)],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        );
+        tx[1] ^= 1;
+        assert_eq!(send(&base64::encode(tx), true)["error"]["code"], -32003);
+
+        // Errors
+        assert_eq!(request(serde_json::json!([]))["error"]["code"], -32600);
+        let response = playnet.rpc.handle_request("{");
+        let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+        assert_eq!(
+            playnet
+                .rpc
+                .handle_request(r#"{"jsonrpc":"2.0","method":"getSlot"}"#),
+            ""
+        );
+    }
+
+    /// Tests whether the account and signature query methods of the JSON-RPC front work as
+    /// expected
+    #[test]
+    #[wasm_bindgen_test]
+    fn json_rpc_queries() {
+        let playnet = Playnet::new(None, None);
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+
+        // Create accounts owned by a program
+        let program_id = Pubkey::new_unique();
+        let account_kps = [Keypair::new(), Keypair::new()];
+        let mut tx_hashes = vec![];
+        for (i, account_kp) in account_kps.iter().enumerate() {
+            let tx_hash = send_tx(
+                &[system_instruction::create_account(
+                    &payer_pk,
+                    &account_kp.pubkey(),
+                    LAMPORTS_PER_SOL / 10,
+                    8 + i as u64,
+                    &program_id,
+                )],
+                &payer_pk,
+                [&payer_kp, account_kp],
+                &playnet,
+            );
+            tx_hashes.push(tx_hash);
+        }
+
+        // getProgramAccounts
+        let accounts = &rpc_request(
+            "getProgramAccounts",
+            serde_json::json!([program_id.to_string()]),
+            &playnet,
+        )["result"];
+        assert_eq!(accounts.as_array().unwrap().len(), 2);
+        let accounts = &rpc_request(
+            "getProgramAccounts",
+            serde_json::json!([
+                program_id.to_string(),
+                {
+                    "encoding": "base64",
+                    "filters": [{ "dataSize": 9 }, { "memcmp": { "offset": 1, "bytes": "1" } }],
+                },
+            ]),
+            &playnet,
+        )["result"];
+        assert_eq!(
+            accounts[0]["pubkey"],
+            account_kps[1].pubkey().to_string().as_str()
+        );
+        assert_eq!(accounts.as_array().unwrap().len(), 1);
+        let accounts = &rpc_request(
+            "getProgramAccounts",
+            serde_json::json!([program_id.to_string(), { "filters": [{ "dataSize": 10 }] }]),
+            &playnet,
+        )["result"];
+        assert!(accounts.as_array().unwrap().is_empty());
+
+        // getMultipleAccounts
+        let accounts = &rpc_request(
+            "getMultipleAccounts",
+            serde_json::json!([[
+                account_kps[0].pubkey().to_string(),
+                Pubkey::new_unique().to_string(),
+            ]]),
+            &playnet,
+        )["result"];
+        assert_eq!(accounts["value"][0]["lamports"], LAMPORTS_PER_SOL / 10);
+        assert!(accounts["value"][1].is_null());
+
+        // getTokenAccountsByOwner
+        let token_program_id =
+            Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        let mint = Pubkey::new_unique();
+        let token_account_pk = Pubkey::new_unique();
+        let mut token_account = Account::new(LAMPORTS_PER_SOL, 165, &token_program_id);
+        token_account.data[..32].copy_from_slice(mint.as_ref());
+        token_account.data[32..64].copy_from_slice(payer_pk.as_ref());
+        token_account.data[108] = 1;
+        playnet
+            .bank
+            .write()
+            .unwrap()
+            .set_account(token_account_pk, token_account);
+        let accounts = &rpc_request(
+            "getTokenAccountsByOwner",
+            serde_json::json!([payer_pk.to_string(), { "mint": mint.to_string() }]),
+            &playnet,
+        )["result"];
+        assert_eq!(
+            accounts["value"][0]["pubkey"],
+            token_account_pk.to_string().as_str()
+        );
+        let accounts = &rpc_request(
+            "getTokenAccountsByOwner",
+            serde_json::json!([
+                payer_pk.to_string(),
+                { "mint": Pubkey::new_unique().to_string() },
+            ]),
+            &playnet,
+        )["result"];
+        assert!(accounts["value"].as_array().unwrap().is_empty());
+
+        // getSignaturesForAddress, newest first
+        let signatures = &rpc_request(
+            "getSignaturesForAddress",
+            serde_json::json!([payer_pk.to_string()]),
+            &playnet,
+        )["result"];
+        let signatures = signatures.as_array().unwrap();
+        assert_eq!(signatures.len(), 3);
+        let slots = signatures
+            .iter()
+            .map(|signature| signature["slot"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert!(slots.windows(2).all(|slots| slots[0] >= slots[1]));
+        assert_eq!(signatures[0]["signature"], tx_hashes[1].as_str());
+        assert_eq!(signatures[1]["signature"], tx_hashes[0].as_str());
+        let before = &rpc_request(
+            "getSignaturesForAddress",
+            serde_json::json!([
+                payer_pk.to_string(),
+                { "before": signatures[0]["signature"], "limit": 1 },
+            ]),
+            &playnet,
+        )["result"];
+        assert_eq!(before[0]["signature"], signatures[1]["signature"]);
+        assert_eq!(before.as_array().unwrap().len(), 1);
+    }
+
+    /// Tests whether transactions are accumulated into blocks and blocks are served
+    #[test]
+    #[wasm_bindgen_test]
+    fn blocks() {
+        let playnet = Playnet::new(None, None);
+        playnet.set_block_interval(None);
+
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let receiver_pk = Keypair::new().pubkey();
+
+        // Transactions accumulate into the current block
+        let slot = playnet.rpc.get_slot();
+        let tx_hashes = (1..=2)
+            .map(|lamports| {
+                send_tx(
+                    &[system_instruction::transfer(
+                        &payer_pk,
+                        &receiver_pk,
+                        lamports,
+                    )],
+                    &payer_pk,
+                    [&payer_kp],
+                    &playnet,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(playnet.rpc.get_slot(), slot);
+        assert_eq!(
+            rpc_request("getBlock", serde_json::json!([slot]), &playnet)["error"]["code"],
+            -32004
+        );
+        assert!(playnet
+            .rpc
+            .get_transaction(&tx_hashes[0])
+            .block_time()
+            .is_none());
+
+        // Seal the block
+        let genesis_hash = playnet.rpc.get_genesis_hash();
+        assert_eq!(playnet.seal_block(), slot);
+        assert_eq!(playnet.rpc.get_slot(), slot + 1);
+        assert_eq!(playnet.rpc.get_genesis_hash(), genesis_hash);
+        assert_ne!(playnet.rpc.get_latest_blockhash().blockhash(), genesis_hash);
+        assert!(playnet
+            .rpc
+            .get_transaction(&tx_hashes[0])
+            .block_time()
+            .is_some());
+
+        let block = rpc_request("getBlock", serde_json::json!([slot]), &playnet)["result"].take();
+        assert_eq!(block["parentSlot"], slot.saturating_sub(1));
+        assert_eq!(block["transactions"].as_array().unwrap().len(), 3);
+        let block = rpc_request(
+            "getBlock",
+            serde_json::json!([slot, { "transactionDetails": "signatures" }]),
+            &playnet,
+        )["result"]
+            .take();
+        assert_eq!(block["signatures"][1], tx_hashes[0].as_str());
+        assert_eq!(block["signatures"][2], tx_hashes[1].as_str());
+        let blockhash = block["blockhash"].take();
+
+        let block_time =
+            rpc_request("getBlockTime", serde_json::json!([slot]), &playnet)["result"].take();
+        assert_eq!(block_time, block["blockTime"]);
+
+        // Empty blocks
+        playnet.seal_block();
+        let blocks = rpc_request("getBlocks", serde_json::json!([slot]), &playnet)["result"].take();
+        assert_eq!(blocks, serde_json::json!([slot, slot + 1]));
+        let block =
+            rpc_request("getBlock", serde_json::json!([slot + 1]), &playnet)["result"].take();
+        assert!(block["transactions"].as_array().unwrap().is_empty());
+        assert_eq!(block["previousBlockhash"], blockhash);
+
+        // Reads seal the due block and skip the slots of the other passed intervals
+        let slot = playnet.rpc.get_slot();
+        playnet.set_block_interval(Some(1));
+        let start_ms = get_current_timestamp_ms();
+        while get_current_timestamp_ms() < start_ms + 3 {}
+        let new_slot = playnet.rpc.get_slot();
+        playnet.set_block_interval(None);
+        assert!(new_slot >= slot + 3);
+        assert_eq!(
+            rpc_request("getBlocks", serde_json::json!([slot, new_slot]), &playnet)["result"],
+            serde_json::json!([slot])
+        );
+
+        // Zero interval is the same as not having an interval
+        let slot = playnet.rpc.get_slot();
+        playnet.set_block_interval(Some(0));
+        assert!(!playnet.tick());
+        assert_eq!(playnet.rpc.get_slot(), slot);
+        playnet.set_block_interval(None);
+    }
+
+    /// Tests whether the clock and the epochs progress with the blocks and can be warped
+    #[test]
+    #[wasm_bindgen_test]
+    fn clock() {
+        let playnet = Playnet::new(None, None);
+        playnet.set_block_interval(None);
+        playnet.set_clock_ms_per_slot(Some(400));
+        assert!(playnet.set_epoch_schedule(16, false).is_err());
+        playnet.set_epoch_schedule(32, false).unwrap();
+
+        let get_sysvar_account = |id| playnet.bank.read().unwrap().get_account_default(&id);
+        let get_clock = || from_account::<Clock, _>(&get_sysvar_account(sysvar::clock::id()));
+
+        // Sysvars
+        for id in [
+            sysvar::clock::id(),
+            sysvar::epoch_schedule::id(),
+            sysvar::rent::id(),
+            sysvar::slot_hashes::id(),
+            sysvar::stake_history::id(),
+            EPOCH_REWARDS_ID,
+            LAST_RESTART_SLOT_ID,
+        ] {
+            assert_eq!(get_sysvar_account(id).owner, sysvar::id());
+        }
+        let epoch_schedule =
+            from_account::<EpochSchedule, _>(&get_sysvar_account(sysvar::epoch_schedule::id()))
+                .unwrap();
+        assert_eq!(epoch_schedule.slots_per_epoch, 32);
+
+        // Simulated clock advances per slot
+        let clock = get_clock().unwrap();
+        assert_eq!(clock.slot, 0);
+        assert_eq!(clock.epoch, 0);
+        for _ in 0..5 {
+            playnet.seal_block();
+        }
+        let unix_timestamp = get_clock().unwrap().unix_timestamp;
+        assert_eq!(unix_timestamp, clock.unix_timestamp + 2);
+
+        // Warp to the next epoch
+        assert!(playnet.warp_to_slot(5).is_err());
+        playnet.warp_to_slot(40).unwrap();
+        let clock = get_clock().unwrap();
+        assert_eq!(clock.slot, 40);
+        assert_eq!(clock.epoch, 1);
+        assert_eq!(clock.leader_schedule_epoch, 2);
+        assert_eq!(clock.unix_timestamp, unix_timestamp + 14);
+        assert_eq!(clock.epoch_start_timestamp, clock.unix_timestamp);
+        let stake_history =
+            from_account::<StakeHistory, _>(&get_sysvar_account(sysvar::stake_history::id()))
+                .unwrap();
+        assert!(stake_history.get(0).is_some());
+
+        // Skipped slots don't have blocks
+        assert_eq!(playnet.seal_block(), 40);
+        assert_eq!(
+            rpc_request("getBlocks", serde_json::json!([4]), &playnet)["result"],
+            serde_json::json!([4, 5, 40])
+        );
+        assert_eq!(
+            rpc_request("getBlock", serde_json::json!([40]), &playnet)["result"]["parentSlot"],
+            5
+        );
+
+        let epoch_info = &rpc_request("getEpochInfo", serde_json::json!([]), &playnet)["result"];
+        assert_eq!(epoch_info["absoluteSlot"], 41);
+        assert_eq!(epoch_info["epoch"], 1);
+        assert_eq!(epoch_info["slotIndex"], 9);
+        assert_eq!(epoch_info["slotsInEpoch"], 32);
+        let epoch_schedule =
+            &rpc_request("getEpochSchedule", serde_json::json!([]), &playnet)["result"];
+        assert_eq!(epoch_schedule["slotsPerEpoch"], 32);
+
+        // Warp the timestamp
+        let unix_timestamp = get_clock().unwrap().unix_timestamp + 1000;
+        playnet.warp_to_timestamp(unix_timestamp).unwrap();
+        assert_eq!(get_clock().unwrap().unix_timestamp, unix_timestamp);
+        assert!(playnet.warp_to_timestamp(unix_timestamp - 1).is_err());
+
+        let slot = playnet.seal_block();
+        assert_eq!(
+            rpc_request("getBlockTime", serde_json::json!([slot]), &playnet)["result"],
+            unix_timestamp
+        );
+
+        // Skipped epochs are also added to the stake history
+        playnet.warp_to_slot(4 * 32).unwrap();
+        assert_eq!(get_clock().unwrap().epoch, 4);
+        let stake_history =
+            from_account::<StakeHistory, _>(&get_sysvar_account(sysvar::stake_history::id()))
+                .unwrap();
+        assert!((0..4).all(|epoch| stake_history.get(epoch).is_some()));
+
+        // Epoch schedules can't move the epoch backwards
+        assert!(playnet.set_epoch_schedule(64, false).is_err());
+        assert_eq!(get_clock().unwrap().epoch, 4);
+        playnet.set_epoch_schedule(32, false).unwrap();
+    }
+
+    /// Tests whether the cheat codes change the state directly
+    #[test]
+    #[wasm_bindgen_test]
+    fn cheats() {
+        let playnet = Playnet::new(None, None);
+        playnet.set_block_interval(None);
+
+        let get_account =
+            |pubkey: &Pubkey| playnet.bank.read().unwrap().get_account_default(pubkey);
+        let read_u64 = |data: &[u8], offset: usize| {
+            u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+        };
+
+        // Accounts
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        playnet
+            .cheats
+            .set_account(
+                &pubkey.to_string(),
+                100,
+                vec![1, 2, 3],
+                &owner.to_string(),
+                false,
+            )
+            .unwrap();
+        let account = get_account(&pubkey);
+        assert_eq!(account.lamports, 100);
+        assert_eq!(account.data, vec![1, 2, 3]);
+        assert_eq!(account.owner, owner);
+
+        playnet
+            .cheats
+            .set_lamports(&pubkey.to_string(), 200)
+            .unwrap();
+        let account = get_account(&pubkey);
+        assert_eq!(account.lamports, 200);
+        assert_eq!(account.data, vec![1, 2, 3]);
+        assert!(playnet.cheats.set_lamports("invalid", 200).is_err());
+
+        // Token balances
+        let mint = Pubkey::new_unique();
+        let mut mint_data = vec![0; 82];
+        mint_data[45] = 1;
+        playnet
+            .cheats
+            .set_account(
+                &mint.to_string(),
+                LAMPORTS_PER_SOL,
+                mint_data,
+                "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                false,
+            )
+            .unwrap();
+        let wallet = Pubkey::new_unique();
+        let token_account_pk = playnet
+            .cheats
+            .set_token_balance(&wallet.to_string(), &mint.to_string(), 1000)
+            .unwrap();
+        assert_eq!(
+            playnet
+                .cheats
+                .set_token_balance(&wallet.to_string(), &mint.to_string(), 600)
+                .unwrap(),
+            token_account_pk
+        );
+        let token_account = get_account(&Pubkey::from_str(&token_account_pk).unwrap());
+        assert_eq!(read_u64(&token_account.data, 64), 600);
+        assert_eq!(read_u64(&get_account(&mint).data, 36), 600);
+        assert!(playnet
+            .cheats
+            .set_token_balance(&wallet.to_string(), &pubkey.to_string(), 600)
+            .is_err());
+
+        // Token 2022 mints with extensions and native mints are not supported
+        let mint_2022 = Pubkey::new_unique();
+        let mut mint_data = vec![0; 170];
+        mint_data[45] = 1;
+        playnet
+            .cheats
+            .set_account(
+                &mint_2022.to_string(),
+                LAMPORTS_PER_SOL,
+                mint_data.clone(),
+                "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+                false,
+            )
+            .unwrap();
+        assert!(playnet
+            .cheats
+            .set_token_balance(&wallet.to_string(), &mint_2022.to_string(), 600)
+            .is_err());
+        let native_mint = "So11111111111111111111111111111111111111112";
+        playnet
+            .cheats
+            .set_account(
+                native_mint,
+                LAMPORTS_PER_SOL,
+                mint_data[..82].to_vec(),
+                "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                false,
+            )
+            .unwrap();
+        assert!(playnet
+            .cheats
+            .set_token_balance(&wallet.to_string(), native_mint, 600)
+            .is_err());
+
+        let response = rpc_request(
+            "getTokenAccountsByOwner",
+            serde_json::json!([wallet.to_string(), { "mint": mint.to_string() }]),
+            &playnet,
+        );
+        assert_eq!(
+            response["result"]["value"][0]["pubkey"],
+            token_account_pk.as_str()
+        );
+
+        // Programs
+        let program_id = Pubkey::new_unique();
+        playnet
+            .cheats
+            .deploy_program(
+                &program_id.to_string(),
+                test_programs::hello_world::PROGRAM_BYTES.to_vec(),
+            )
+            .unwrap();
+        let payer_kp = get_payer(&playnet);
+        let payer_pk = payer_kp.pubkey();
+        let tx_hash = send_tx(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            &payer_pk,
+            [&payer_kp],
+            &playnet,
+        );
+        let logs = playnet.rpc.get_transaction(&tx_hash).meta().log_messages;
+        assert!(logs
+            .unwrap()
+            .iter()
+            .any(|log| log == "Program log: Hello, World!"));
+
+        // Clock and slot
+        playnet.cheats.set_unix_timestamp(1000);
+        assert_eq!(
+            playnet.bank.read().unwrap().get_clock().unix_timestamp,
+            1000
+        );
+        playnet.cheats.set_slot(100).unwrap();
+        assert_eq!(playnet.rpc.get_slot(), 100);
+        assert!(playnet.cheats.set_slot(50).is_err());
+    }
+
     /// Tests whether "hello world" program works as expected
     #[test]
     #[wasm_bindgen_test]
     fn hello_world() {
-        let playnet = Playnet::new(None);
+        let playnet = Playnet::new(None, None);
 
         let owner_kp = get_payer(&playnet);
         let owner_pk = owner_kp.pubkey();
@@ -149,7 +1167,7 @@ pub mod test {
     #[test]
     #[wasm_bindgen_test]
     fn transfer_cpi() {
-        let playnet = Playnet::new(None);
+        let playnet = Playnet::new(None, None);
 
         let owner_kp = get_payer(&playnet);
         let owner_pk = owner_kp.pubkey();
@@ -227,21 +1245,47 @@ pub mod test {
         signers: impl Signers,
         playnet: &Playnet,
     ) -> String {
+        let result = playnet
+            .rpc
+            .send_transaction(&create_tx(ixs, payer, signers, playnet), None)
+            .unwrap();
+
+        result.tx_hash()
+    }
+
+    /// Sends the JSON-RPC request and returns the response
+    fn rpc_request(
+        method: &str,
+        params: serde_json::Value,
+        playnet: &Playnet,
+    ) -> serde_json::Value {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": method,
+            "params": params,
+        });
+        let response = playnet.rpc.handle_request(&request.to_string());
+        serde_json::from_str(&response).unwrap()
+    }
+
+    /// Returns the signed tx in wire format
+    fn create_tx(
+        ixs: &[Instruction],
+        payer: &Pubkey,
+        signers: impl Signers,
+        playnet: &Playnet,
+    ) -> Vec<u8> {
         let latest_blockhash =
             Hash::from_str(&playnet.rpc.get_latest_blockhash().blockhash()).unwrap();
 
-        let result = playnet.rpc.send_transaction(
-            serde_json::to_string(&Transaction::new_signed_with_payer(
-                ixs,
-                Some(&payer),
-                &signers,
-                latest_blockhash,
-            ))
-            .unwrap()
-            .as_bytes(),
-        );
-
-        result.tx_hash()
+        bincode::serialize(&Transaction::new_signed_with_payer(
+            ixs,
+            Some(&payer),
+            &signers,
+            latest_blockhash,
+        ))
+        .unwrap()
     }
 
     /// Deploys the program and returns the program id
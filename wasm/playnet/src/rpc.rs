@@ -9,7 +9,7 @@ use std::{
 
 use solana_sdk::{
     feature_set::FeatureSet,
-    message::{Message, SanitizedMessage},
+    message::{SanitizedMessage, SanitizedVersionedMessage},
     pubkey::Pubkey,
     signature::Signature,
     slot_history::Slot,
@@ -18,15 +18,18 @@ use solana_sdk::{
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    runtime::bank::PgBank,
+    runtime::bank::{PgAddressLoader, PgBank},
     types::{
         GetLatestBlockhashResult, GetSignatureStatusesResult, GetTransactionResult,
         SendTransactionResult, SimulateTransactionResult, TransactionStatus, WasmAccount,
-        WasmCommitmentLevel,
+        WasmCommitmentLevel, WasmEncoding,
     },
-    utils::get_sanitized_tx_from_serialized_tx,
+    utils::{deserialize_msg, deserialize_tx, get_sanitized_tx_from_versioned_tx},
 };
 
+pub(crate) mod filter;
+mod json_rpc;
+
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct PgRpc {
@@ -38,12 +41,18 @@ impl PgRpc {
         Self { bank }
     }
 
+    /// Get the bank after sealing the due blocks, in order to not serve a stale slot when the
+    /// host doesn't tick often enough.
     fn get_bank(&self) -> RwLockReadGuard<'_, PgBank> {
+        self.bank.write().unwrap().tick();
         self.bank.read().unwrap()
     }
 
+    /// Get the mutable bank after sealing the due blocks.
     fn get_bank_mut(&self) -> RwLockWriteGuard<'_, PgBank> {
-        self.bank.write().unwrap()
+        let mut bank = self.bank.write().unwrap();
+        bank.tick();
+        bank
     }
 }
 
@@ -84,49 +93,71 @@ impl PgRpc {
             .get_minimum_balance_for_rent_exemption(data_len)
     }
 
+    /// Serialized message is in wire format by default.
+    ///
+    /// Errors if the message couldn't be de-serialized or sanitized.
     #[wasm_bindgen(js_name = getFeeForMessage)]
-    pub fn get_fee_for_message(&self, serialized_msg: &[u8]) -> Option<u64> {
-        let msg: Message = serde_json::from_slice(serialized_msg).unwrap();
-        self.get_bank()
-            .get_fee_for_message(&SanitizedMessage::try_from(msg).unwrap())
+    pub fn get_fee_for_message(
+        &self,
+        serialized_msg: &[u8],
+        encoding: Option<WasmEncoding>,
+    ) -> Result<Option<u64>, String> {
+        let msg = deserialize_msg(serialized_msg, encoding.unwrap_or_default())?;
+        let msg = SanitizedVersionedMessage::try_from(msg).map_err(|e| e.to_string())?;
+
+        let bank = self.get_bank();
+        let msg = SanitizedMessage::try_new(msg, PgAddressLoader::new(&bank))
+            .map_err(|e| e.to_string())?;
+        Ok(bank.get_fee_for_message(&msg))
     }
 
+    /// Serialized transaction is in wire format by default.
+    ///
+    /// Errors if the transaction couldn't be de-serialized.
     #[wasm_bindgen(js_name = simulateTransaction)]
-    pub fn simulate_transaction(&self, serialized_tx: &[u8]) -> SimulateTransactionResult {
-        let sanitized_transaction = match get_sanitized_tx_from_serialized_tx(serialized_tx) {
-            Ok(tx) => tx,
-            Err(err) => return SimulateTransactionResult::new_error(err),
-        };
+    pub fn simulate_transaction(
+        &self,
+        serialized_tx: &[u8],
+        encoding: Option<WasmEncoding>,
+    ) -> Result<SimulateTransactionResult, String> {
+        let tx = deserialize_tx(serialized_tx, encoding.unwrap_or_default())?;
 
         let bank = self.get_bank();
-        bank.simulate_tx(&sanitized_transaction)
+        let sanitized_tx = match get_sanitized_tx_from_versioned_tx(tx, PgAddressLoader::new(&bank))
+        {
+            Ok(sanitized_tx) => sanitized_tx,
+            Err(err) => return Ok(SimulateTransactionResult::new_error(err)),
+        };
+
+        Ok(bank.simulate_tx(&sanitized_tx))
     }
 
+    /// Serialized transaction is in wire format by default.
+    ///
+    /// Errors if the transaction couldn't be de-serialized.
     #[wasm_bindgen(js_name = sendTransaction)]
-    pub fn send_transaction(&self, serialized_tx: &[u8]) -> SendTransactionResult {
-        let sanitized_tx = match get_sanitized_tx_from_serialized_tx(serialized_tx) {
+    pub fn send_transaction(
+        &self,
+        serialized_tx: &[u8],
+        encoding: Option<WasmEncoding>,
+    ) -> Result<SendTransactionResult, String> {
+        let tx = deserialize_tx(serialized_tx, encoding.unwrap_or_default())?;
+
+        let mut bank = self.get_bank_mut();
+        let sanitized_tx = match get_sanitized_tx_from_versioned_tx(tx, PgAddressLoader::new(&bank))
+        {
             Ok(sanitized_tx) => sanitized_tx,
-            Err(err) => return SendTransactionResult::new_error(err),
+            Err(err) => return Ok(SendTransactionResult::new_error(err)),
         };
 
-        fn verify_transaction(
-            transaction: &SanitizedTransaction,
-            feature_set: &FeatureSet,
-        ) -> transaction::Result<()> {
-            transaction.verify()?;
-            transaction.verify_precompiles(feature_set)?;
-            Ok(())
-        }
-
-        let mut bank = self.get_bank_mut();
         if let Err(err) = verify_transaction(&sanitized_tx, &bank.feature_set()) {
-            return SendTransactionResult::new_error(err);
+            return Ok(SendTransactionResult::new_error(err));
         }
 
-        match bank.process_tx(sanitized_tx) {
+        Ok(match bank.process_tx(sanitized_tx) {
             Ok(tx_hash) => SendTransactionResult::new(tx_hash.to_string()),
             Err(err) => SendTransactionResult::new_error(err),
-        }
+        })
     }
 
     #[wasm_bindgen(js_name = getSignatureStatuses)]
@@ -184,3 +215,13 @@ impl PgRpc {
         }
     }
 }
+
+/// Verify the signatures and the precompile instructions of the transaction.
+fn verify_transaction(
+    transaction: &SanitizedTransaction,
+    feature_set: &FeatureSet,
+) -> transaction::Result<()> {
+    transaction.verify()?;
+    transaction.verify_precompiles(feature_set)?;
+    Ok(())
+}
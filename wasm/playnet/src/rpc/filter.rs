@@ -0,0 +1,149 @@
+// Account filters of `getProgramAccounts`, same semantics as `RpcFilterType` of the real RPC.
+
+use serde::Deserialize;
+use solana_sdk::{account::Account, pubkey, pubkey::Pubkey};
+
+/// SPL Token program id
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// SPL Token 2022 program id
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Maximum amount of filters per request
+pub const MAX_FILTERS: usize = 4;
+
+/// Maximum lengths of the memcmp bytes, decoded and encoded
+const MAX_DATA_SIZE: usize = 128;
+const MAX_DATA_BASE58_SIZE: usize = 175;
+const MAX_DATA_BASE64_SIZE: usize = 172;
+
+/// Token account layout
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+pub const TOKEN_ACCOUNT_AMOUNT_INDEX: usize = 64;
+pub const TOKEN_ACCOUNT_STATE_INDEX: usize = 108;
+pub const TOKEN_ACCOUNT_STATE_INITIALIZED: u8 = 1;
+const TOKEN_MULTISIG_LEN: usize = 355;
+const TOKEN_ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcFilterType {
+    DataSize(u64),
+    Memcmp(Memcmp),
+    TokenAccountState,
+}
+
+impl RpcFilterType {
+    /// Verify the filter, e.g. memcmp bytes are not too large.
+    pub fn verify(&self) -> Result<(), String> {
+        match self {
+            Self::Memcmp(memcmp) => {
+                let (encoded_len, max_encoded_len) = match &memcmp.bytes {
+                    MemcmpEncodedBytes::Base58(bytes) => (bytes.len(), MAX_DATA_BASE58_SIZE),
+                    MemcmpEncodedBytes::Base64(bytes) => (bytes.len(), MAX_DATA_BASE64_SIZE),
+                    MemcmpEncodedBytes::Bytes(bytes) => (bytes.len(), MAX_DATA_SIZE),
+                };
+                if encoded_len > max_encoded_len {
+                    return Err("encoded binary data should be less than 129 bytes".into());
+                }
+
+                match memcmp.bytes() {
+                    Some(bytes) if bytes.len() > MAX_DATA_SIZE => {
+                        Err("encoded binary data should be less than 129 bytes".into())
+                    }
+                    Some(_) => Ok(()),
+                    None => Err("memcmp bytes decode error".into()),
+                }
+            }
+            Self::DataSize(_) | Self::TokenAccountState => Ok(()),
+        }
+    }
+
+    /// Get whether the account passes the filter.
+    pub fn allows(&self, account: &Account) -> bool {
+        match self {
+            Self::DataSize(size) => account.data.len() as u64 == *size,
+            Self::Memcmp(memcmp) => memcmp.bytes_match(&account.data),
+            Self::TokenAccountState => is_valid_token_account_data(&account.data),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Memcmp {
+    /// Data offset to begin match
+    offset: usize,
+    /// Bytes, encoded with the specified encoding
+    #[serde(flatten)]
+    bytes: MemcmpEncodedBytes,
+}
+
+impl Memcmp {
+    /// Decode the bytes, `None` is returned for invalid encodings.
+    fn bytes(&self) -> Option<Vec<u8>> {
+        match &self.bytes {
+            MemcmpEncodedBytes::Base58(bytes) => bs58::decode(bytes).into_vec().ok(),
+            MemcmpEncodedBytes::Base64(bytes) => base64::decode(bytes).ok(),
+            MemcmpEncodedBytes::Bytes(bytes) => Some(bytes.to_owned()),
+        }
+    }
+
+    fn bytes_match(&self, data: &[u8]) -> bool {
+        match self.bytes() {
+            Some(bytes) => data
+                .get(self.offset..)
+                .and_then(|data| data.get(..bytes.len()))
+                .map(|data| data == bytes)
+                .unwrap_or_default(),
+            None => false,
+        }
+    }
+}
+
+/// Memcmp bytes, defaults to base58 if the encoding is not specified
+#[derive(Debug)]
+enum MemcmpEncodedBytes {
+    Base58(String),
+    Base64(String),
+    Bytes(Vec<u8>),
+}
+
+impl<'de> Deserialize<'de> for MemcmpEncodedBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Bytes {
+            String(String),
+            Bytes(Vec<u8>),
+        }
+
+        #[derive(Deserialize)]
+        struct EncodedBytes {
+            bytes: Bytes,
+            encoding: Option<String>,
+        }
+
+        let encoded_bytes = EncodedBytes::deserialize(deserializer)?;
+        match (encoded_bytes.bytes, encoded_bytes.encoding.as_deref()) {
+            (Bytes::String(bytes), None | Some("base58" | "binary")) => Ok(Self::Base58(bytes)),
+            (Bytes::String(bytes), Some("base64")) => Ok(Self::Base64(bytes)),
+            (Bytes::Bytes(bytes), None) => Ok(Self::Bytes(bytes)),
+            (_, Some(encoding)) => Err(serde::de::Error::custom(format!(
+                "unsupported memcmp encoding: {encoding}"
+            ))),
+        }
+    }
+}
+
+/// Get whether the data belongs to an initialized SPL Token or Token 2022 account.
+pub fn is_valid_token_account_data(data: &[u8]) -> bool {
+    let is_initialized = || data[TOKEN_ACCOUNT_STATE_INDEX] != 0;
+    match data.len() {
+        TOKEN_ACCOUNT_LEN => is_initialized(),
+        TOKEN_MULTISIG_LEN => false,
+        len if len > TOKEN_ACCOUNT_LEN => {
+            data[TOKEN_ACCOUNT_LEN] == TOKEN_ACCOUNT_TYPE_ACCOUNT && is_initialized()
+        }
+        _ => false,
+    }
+}
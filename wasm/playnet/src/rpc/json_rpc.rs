@@ -0,0 +1,1092 @@
+// Solana JSON-RPC 2.0 compatible front of the Playnet RPC.
+//
+// This allows existing Solana clients (e.g. web3.js `Connection`) to use Playnet without custom
+// adapters by passing the JSON request body to `handleRequest` and returning its result as the
+// response body.
+
+use std::str::FromStr;
+
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+use solana_sdk::{
+    account::Account,
+    clock::MAX_PROCESSING_AGE,
+    message::{SanitizedMessage, SanitizedVersionedMessage, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    slot_history::Slot,
+    transaction::{SanitizedTransaction, TransactionError, VersionedTransaction},
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    runtime::{
+        bank::{PgAddressLoader, PgBank},
+        transaction_history::TransactionData,
+    },
+    types::{SimulateTransactionResult, WasmEncoding},
+    utils::{deserialize_msg, deserialize_tx, get_sanitized_tx_from_versioned_tx},
+};
+
+use super::{
+    filter::{
+        is_valid_token_account_data, RpcFilterType, MAX_FILTERS, TOKEN_2022_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+    },
+    verify_transaction, PgRpc,
+};
+
+/// Version of the Solana RPC API that Playnet is compatible with
+const API_VERSION: &str = "1.15.0";
+
+/// Maximum amount of items in array params, e.g. `getSignatureStatuses`
+const MAX_ARRAY_PARAMS_LEN: usize = 256;
+
+/// Maximum amount of accounts in `getMultipleAccounts`
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Maximum slot range of `getBlocks` and the maximum limit of `getBlocksWithLimit`
+const MAX_BLOCKS_RANGE: u64 = 500_000;
+
+/// Maximum (and the default) amount of signatures in `getSignaturesForAddress`
+const MAX_SIGNATURES_FOR_ADDRESS: usize = 1000;
+
+#[wasm_bindgen]
+impl PgRpc {
+    /// Handle a Solana JSON-RPC 2.0 request (or a batch of requests) and return the response.
+    ///
+    /// An empty string is returned if the request only consists of notifications (requests
+    /// without an `id`), as they don't get a response.
+    #[wasm_bindgen(js_name = handleRequest)]
+    pub fn handle_request(&self, request: &str) -> String {
+        let request = match serde_json::from_str::<Value>(request) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(Value::Null, RpcError::parse_error(e.to_string()))
+                    .to_string()
+            }
+        };
+
+        let response = match request {
+            Value::Array(requests) if requests.is_empty() => Some(error_response(
+                Value::Null,
+                RpcError::invalid_request("Empty batch"),
+            )),
+            Value::Array(requests) => {
+                let responses = requests
+                    .into_iter()
+                    .filter_map(|request| self.handle_single_request(request))
+                    .collect::<Vec<_>>();
+                (!responses.is_empty()).then(|| Value::Array(responses))
+            }
+            request => self.handle_single_request(request),
+        };
+
+        response
+            .map(|response| response.to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl PgRpc {
+    /// Handle a single JSON-RPC request, `None` is returned for notifications.
+    fn handle_single_request(&self, request: Value) -> Option<Value> {
+        let Value::Object(mut request) = request else {
+            return Some(error_response(
+                Value::Null,
+                RpcError::invalid_request("Request must be an object"),
+            ));
+        };
+
+        let id = match request.remove("id") {
+            Some(id @ (Value::Null | Value::Number(_) | Value::String(_))) => id,
+            Some(_) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::invalid_request("Invalid id"),
+                ))
+            }
+            None => return None,
+        };
+        if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Some(error_response(
+                id,
+                RpcError::invalid_request("Invalid jsonrpc version"),
+            ));
+        }
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id,
+                RpcError::invalid_request("Invalid method"),
+            ));
+        };
+        let params = match request.get("params") {
+            None | Some(Value::Null) => vec![],
+            Some(Value::Array(params)) => params.to_owned(),
+            Some(_) => {
+                return Some(error_response(
+                    id,
+                    RpcError::invalid_params("Params must be an array"),
+                ))
+            }
+        };
+
+        let response = match self.dispatch(method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(id, err),
+        };
+        Some(response)
+    }
+
+    /// Call the method with the given params.
+    fn dispatch(&self, method: &str, params: &[Value]) -> RpcResult {
+        match method {
+            "getAccountInfo" => self.rpc_get_account_info(params),
+            "getBalance" => self.rpc_get_balance(params),
+            "getBlock" => self.rpc_get_block(params),
+            "getBlockHeight" => Ok(json!(self.get_bank().get_block_height())),
+            "getBlockTime" => self.rpc_get_block_time(params),
+            "getBlocks" => self.rpc_get_blocks(params),
+            "getBlocksWithLimit" => self.rpc_get_blocks_with_limit(params),
+            "getEpochInfo" => self.rpc_get_epoch_info(),
+            "getEpochSchedule" => Ok(json!(self.get_bank().get_epoch_schedule())),
+            "getFeeForMessage" => self.rpc_get_fee_for_message(params),
+            "getFirstAvailableBlock" => Ok(json!(self
+                .get_bank()
+                .get_blocks(0, Slot::MAX)
+                .first()
+                .map(|block| block.get_slot())
+                .unwrap_or_default())),
+            "getGenesisHash" => Ok(json!(self.get_bank().get_genesis_hash().to_string())),
+            "getHealth" => Ok(json!("ok")),
+            "getLatestBlockhash" => self.rpc_get_latest_blockhash(),
+            "getMultipleAccounts" => self.rpc_get_multiple_accounts(params),
+            "getMinimumBalanceForRentExemption" => {
+                let data_len = param::<usize>(params, 0)?;
+                Ok(json!(self
+                    .get_bank()
+                    .get_minimum_balance_for_rent_exemption(data_len)))
+            }
+            "getProgramAccounts" => self.rpc_get_program_accounts(params),
+            "getRecentBlockhash" => self.rpc_get_recent_blockhash(),
+            "getRecentPrioritizationFees" => Ok(json!([])),
+            "getSignatureStatuses" => self.rpc_get_signature_statuses(params),
+            "getSignaturesForAddress" => self.rpc_get_signatures_for_address(params),
+            "getSlot" => Ok(json!(self.get_bank().get_slot())),
+            "getTokenAccountsByOwner" => self.rpc_get_token_accounts_by_owner(params),
+            "getTransaction" => self.rpc_get_transaction(params),
+            "getVersion" => Ok(json!({ "solana-core": API_VERSION })),
+            "requestAirdrop" => self.rpc_request_airdrop(params),
+            "sendTransaction" => self.rpc_send_transaction(params),
+            "simulateTransaction" => self.rpc_simulate_transaction(params),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    fn rpc_get_account_info(&self, params: &[Value]) -> RpcResult {
+        let pubkey = pubkey_param(params, 0)?;
+        let config = optional_param::<AccountInfoConfig>(params, 1)?.unwrap_or_default();
+
+        let bank = self.get_bank();
+        let value = bank
+            .get_account(&pubkey)
+            .filter(|account| account.lamports != 0)
+            .map(|account| encode_account(account, &config))
+            .transpose()?;
+        Ok(with_context(&bank, value))
+    }
+
+    fn rpc_get_balance(&self, params: &[Value]) -> RpcResult {
+        let pubkey = pubkey_param(params, 0)?;
+        let bank = self.get_bank();
+        let lamports = bank.get_account_default(&pubkey).lamports;
+        Ok(with_context(&bank, lamports))
+    }
+
+    fn rpc_get_block(&self, params: &[Value]) -> RpcResult {
+        let slot = param::<Slot>(params, 0)?;
+        let config = optional_param::<BlockConfig>(params, 1)?.unwrap_or_default();
+
+        let bank = self.get_bank();
+        let block = bank
+            .get_block(slot)
+            .ok_or_else(|| RpcError::block_not_available(slot))?;
+        let mut result = json!({
+            "blockhash": block.get_blockhash().to_string(),
+            "previousBlockhash": block.get_previous_blockhash().to_string(),
+            "parentSlot": block.get_parent_slot(),
+            "blockHeight": block.get_block_height(),
+            "blockTime": block.get_block_time(),
+        });
+        if config.rewards.unwrap_or(true) {
+            result["rewards"] = json!([]);
+        }
+
+        let tx_datas = block
+            .get_signatures()
+            .iter()
+            .filter_map(|signature| bank.get_tx(signature));
+        match config
+            .transaction_details
+            .unwrap_or(TransactionDetails::Full)
+        {
+            TransactionDetails::Full => {
+                result["transactions"] = tx_datas
+                    .map(|tx_data| encode_transaction(tx_data, &config.transaction_config))
+                    .collect::<Result<Vec<_>, RpcError>>()?
+                    .into();
+            }
+            TransactionDetails::Signatures => {
+                result["signatures"] = block
+                    .get_signatures()
+                    .iter()
+                    .map(|signature| signature.to_string())
+                    .collect();
+            }
+            TransactionDetails::None => {}
+        }
+
+        Ok(result)
+    }
+
+    fn rpc_get_block_time(&self, params: &[Value]) -> RpcResult {
+        let slot = param::<Slot>(params, 0)?;
+        self.get_bank()
+            .get_block(slot)
+            .map(|block| json!(block.get_block_time()))
+            .ok_or_else(|| RpcError::block_not_available(slot))
+    }
+
+    fn rpc_get_blocks(&self, params: &[Value]) -> RpcResult {
+        let start_slot = param::<Slot>(params, 0)?;
+        let bank = self.get_bank();
+        let end_slot = optional_param::<Slot>(params, 1)?.unwrap_or_else(|| bank.get_slot());
+        if end_slot.saturating_sub(start_slot) > MAX_BLOCKS_RANGE {
+            return Err(RpcError::invalid_params(format!(
+                "Slot range too large; max {MAX_BLOCKS_RANGE}"
+            )));
+        }
+
+        let slots = bank
+            .get_blocks(start_slot, end_slot)
+            .iter()
+            .map(|block| block.get_slot())
+            .collect::<Vec<_>>();
+        Ok(json!(slots))
+    }
+
+    fn rpc_get_blocks_with_limit(&self, params: &[Value]) -> RpcResult {
+        let start_slot = param::<Slot>(params, 0)?;
+        let limit = param::<usize>(params, 1)?;
+        if limit as u64 > MAX_BLOCKS_RANGE {
+            return Err(RpcError::invalid_params(format!(
+                "Limit too large; max {MAX_BLOCKS_RANGE}"
+            )));
+        }
+
+        let slots = self
+            .get_bank()
+            .get_blocks(start_slot, Slot::MAX)
+            .iter()
+            .take(limit)
+            .map(|block| block.get_slot())
+            .collect::<Vec<_>>();
+        Ok(json!(slots))
+    }
+
+    fn rpc_get_epoch_info(&self) -> RpcResult {
+        let bank = self.get_bank();
+        let slot = bank.get_slot();
+        let epoch_schedule = bank.get_epoch_schedule();
+        let (epoch, slot_index) = epoch_schedule.get_epoch_and_slot_index(slot);
+        Ok(json!({
+            "absoluteSlot": slot,
+            "blockHeight": bank.get_block_height(),
+            "epoch": epoch,
+            "slotIndex": slot_index,
+            "slotsInEpoch": epoch_schedule.get_slots_in_epoch(epoch),
+            "transactionCount": bank.get_transaction_count(),
+        }))
+    }
+
+    fn rpc_get_fee_for_message(&self, params: &[Value]) -> RpcResult {
+        let msg = param::<String>(params, 0)?;
+        let msg = deserialize_msg(msg.as_bytes(), WasmEncoding::Base64)
+            .map_err(RpcError::invalid_params)?;
+        let msg = SanitizedVersionedMessage::try_from(msg)
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+
+        let bank = self.get_bank();
+        let msg = SanitizedMessage::try_new(msg, PgAddressLoader::new(&bank))
+            .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+        let fee = bank.get_fee_for_message(&msg);
+        Ok(with_context(&bank, fee))
+    }
+
+    fn rpc_get_multiple_accounts(&self, params: &[Value]) -> RpcResult {
+        let pubkeys = param::<Vec<String>>(params, 0)?;
+        if pubkeys.len() > MAX_MULTIPLE_ACCOUNTS {
+            return Err(RpcError::invalid_params(format!(
+                "Too many inputs provided; max {MAX_MULTIPLE_ACCOUNTS}"
+            )));
+        }
+        let config = optional_param::<AccountInfoConfig>(params, 1)?.unwrap_or_default();
+
+        let bank = self.get_bank();
+        let accounts = pubkeys
+            .iter()
+            .map(|pubkey| {
+                bank.get_account(&parse_pubkey(pubkey)?)
+                    .filter(|account| account.lamports != 0)
+                    .map(|account| encode_account(account, &config))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, RpcError>>()?;
+        Ok(with_context(&bank, accounts))
+    }
+
+    fn rpc_get_program_accounts(&self, params: &[Value]) -> RpcResult {
+        let program_id = pubkey_param(params, 0)?;
+        let config = optional_param::<ProgramAccountsConfig>(params, 1)?.unwrap_or_default();
+        let filters = config.filters.unwrap_or_default();
+        if filters.len() > MAX_FILTERS {
+            return Err(RpcError::invalid_params(format!(
+                "Too many filters provided; max {MAX_FILTERS}"
+            )));
+        }
+        for filter in &filters {
+            filter.verify().map_err(RpcError::invalid_params)?;
+        }
+
+        let bank = self.get_bank();
+        let accounts = bank
+            .get_program_accounts(&program_id)
+            .into_iter()
+            .filter(|(_, account)| filters.iter().all(|filter| filter.allows(account)))
+            .map(|(pubkey, account)| encode_keyed_account(pubkey, account, &config.account_config))
+            .collect::<Result<Vec<_>, RpcError>>()?;
+        if config.with_context {
+            Ok(with_context(&bank, accounts))
+        } else {
+            Ok(json!(accounts))
+        }
+    }
+
+    fn rpc_get_token_accounts_by_owner(&self, params: &[Value]) -> RpcResult {
+        let owner = pubkey_param(params, 0)?;
+        let (program_ids, mint) = match param::<TokenAccountsFilter>(params, 1)? {
+            TokenAccountsFilter::Mint(mint) => (
+                vec![TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID],
+                Some(parse_pubkey(&mint)?),
+            ),
+            TokenAccountsFilter::ProgramId(program_id) => {
+                let program_id = parse_pubkey(&program_id)?;
+                if program_id != TOKEN_PROGRAM_ID && program_id != TOKEN_2022_PROGRAM_ID {
+                    return Err(RpcError::invalid_params("unrecognized Token program id"));
+                }
+                (vec![program_id], None)
+            }
+        };
+        let config = optional_param::<AccountInfoConfig>(params, 2)?.unwrap_or_default();
+
+        // Token account layout: mint (32 bytes) + owner (32 bytes) + ...
+        let bank = self.get_bank();
+        let accounts = program_ids
+            .iter()
+            .flat_map(|program_id| bank.get_program_accounts(program_id))
+            .filter(|(_, account)| {
+                is_valid_token_account_data(&account.data)
+                    && account.data[32..64] == owner.to_bytes()
+                    && mint.map_or(true, |mint| account.data[..32] == mint.to_bytes())
+            })
+            .map(|(pubkey, account)| encode_keyed_account(pubkey, account, &config))
+            .collect::<Result<Vec<_>, RpcError>>()?;
+        Ok(with_context(&bank, accounts))
+    }
+
+    fn rpc_get_signatures_for_address(&self, params: &[Value]) -> RpcResult {
+        let address = pubkey_param(params, 0)?;
+        let config = optional_param::<SignaturesForAddressConfig>(params, 1)?.unwrap_or_default();
+        let limit = config.limit.unwrap_or(MAX_SIGNATURES_FOR_ADDRESS);
+        if limit == 0 || limit > MAX_SIGNATURES_FOR_ADDRESS {
+            return Err(RpcError::invalid_params(format!(
+                "Invalid limit; max {MAX_SIGNATURES_FOR_ADDRESS}"
+            )));
+        }
+        let before = config.before.as_deref().map(parse_signature).transpose()?;
+        let until = config.until.as_deref().map(parse_signature).transpose()?;
+
+        // Transactions are sorted newest first, start after `before` and stop at `until`
+        let bank = self.get_bank();
+        let txs = bank.get_txs_for_address(&address);
+        let start = before
+            .and_then(|before| txs.iter().position(|(signature, _)| **signature == before))
+            .map(|index| index + 1)
+            .unwrap_or_default();
+        let signatures = txs[start.min(txs.len())..]
+            .iter()
+            .take_while(|(signature, _)| Some(**signature) != until)
+            .take(limit)
+            .map(|(signature, tx_data)| {
+                let (_, confirmation_status) = confirmation_status(&bank, tx_data.get_slot());
+                json!({
+                    "signature": signature.to_string(),
+                    "slot": tx_data.get_slot(),
+                    "err": tx_data.get_meta().as_ref().and_then(|meta| meta.err.clone()),
+                    "memo": null,
+                    "blockTime": tx_data.get_block_time(),
+                    "confirmationStatus": confirmation_status,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(json!(signatures))
+    }
+
+    fn rpc_get_latest_blockhash(&self) -> RpcResult {
+        let bank = self.get_bank();
+        let value = json!({
+            "blockhash": bank.get_latest_blockhash().to_string(),
+            "lastValidBlockHeight": bank.get_block_height() + MAX_PROCESSING_AGE as u64,
+        });
+        Ok(with_context(&bank, value))
+    }
+
+    fn rpc_get_recent_blockhash(&self) -> RpcResult {
+        let bank = self.get_bank();
+        let value = json!({
+            "blockhash": bank.get_latest_blockhash().to_string(),
+            "feeCalculator": { "lamportsPerSignature": bank.get_lamports_per_signature() },
+        });
+        Ok(with_context(&bank, value))
+    }
+
+    fn rpc_get_signature_statuses(&self, params: &[Value]) -> RpcResult {
+        let signatures = param::<Vec<String>>(params, 0)?;
+        if signatures.len() > MAX_ARRAY_PARAMS_LEN {
+            return Err(RpcError::invalid_params(format!(
+                "Too many inputs provided; max {MAX_ARRAY_PARAMS_LEN}"
+            )));
+        }
+
+        let bank = self.get_bank();
+        let statuses = signatures
+            .iter()
+            .map(|signature| {
+                let signature = parse_signature(signature)?;
+                Ok(bank.get_tx(&signature).map(|tx_data| {
+                    let (confirmations, confirmation_status) =
+                        confirmation_status(&bank, tx_data.get_slot());
+                    let err = tx_data
+                        .get_meta()
+                        .as_ref()
+                        .and_then(|meta| meta.err.clone());
+
+                    json!({
+                        "slot": tx_data.get_slot(),
+                        "confirmations": confirmations,
+                        "err": err,
+                        "status": status(&err),
+                        "confirmationStatus": confirmation_status,
+                    })
+                }))
+            })
+            .collect::<Result<Vec<_>, RpcError>>()?;
+        Ok(with_context(&bank, statuses))
+    }
+
+    fn rpc_get_transaction(&self, params: &[Value]) -> RpcResult {
+        let signature = parse_signature(&param::<String>(params, 0)?)?;
+        let config = optional_param::<TransactionConfig>(params, 1)?.unwrap_or_default();
+
+        let bank = self.get_bank();
+        let Some(tx_data) = bank.get_tx(&signature) else {
+            return Ok(Value::Null);
+        };
+
+        let mut tx = encode_transaction(tx_data, &config)?;
+        tx["slot"] = json!(tx_data.get_slot());
+        tx["blockTime"] = json!(tx_data.get_block_time());
+        Ok(tx)
+    }
+
+    fn rpc_request_airdrop(&self, params: &[Value]) -> RpcResult {
+        let pubkey = pubkey_param(params, 0)?;
+        let lamports = param::<u64>(params, 1)?;
+        self.get_bank_mut()
+            .airdrop(&pubkey, lamports)
+            .map(|signature| json!(signature.to_string()))
+            .map_err(|e| RpcError::internal(format!("Airdrop failed: {e}")))
+    }
+
+    fn rpc_send_transaction(&self, params: &[Value]) -> RpcResult {
+        let config = optional_param::<SendTransactionConfig>(params, 1)?.unwrap_or_default();
+        let tx = decode_tx(&param::<String>(params, 0)?, config.encoding)?;
+
+        let mut bank = self.get_bank_mut();
+        let tx = sanitize_tx(tx, &bank)?;
+        verify_tx(&tx, &bank)?;
+
+        if !config.skip_preflight {
+            let result = bank.simulate_tx(&tx);
+            if result.result.is_err() {
+                return Err(RpcError::preflight_failure(&result, &bank));
+            }
+        }
+
+        // Transactions that skip the preflight return the signature even if they fail, the same
+        // as the real RPC. Transactions that can't be processed (e.g. insufficient funds for fee)
+        // are dropped without being saved.
+        let signature = *tx.signature();
+        match bank.commit_tx(tx) {
+            _ if config.skip_preflight => Ok(json!(signature.to_string())),
+            Ok((_, result)) if result.result.is_err() => {
+                Err(RpcError::preflight_failure(&result, &bank))
+            }
+            Ok(_) => Ok(json!(signature.to_string())),
+            Err(err) => Err(RpcError::preflight_failure(
+                &SimulateTransactionResult::new_error(err),
+                &bank,
+            )),
+        }
+    }
+
+    fn rpc_simulate_transaction(&self, params: &[Value]) -> RpcResult {
+        let config = optional_param::<SimulateTransactionConfig>(params, 1)?.unwrap_or_default();
+        let mut tx = decode_tx(&param::<String>(params, 0)?, config.encoding)?;
+
+        let bank = self.get_bank();
+        if config.replace_recent_blockhash {
+            if config.sig_verify {
+                return Err(RpcError::invalid_params(
+                    "sigVerify may not be used with replaceRecentBlockhash",
+                ));
+            }
+            tx.message.set_recent_blockhash(bank.get_latest_blockhash());
+        }
+
+        let tx = sanitize_tx(tx, &bank)?;
+        if config.sig_verify {
+            verify_tx(&tx, &bank)?;
+        }
+
+        let result = bank.simulate_tx(&tx);
+        let value = encode_simulation_result(&result, &bank, config.accounts.as_ref())?;
+        Ok(with_context(&bank, value))
+    }
+}
+
+/// Result type of the RPC methods
+type RpcResult = Result<Value, RpcError>;
+
+/// JSON-RPC error object
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i64 = -32700;
+    const INVALID_REQUEST: i64 = -32600;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+    const INTERNAL_ERROR: i64 = -32603;
+    const SEND_TRANSACTION_PREFLIGHT_FAILURE: i64 = -32002;
+    const TRANSACTION_SIGNATURE_VERIFICATION_FAILURE: i64 = -32003;
+    const BLOCK_NOT_AVAILABLE: i64 = -32004;
+    const TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE: i64 = -32006;
+    const UNSUPPORTED_TRANSACTION_VERSION: i64 = -32015;
+
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    fn parse_error(message: impl ToString) -> Self {
+        Self::new(
+            Self::PARSE_ERROR,
+            format!("Parse error: {}", message.to_string()),
+        )
+    }
+
+    fn invalid_request(message: impl ToString) -> Self {
+        Self::new(
+            Self::INVALID_REQUEST,
+            format!("Invalid request: {}", message.to_string()),
+        )
+    }
+
+    fn method_not_found(method: &str) -> Self {
+        Self::new(
+            Self::METHOD_NOT_FOUND,
+            format!("Method not found: {method}"),
+        )
+    }
+
+    fn invalid_params(message: impl ToString) -> Self {
+        Self::new(
+            Self::INVALID_PARAMS,
+            format!("Invalid params: {}", message.to_string()),
+        )
+    }
+
+    fn internal(message: impl ToString) -> Self {
+        Self::new(Self::INTERNAL_ERROR, message)
+    }
+
+    fn block_not_available(slot: Slot) -> Self {
+        Self::new(
+            Self::BLOCK_NOT_AVAILABLE,
+            format!("Block not available for slot {slot}"),
+        )
+    }
+
+    fn signature_verification_failure() -> Self {
+        Self::new(
+            Self::TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+            "Transaction signature verification failure",
+        )
+    }
+
+    fn precompile_verification_failure(err: TransactionError) -> Self {
+        Self::new(
+            Self::TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE,
+            format!("Transaction precompile verification failure {err:?}"),
+        )
+    }
+
+    /// Create the error of a failed transaction, `result` must be an error.
+    fn preflight_failure(result: &SimulateTransactionResult, bank: &PgBank) -> Self {
+        Self {
+            code: Self::SEND_TRANSACTION_PREFLIGHT_FAILURE,
+            message: format!(
+                "Transaction simulation failed: {}",
+                result.result.as_ref().unwrap_err()
+            ),
+            // Encoding can only fail when encoding the accounts
+            data: encode_simulation_result(result, bank, None).ok(),
+        }
+    }
+}
+
+/// Create a JSON-RPC error response.
+fn error_response(id: Value, err: RpcError) -> Value {
+    let mut error = Map::new();
+    error.insert("code".into(), json!(err.code));
+    error.insert("message".into(), json!(err.message));
+    if let Some(data) = err.data {
+        error.insert("data".into(), data);
+    }
+
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// Wrap the value with the RPC response context.
+fn with_context(bank: &PgBank, value: impl serde::Serialize) -> Value {
+    json!({
+        "context": { "slot": bank.get_slot(), "apiVersion": API_VERSION },
+        "value": value,
+    })
+}
+
+/// Get the required param at the given index.
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    optional_param(params, index)?.ok_or_else(|| {
+        RpcError::invalid_params(format!("missing required parameter at index {index}"))
+    })
+}
+
+/// Get the optional param at the given index, `null` params are treated as missing.
+fn optional_param<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+) -> Result<Option<T>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(param) => serde_json::from_value(param.to_owned())
+            .map(Some)
+            .map_err(|e| RpcError::invalid_params(format!("parameter at index {index}: {e}"))),
+    }
+}
+
+/// Get the required base58 public key param at the given index.
+fn pubkey_param(params: &[Value], index: usize) -> Result<Pubkey, RpcError> {
+    parse_pubkey(&param::<String>(params, index)?)
+}
+
+/// Parse a base58 public key.
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, RpcError> {
+    Pubkey::from_str(pubkey).map_err(|e| RpcError::invalid_params(format!("{pubkey}: {e}")))
+}
+
+/// Parse a base58 signature.
+fn parse_signature(signature: &str) -> Result<Signature, RpcError> {
+    Signature::from_str(signature)
+        .map_err(|e| RpcError::invalid_params(format!("{signature}: {e}")))
+}
+
+/// Encoding of the binary data in requests and responses
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum UiEncoding {
+    /// Legacy base58 encoding
+    Binary,
+    Base58,
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    Json,
+    JsonParsed,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+struct DataSlice {
+    offset: usize,
+    length: usize,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct AccountInfoConfig {
+    encoding: Option<UiEncoding>,
+    data_slice: Option<DataSlice>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ProgramAccountsConfig {
+    #[serde(flatten)]
+    account_config: AccountInfoConfig,
+    filters: Option<Vec<RpcFilterType>>,
+    with_context: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TokenAccountsFilter {
+    Mint(String),
+    ProgramId(String),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SignaturesForAddressConfig {
+    limit: Option<usize>,
+    before: Option<String>,
+    until: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SimulateAccountsConfig {
+    encoding: Option<UiEncoding>,
+    addresses: Vec<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SendTransactionConfig {
+    encoding: Option<UiEncoding>,
+    skip_preflight: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SimulateTransactionConfig {
+    encoding: Option<UiEncoding>,
+    sig_verify: bool,
+    replace_recent_blockhash: bool,
+    accounts: Option<SimulateAccountsConfig>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TransactionDetails {
+    Full,
+    Signatures,
+    None,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BlockConfig {
+    #[serde(flatten)]
+    transaction_config: TransactionConfig,
+    transaction_details: Option<TransactionDetails>,
+    rewards: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TransactionConfig {
+    encoding: Option<UiEncoding>,
+    max_supported_transaction_version: Option<u8>,
+}
+
+/// Encode the account in the requested encoding (legacy base58 by default).
+///
+/// `jsonParsed` falls back to base64 since Playnet doesn't have account parsers.
+fn encode_account(account: &Account, config: &AccountInfoConfig) -> RpcResult {
+    let data = match config.data_slice {
+        Some(DataSlice { offset, length }) => {
+            let start = offset.min(account.data.len());
+            let end = offset.saturating_add(length).min(account.data.len());
+            &account.data[start..end]
+        }
+        None => &account.data[..],
+    };
+    let data = match config.encoding.unwrap_or(UiEncoding::Binary) {
+        UiEncoding::Binary | UiEncoding::Base58 if data.len() > 128 => {
+            return Err(RpcError::invalid_params(
+                "Encoded binary (base 58) data should be less than 128 bytes, please use Base64 \
+                 encoding.",
+            ))
+        }
+        UiEncoding::Binary => json!(bs58::encode(data).into_string()),
+        UiEncoding::Base58 => json!([bs58::encode(data).into_string(), "base58"]),
+        UiEncoding::Base64 | UiEncoding::JsonParsed => json!([base64::encode(data), "base64"]),
+        UiEncoding::Base64Zstd | UiEncoding::Json => {
+            return Err(RpcError::invalid_params("Unsupported account encoding"))
+        }
+    };
+
+    Ok(json!({
+        "lamports": account.lamports,
+        "data": data,
+        "owner": account.owner.to_string(),
+        "executable": account.executable,
+        "rentEpoch": account.rent_epoch,
+        "space": account.data.len(),
+    }))
+}
+
+/// Encode the account with its public key, e.g. for `getProgramAccounts`.
+fn encode_keyed_account(
+    pubkey: &Pubkey,
+    account: &Account,
+    config: &AccountInfoConfig,
+) -> RpcResult {
+    Ok(json!({
+        "pubkey": pubkey.to_string(),
+        "account": encode_account(account, config)?,
+    }))
+}
+
+/// Get the confirmations and the confirmation status of the transaction at the given slot.
+fn confirmation_status(bank: &PgBank, slot: u64) -> (Option<u64>, &'static str) {
+    let confirmations = bank.get_slot().saturating_sub(slot);
+    if confirmations == 0 {
+        (Some(confirmations), "processed")
+    } else if confirmations < 32 {
+        (Some(confirmations), "confirmed")
+    } else {
+        (None, "finalized")
+    }
+}
+
+/// Decode the transaction param in the requested encoding (base58 by default).
+fn decode_tx(
+    encoded_tx: &str,
+    encoding: Option<UiEncoding>,
+) -> Result<VersionedTransaction, RpcError> {
+    let result = match encoding.unwrap_or(UiEncoding::Base58) {
+        UiEncoding::Base58 | UiEncoding::Binary => bs58::decode(encoded_tx)
+            .into_vec()
+            .map_err(|e| format!("invalid base58: {e}"))
+            .and_then(|tx| deserialize_tx(&tx, WasmEncoding::Binary)),
+        UiEncoding::Base64 => deserialize_tx(encoded_tx.as_bytes(), WasmEncoding::Base64),
+        _ => Err("unsupported transaction encoding".into()),
+    };
+    result.map_err(RpcError::invalid_params)
+}
+
+/// Sanitize the transaction with the lookup tables of the bank.
+fn sanitize_tx(tx: VersionedTransaction, bank: &PgBank) -> Result<SanitizedTransaction, RpcError> {
+    get_sanitized_tx_from_versioned_tx(tx, PgAddressLoader::new(bank))
+        .map_err(|e| RpcError::invalid_params(format!("invalid transaction: {e}")))
+}
+
+/// Verify the transaction with the same errors as the real RPC.
+fn verify_tx(tx: &SanitizedTransaction, bank: &PgBank) -> Result<(), RpcError> {
+    verify_transaction(tx, bank.feature_set()).map_err(|e| match e {
+        TransactionError::SignatureFailure => RpcError::signature_verification_failure(),
+        e => RpcError::precompile_verification_failure(e),
+    })
+}
+
+/// Encode the simulation result as the `value` of `simulateTransaction`.
+fn encode_simulation_result(
+    result: &SimulateTransactionResult,
+    bank: &PgBank,
+    accounts_config: Option<&SimulateAccountsConfig>,
+) -> RpcResult {
+    let accounts = accounts_config
+        .map(|config| {
+            let account_config = AccountInfoConfig {
+                encoding: Some(config.encoding.unwrap_or(UiEncoding::Base64)),
+                data_slice: None,
+            };
+            config
+                .addresses
+                .iter()
+                .map(|address| {
+                    let pubkey = parse_pubkey(address)?;
+                    let account = match result.post_accounts.iter().find(|(key, _)| *key == pubkey)
+                    {
+                        Some((_, account)) => Some(Account::from(account.to_owned())),
+                        None => bank.get_account(&pubkey).cloned(),
+                    };
+                    account
+                        .filter(|account| account.lamports != 0)
+                        .map(|account| encode_account(&account, &account_config))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, RpcError>>()
+        })
+        .transpose()?;
+
+    Ok(json!({
+        "err": result.result.as_ref().err(),
+        "logs": result.logs,
+        "accounts": accounts,
+        "unitsConsumed": result.units_consumed,
+        "returnData": result.return_data.as_ref().map(|return_data| json!({
+            "programId": return_data.program_id.to_string(),
+            "data": [base64::encode(&return_data.data), "base64"],
+        })),
+    }))
+}
+
+/// Encode the transaction with its metadata, e.g. for `getTransaction`.
+///
+/// `jsonParsed` falls back to `json` since Playnet doesn't have instruction parsers.
+fn encode_transaction(tx_data: &TransactionData, config: &TransactionConfig) -> RpcResult {
+    let tx = tx_data.get_tx();
+    let version = match &tx.message {
+        VersionedMessage::Legacy(_) => None,
+        VersionedMessage::V0(_) => Some(0),
+    };
+    match (version, config.max_supported_transaction_version) {
+        (Some(version), None) => {
+            return Err(RpcError::new(
+                RpcError::UNSUPPORTED_TRANSACTION_VERSION,
+                format!(
+                    "Transaction version ({version}) is not supported by the requesting client. \
+                     Please try the request again with the following configuration parameter: \
+                     \"maxSupportedTransactionVersion\": {version}"
+                ),
+            ))
+        }
+        (Some(version), Some(max_version)) if version > max_version => {
+            return Err(RpcError::new(
+                RpcError::UNSUPPORTED_TRANSACTION_VERSION,
+                format!("Transaction version ({version}) is not supported"),
+            ))
+        }
+        _ => {}
+    }
+
+    let transaction = match config.encoding.unwrap_or(UiEncoding::Json) {
+        UiEncoding::Json | UiEncoding::JsonParsed => json!({
+            "signatures": tx.signatures.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            "message": encode_message(&tx.message),
+        }),
+        UiEncoding::Base64 => json!([base64::encode(serialize_tx(tx)?), "base64"]),
+        UiEncoding::Base58 => json!([bs58::encode(serialize_tx(tx)?).into_string(), "base58"]),
+        UiEncoding::Binary => json!(bs58::encode(serialize_tx(tx)?).into_string()),
+        UiEncoding::Base64Zstd => {
+            return Err(RpcError::invalid_params("Unsupported transaction encoding"))
+        }
+    };
+
+    let meta = tx_data.get_meta().as_ref().map(|meta| {
+        let loaded_addresses = meta.loaded_addresses.clone().unwrap_or_default();
+        let to_strings = |keys: &[Pubkey]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        json!({
+            "err": meta.err,
+            "status": status(&meta.err),
+            "fee": meta.fee,
+            "preBalances": meta.pre_balances,
+            "postBalances": meta.post_balances,
+            "innerInstructions": [],
+            "logMessages": meta.log_messages,
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": [],
+            "loadedAddresses": {
+                "writable": to_strings(&loaded_addresses.writable),
+                "readonly": to_strings(&loaded_addresses.readonly),
+            },
+            "computeUnitsConsumed": meta.compute_units_consumed,
+        })
+    });
+
+    let mut result = json!({ "transaction": transaction, "meta": meta });
+    if config.max_supported_transaction_version.is_some() {
+        result["version"] = match version {
+            Some(version) => json!(version),
+            None => json!("legacy"),
+        };
+    }
+
+    Ok(result)
+}
+
+/// Encode the message in the JSON format of the real RPC.
+fn encode_message(msg: &VersionedMessage) -> Value {
+    let header = msg.header();
+    let mut encoded_msg = json!({
+        "header": {
+            "numRequiredSignatures": header.num_required_signatures,
+            "numReadonlySignedAccounts": header.num_readonly_signed_accounts,
+            "numReadonlyUnsignedAccounts": header.num_readonly_unsigned_accounts,
+        },
+        "accountKeys": msg
+            .static_account_keys()
+            .iter()
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>(),
+        "recentBlockhash": msg.recent_blockhash().to_string(),
+        "instructions": msg
+            .instructions()
+            .iter()
+            .map(|ix| json!({
+                "programIdIndex": ix.program_id_index,
+                "accounts": ix.accounts,
+                "data": bs58::encode(&ix.data).into_string(),
+                "stackHeight": null,
+            }))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(lookups) = msg.address_table_lookups() {
+        encoded_msg["addressTableLookups"] = lookups
+            .iter()
+            .map(|lookup| {
+                json!({
+                    "accountKey": lookup.account_key.to_string(),
+                    "writableIndexes": lookup.writable_indexes,
+                    "readonlyIndexes": lookup.readonly_indexes,
+                })
+            })
+            .collect();
+    }
+
+    encoded_msg
+}
+
+/// Serialize the transaction in wire format.
+fn serialize_tx(tx: &VersionedTransaction) -> Result<Vec<u8>, RpcError> {
+    bincode::serialize(tx).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+/// Get the deprecated `status` field of the transaction status.
+fn status(err: &Option<TransactionError>) -> Value {
+    match err {
+        Some(err) => json!({ "Err": err }),
+        None => json!({ "Ok": null }),
+    }
+}
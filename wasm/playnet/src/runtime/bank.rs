@@ -1,24 +1,35 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    mem,
     num::NonZeroUsize,
     rc::Rc,
     sync::{Arc, RwLock},
 };
 
 use serde::{Deserialize, Serialize};
+use solana_address_lookup_table_program::{
+    error::AddressLookupError, processor::process_instruction as process_lookup_table_instruction,
+    state::AddressLookupTable,
+};
 use solana_bpf_loader_program::process_instruction as process_bpf_loader_instruction;
 use solana_program_runtime::{
     compute_budget::ComputeBudget, executor_cache::TransactionExecutorCache,
-    invoke_context::BuiltinProgram, log_collector::LogCollector, sysvar_cache::SysvarCache,
+    invoke_context::BuiltinProgram, log_collector::LogCollector,
+    prioritization_fee::PrioritizationFeeDetails, sysvar_cache::SysvarCache,
     timings::ExecuteTimings,
 };
 use solana_sdk::{
-    account::{to_account, Account, AccountSharedData, ReadableAccount, WritableAccount},
+    account::{
+        from_account, to_account, Account, AccountSharedData, ReadableAccount, WritableAccount,
+    },
     account_utils::StateMut,
     bpf_loader,
     bpf_loader_upgradeable::{self, UpgradeableLoaderState},
-    clock::Clock,
+    clock::{Clock, UnixTimestamp},
+    compute_budget, ed25519_program,
+    epoch_schedule::{EpochSchedule, MINIMUM_SLOTS_PER_EPOCH},
     feature_set::{self, FeatureSet},
     hash::Hash,
     instruction::CompiledInstruction,
@@ -28,11 +39,15 @@ use solana_sdk::{
     },
     native_loader,
     native_token::LAMPORTS_PER_SOL,
+    nonce,
     pubkey::Pubkey,
     rent::Rent,
+    secp256k1_program,
     signature::{Keypair, Signature},
     signer::Signer,
+    slot_hashes::SlotHashes,
     slot_history::Slot,
+    stake_history::{StakeHistory, StakeHistoryEntry, MAX_ENTRIES as MAX_STAKE_HISTORY_ENTRIES},
     system_instruction, system_program,
     sysvar::{self, instructions::construct_instructions_data, Sysvar},
     transaction::{
@@ -47,14 +62,17 @@ use solana_sdk::{
 use crate::{
     serde::{bank_accounts, bank_keypair},
     types::SimulateTransactionResult,
-    utils::{create_blockhash, get_sanitized_tx_from_versioned_tx},
+    utils::{create_blockhash, get_current_timestamp_ms, get_sanitized_tx_from_versioned_tx},
 };
 
 use super::{
+    block_history::BlockData,
+    compute_budget_processor::process_compute_budget_instruction,
     message_processor::MessageProcessor,
     system_instruction_processor::{
         get_system_account_kind, process_system_instruction, SystemAccountKind,
     },
+    sysvars::{EpochRewards, LastRestartSlot},
     transaction_history::{ConfirmedTransactionMeta, TransactionData},
 };
 
@@ -74,7 +92,23 @@ pub struct PgBank {
     #[serde(skip)]
     txs: BankTxs,
 
-    /// Bank's slot (i.e. block)
+    /// Sealed blocks, not saved for the same reason as `txs`
+    #[serde(skip)]
+    blocks: BankBlocks,
+
+    /// Signatures of the transactions in the current block that is not sealed yet
+    #[serde(skip)]
+    current_block_signatures: Vec<Signature>,
+
+    /// The unix timestamp in milliseconds of when the current block started
+    #[serde(skip, default = "get_current_timestamp_ms")]
+    current_block_start_ms: u64,
+
+    /// Interval of sealing blocks in milliseconds, blocks are only sealed on demand if `None`
+    #[serde(skip, default = "PgBank::default_block_interval_ms")]
+    block_interval_ms: Option<u64>,
+
+    /// Bank's slot (i.e. the slot of the current block)
     slot: Slot,
 
     /// Bank's block height
@@ -86,6 +120,26 @@ pub struct PgBank {
     /// Bank's latest blockhash
     latest_blockhash: Hash,
 
+    /// Fee charged per transaction signature
+    #[serde(default = "PgBank::default_lamports_per_signature")]
+    lamports_per_signature: u64,
+
+    /// Epoch schedule of the bank, epochs don't have warmup by default
+    #[serde(default = "EpochSchedule::without_warmup")]
+    epoch_schedule: EpochSchedule,
+
+    /// Unix timestamp of the clock in milliseconds
+    #[serde(default = "get_current_timestamp_ms")]
+    clock_timestamp_ms: u64,
+
+    /// Milliseconds the clock advances per slot, the clock follows the real time if `None`
+    #[serde(default)]
+    clock_ms_per_slot: Option<u64>,
+
+    /// Offset of the clock from the real time in milliseconds, changes when the clock is warped
+    #[serde(default)]
+    clock_offset_ms: i64,
+
     /// The keypair that signs airdrop transactions
     #[serde(with = "bank_keypair")]
     airdrop_kp: Keypair,
@@ -109,10 +163,19 @@ impl Default for PgBank {
         Self {
             accounts: HashMap::new(),
             txs: HashMap::new(),
+            blocks: BTreeMap::new(),
+            current_block_signatures: vec![],
+            current_block_start_ms: get_current_timestamp_ms(),
+            block_interval_ms: PgBank::default_block_interval_ms(),
             slot: 0,
             block_height: 0,
             genesis_hash,
             latest_blockhash: genesis_hash,
+            lamports_per_signature: PgBank::DEFAULT_LAMPORTS_PER_SIGNATURE,
+            epoch_schedule: EpochSchedule::without_warmup(),
+            clock_timestamp_ms: get_current_timestamp_ms(),
+            clock_ms_per_slot: None,
+            clock_offset_ms: 0,
             airdrop_kp: Keypair::new(),
             builtin_programs: vec![],
             sysvar_cache: RwLock::new(SysvarCache::default()),
@@ -122,14 +185,26 @@ impl Default for PgBank {
 }
 
 impl PgBank {
-    const LAMPORTS_PER_SIGNATURE: u64 = 0;
+    /// Same as the default fee of the real clusters
+    const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+    /// Same as the target slot time of the real clusters
+    const DEFAULT_BLOCK_INTERVAL_MS: u64 = 400;
 
-    pub fn new(maybe_bank_string: Option<String>) -> Self {
+    pub fn new(
+        maybe_bank_string: Option<String>,
+        maybe_lamports_per_signature: Option<u64>,
+    ) -> Self {
         let mut bank = match maybe_bank_string {
             Some(bank_string) => serde_json::from_str::<Self>(&bank_string).unwrap_or_default(),
             None => Self::default(),
         };
 
+        // Override the saved fee if a fee is specified
+        if let Some(lamports_per_signature) = maybe_lamports_per_signature {
+            bank.lamports_per_signature = lamports_per_signature;
+        }
+
         // Add native accounts
         let mut add_native_programs = |program_id: Pubkey| {
             let mut account = Account::new(1, 0, &native_loader::id());
@@ -140,26 +215,50 @@ impl PgBank {
         add_native_programs(bpf_loader::id());
         add_native_programs(bpf_loader_upgradeable::id());
         add_native_programs(system_program::id());
+        add_native_programs(compute_budget::id());
+        add_native_programs(solana_address_lookup_table_program::id());
 
-        // Add sysvar accounts
-        fn add_sysvar_account<S: Sysvar>(bank: &mut PgBank) -> S {
-            let default = S::default();
-            let mut account = Account::new(
-                1,
-                bincode::serialized_size(&default).unwrap() as usize,
-                &sysvar::id(),
-            );
-            to_account(&default, &mut account).unwrap();
+        // Add sysvar accounts, the saved state of the sysvars is kept
+        fn get_saved_sysvar<S: Sysvar>(bank: &PgBank) -> Option<S> {
+            bank.accounts.get(&S::id()).and_then(from_account)
+        }
+        fn add_sysvar_account<S: Sysvar>(bank: &mut PgBank, sysvar: S) -> S {
+            let mut account = Account::new(1, S::size_of(), &sysvar::id());
+            to_account(&sysvar, &mut account).unwrap();
             bank.accounts.insert(S::id(), account);
 
-            default
+            sysvar
         }
 
-        let clock = add_sysvar_account::<Clock>(&mut bank);
-        let rent = add_sysvar_account::<Rent>(&mut bank);
+        let unix_timestamp = bank.get_clock_unix_timestamp();
+        let epoch = bank.epoch_schedule.get_epoch(bank.slot);
+        let epoch_start_timestamp = get_saved_sysvar::<Clock>(&bank)
+            .filter(|clock| clock.epoch == epoch)
+            .map(|clock| clock.epoch_start_timestamp)
+            .unwrap_or(unix_timestamp);
+        let clock = Clock {
+            slot: bank.slot,
+            epoch_start_timestamp,
+            epoch,
+            leader_schedule_epoch: bank.epoch_schedule.get_leader_schedule_epoch(bank.slot),
+            unix_timestamp,
+        };
+        let clock = add_sysvar_account(&mut bank, clock);
+        let epoch_schedule = bank.epoch_schedule;
+        let epoch_schedule = add_sysvar_account(&mut bank, epoch_schedule);
+        let rent = add_sysvar_account(&mut bank, Rent::default());
+        let slot_hashes = get_saved_sysvar::<SlotHashes>(&bank).unwrap_or_default();
+        let slot_hashes = add_sysvar_account(&mut bank, slot_hashes);
+        let stake_history = get_saved_sysvar::<StakeHistory>(&bank).unwrap_or_default();
+        let stake_history = add_sysvar_account(&mut bank, stake_history);
+        add_sysvar_account(&mut bank, EpochRewards::default());
+        add_sysvar_account(&mut bank, LastRestartSlot::default());
         let mut sysvar_cache = bank.sysvar_cache.write().unwrap();
         sysvar_cache.set_clock(clock);
+        sysvar_cache.set_epoch_schedule(epoch_schedule);
         sysvar_cache.set_rent(rent);
+        sysvar_cache.set_slot_hashes(slot_hashes);
+        sysvar_cache.set_stake_history(stake_history);
         drop(sysvar_cache);
 
         // Add airdrop account
@@ -186,6 +285,14 @@ impl PgBank {
                 program_id: system_program::id(),
                 process_instruction: process_system_instruction,
             },
+            BuiltinProgram {
+                program_id: compute_budget::id(),
+                process_instruction: process_compute_budget_instruction,
+            },
+            BuiltinProgram {
+                program_id: solana_address_lookup_table_program::id(),
+                process_instruction: process_lookup_table_instruction,
+            },
         ];
 
         // Feature set
@@ -194,6 +301,14 @@ impl PgBank {
         bank
     }
 
+    fn default_lamports_per_signature() -> u64 {
+        Self::DEFAULT_LAMPORTS_PER_SIGNATURE
+    }
+
+    fn default_block_interval_ms() -> Option<u64> {
+        Some(Self::DEFAULT_BLOCK_INTERVAL_MS)
+    }
+
     pub fn get_slot(&self) -> Slot {
         self.slot
     }
@@ -203,7 +318,7 @@ impl PgBank {
     }
 
     pub fn get_genesis_hash(&self) -> Hash {
-        self.latest_blockhash
+        self.genesis_hash
     }
 
     pub fn get_latest_blockhash(&self) -> Hash {
@@ -214,6 +329,10 @@ impl PgBank {
         Rent::default().minimum_balance(data_len).max(1)
     }
 
+    pub fn get_lamports_per_signature(&self) -> u64 {
+        self.lamports_per_signature
+    }
+
     pub fn feature_set(&self) -> &FeatureSet {
         &*self.feature_set
     }
@@ -231,15 +350,58 @@ impl PgBank {
         }
     }
 
+    /// Returns the accounts that are owned by the given program
+    pub fn get_program_accounts(&self, program_id: &Pubkey) -> Vec<(&Pubkey, &Account)> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *program_id && account.lamports != 0)
+            .collect()
+    }
+
     /// Inserts the account if it doesn't exist or updates the existing account.
     /// Previous value or `None` is returned for initial insertion.
     pub fn set_account(&mut self, pubkey: Pubkey, account: Account) -> Option<Account> {
         self.accounts.insert(pubkey, account)
     }
 
+    /// Adds the program as an executable account owned by the BPF loader, without deploying it
+    /// with transactions.
+    pub fn add_program(&mut self, program_id: Pubkey, program_bytes: Vec<u8>) {
+        let account = Account {
+            lamports: self.get_minimum_balance_for_rent_exemption(program_bytes.len()),
+            data: program_bytes,
+            owner: bpf_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        };
+        self.set_account(program_id, account);
+    }
+
+    /// Get the total fee of the message, i.e. signature fee + prioritization fee.
+    ///
+    /// Signatures of the precompile instructions (ed25519 and secp256k1) are also charged, the
+    /// same as the real runtime.
     pub fn get_fee_for_message(&self, msg: &SanitizedMessage) -> Option<u64> {
-        (msg.header().num_required_signatures.max(1) as u64)
-            .checked_mul(PgBank::LAMPORTS_PER_SIGNATURE)
+        let num_signatures = msg.program_instructions_iter().fold(
+            msg.header().num_required_signatures as u64,
+            |acc, (program_id, ix)| {
+                if secp256k1_program::check_id(program_id) || ed25519_program::check_id(program_id)
+                {
+                    acc.saturating_add(ix.data.first().copied().unwrap_or_default() as u64)
+                } else {
+                    acc
+                }
+            },
+        );
+        let signature_fee = num_signatures.checked_mul(self.lamports_per_signature)?;
+
+        // Invalid compute budget instructions fail the transaction during loading
+        let prioritization_fee =
+            Self::process_compute_budget_instructions(msg, &mut ComputeBudget::default())
+                .map(|details| details.get_fee())
+                .unwrap_or_default();
+
+        signature_fee.checked_add(prioritization_fee)
     }
 
     pub fn simulate_tx(&self, tx: &SanitizedTransaction) -> SimulateTransactionResult {
@@ -256,76 +418,455 @@ impl PgBank {
             .take(account_count)
             .collect::<Vec<TransactionAccount>>();
 
+        // The fee is charged before the execution, meaning programs see the balance of the fee
+        // payer after the fee deduction
+        Self::charge_fee(&mut loaded_tx.accounts, loaded_tx.fee);
+
         match self.execute_loaded_tx(&tx, &mut loaded_tx) {
             TransactionExecutionResult::Executed {
                 details,
                 tx_executor_cache: _,
-            } => SimulateTransactionResult::new(
-                details.status,
-                pre_accounts,
-                loaded_tx.accounts.into_iter().take(account_count).collect(),
-                details.log_messages.unwrap_or_default(),
-                details.executed_units,
-                details.return_data,
-            ),
+            } => {
+                let post_accounts = if details.status.is_ok() {
+                    loaded_tx.accounts.into_iter().take(account_count).collect()
+                } else {
+                    // Failed transactions only pay the fee
+                    let mut post_accounts = pre_accounts.clone();
+                    Self::charge_fee(&mut post_accounts, loaded_tx.fee);
+                    post_accounts
+                };
+
+                SimulateTransactionResult::new(
+                    details.status,
+                    pre_accounts,
+                    post_accounts,
+                    details.log_messages.unwrap_or_default(),
+                    details.executed_units,
+                    details.return_data,
+                )
+            }
             TransactionExecutionResult::NotExecuted(err) => {
                 SimulateTransactionResult::new_error(err)
             }
         }
     }
 
+    /// Process the transaction and commit its changes to the bank.
+    ///
+    /// Transactions that fail during the execution are still saved and charged the fee, the same
+    /// as the real runtime. The execution error is returned after the fee is charged.
     pub fn process_tx(&mut self, tx: SanitizedTransaction) -> transaction::Result<Signature> {
+        let (tx_hash, result) = self.commit_tx(tx)?;
+        result.result.map(|_| tx_hash)
+    }
+
+    /// Process the transaction and commit its changes to the bank.
+    ///
+    /// Unlike [`PgBank::process_tx`], the errors that prevent the transaction from being
+    /// processed (e.g. insufficient funds for fee) are separate from the execution result (and the
+    /// logs) of the processed transaction.
+    pub fn commit_tx(
+        &mut self,
+        tx: SanitizedTransaction,
+    ) -> transaction::Result<(Signature, SimulateTransactionResult)> {
+        // Transactions go to the next block if the current block is due
+        self.tick();
+
+        // Check before writing the accounts in order to not execute the same transaction twice
+        if self.txs.contains_key(tx.signature()) {
+            return Err(TransactionError::AlreadyProcessed);
+        }
+
         let simulation_result = self.simulate_tx(&tx);
-        match simulation_result.result {
-            Ok(_) => {
-                // TODO: Substract the fee from the `fee_payer`
-                let fee = self.get_fee_for_message(tx.message()).unwrap();
 
-                for (pubkey, account) in &simulation_result.post_accounts {
-                    self.set_account(pubkey.clone(), account.clone().into());
-                }
+        // Transactions that couldn't be loaded (e.g. insufficient funds for fee) are not charged
+        if simulation_result.post_accounts.is_empty() {
+            return Err(simulation_result.result.unwrap_err());
+        }
 
-                let tx_hash = self.save_tx(tx, simulation_result, fee)?;
-                Ok(tx_hash)
-            }
-            Err(err) => Err(err),
+        let fee = self
+            .get_fee_for_message(tx.message())
+            .ok_or(TransactionError::InsufficientFundsForFee)?;
+        for (pubkey, account) in &simulation_result.post_accounts {
+            self.set_account(pubkey.clone(), account.clone().into());
         }
+
+        let tx_hash = self.save_tx(tx, &simulation_result, fee)?;
+        Ok((tx_hash, simulation_result))
     }
 
     pub fn get_tx(&self, signature: &Signature) -> Option<&TransactionData> {
         self.txs.get(signature)
     }
 
+    /// Returns the transactions that include the given address, newest first.
+    ///
+    /// Transactions in the same slot are ordered by their processing order in the block.
+    /// Addresses that are loaded from address lookup tables are also included.
+    pub fn get_txs_for_address(&self, address: &Pubkey) -> Vec<(&Signature, &TransactionData)> {
+        let mut txs = self
+            .txs
+            .iter()
+            .filter(|(_, tx_data)| {
+                tx_data
+                    .get_tx()
+                    .message
+                    .static_account_keys()
+                    .contains(address)
+                    || tx_data
+                        .get_meta()
+                        .as_ref()
+                        .and_then(|meta| meta.loaded_addresses.as_ref())
+                        .map(|addresses| {
+                            addresses.writable.contains(address)
+                                || addresses.readonly.contains(address)
+                        })
+                        .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        // Transactions whose block is not saved fall back to the signature order
+        let block_index = |signature: &Signature, slot: Slot| {
+            let signatures = if slot == self.slot {
+                &self.current_block_signatures[..]
+            } else {
+                self.blocks
+                    .get(&slot)
+                    .map(|block| block.get_signatures())
+                    .unwrap_or_default()
+            };
+            signatures.iter().position(|sig| sig == signature)
+        };
+        txs.sort_by_cached_key(|(signature, tx_data)| {
+            let slot = tx_data.get_slot();
+            Reverse((slot, block_index(signature, slot), **signature))
+        });
+
+        txs
+    }
+
+    pub fn get_block(&self, slot: Slot) -> Option<&BlockData> {
+        self.blocks.get(&slot)
+    }
+
+    /// Returns the sealed blocks in the given slot range (inclusive).
+    pub fn get_blocks(&self, start_slot: Slot, end_slot: Slot) -> Vec<&BlockData> {
+        if start_slot > end_slot {
+            return vec![];
+        }
+
+        self.blocks
+            .range(start_slot..=end_slot)
+            .map(|(_, block)| block)
+            .collect()
+    }
+
+    pub fn get_block_interval_ms(&self) -> Option<u64> {
+        self.block_interval_ms
+    }
+
+    /// Sets the block interval, `None` (or `0`) means blocks are only sealed on demand.
+    pub fn set_block_interval_ms(&mut self, block_interval_ms: Option<u64>) {
+        self.block_interval_ms = block_interval_ms.filter(|ms| *ms != 0);
+    }
+
+    /// Seals the current block if the block interval has passed since the block started.
+    ///
+    /// If multiple intervals have passed (e.g. ticks were not called in a while), the slots of the
+    /// intervals after the first one are skipped. Returns whether the block was sealed.
+    pub fn tick(&mut self) -> bool {
+        let Some(block_interval_ms) = self.block_interval_ms else {
+            return false;
+        };
+        let elapsed_ms = get_current_timestamp_ms().saturating_sub(self.current_block_start_ms);
+        let intervals = elapsed_ms.checked_div(block_interval_ms).unwrap_or(0);
+        if intervals == 0 {
+            return false;
+        }
+
+        // Keep the block boundaries aligned to the interval instead of the time of the tick
+        let current_block_start_ms = self.current_block_start_ms;
+        self.seal_block_with_next_slot(self.slot + intervals);
+        self.current_block_start_ms =
+            current_block_start_ms.saturating_add(intervals.saturating_mul(block_interval_ms));
+
+        true
+    }
+
+    /// Seals the current block and starts a new block at the next slot.
+    ///
+    /// Returns the slot of the sealed block.
+    pub fn seal_block(&mut self) -> Slot {
+        self.seal_block_with_next_slot(self.slot + 1)
+    }
+
+    /// Seals the current block and skips the slots until the given slot.
+    ///
+    /// The clock advances by the skipped slots if the clock is simulated.
+    pub fn warp_to_slot(&mut self, slot: Slot) -> Result<(), String> {
+        if slot <= self.slot {
+            return Err(format!(
+                "Warp slot must be greater than the current slot ({})",
+                self.slot
+            ));
+        }
+
+        self.seal_block_with_next_slot(slot);
+        Ok(())
+    }
+
+    pub fn get_epoch_schedule(&self) -> &EpochSchedule {
+        &self.epoch_schedule
+    }
+
+    /// Sets the epoch schedule, all epochs have the same amount of slots if `warmup` is `false`.
+    pub fn set_epoch_schedule(&mut self, slots_per_epoch: u64, warmup: bool) -> Result<(), String> {
+        if slots_per_epoch < MINIMUM_SLOTS_PER_EPOCH {
+            return Err(format!(
+                "Slots per epoch must be at least {MINIMUM_SLOTS_PER_EPOCH}"
+            ));
+        }
+
+        // The epoch of the clock never goes backwards
+        let epoch_schedule = EpochSchedule::custom(slots_per_epoch, slots_per_epoch, warmup);
+        let current_epoch = self.get_clock().epoch;
+        let epoch = epoch_schedule.get_epoch(self.slot);
+        if epoch < current_epoch {
+            return Err(format!(
+                "Epoch schedule would move the epoch backwards (from {current_epoch} to {epoch})"
+            ));
+        }
+
+        self.epoch_schedule = epoch_schedule;
+        self.set_sysvar_account(&epoch_schedule);
+        self.sysvar_cache
+            .write()
+            .unwrap()
+            .set_epoch_schedule(epoch_schedule);
+        self.update_clock();
+
+        Ok(())
+    }
+
+    pub fn get_clock(&self) -> Clock {
+        (*self.sysvar_cache.read().unwrap().get_clock().unwrap()).clone()
+    }
+
+    /// Returns the unix timestamp of the clock in seconds.
+    pub fn get_clock_unix_timestamp(&self) -> UnixTimestamp {
+        (self.clock_timestamp_ms / 1000) as UnixTimestamp
+    }
+
+    pub fn get_clock_ms_per_slot(&self) -> Option<u64> {
+        self.clock_ms_per_slot
+    }
+
+    /// Sets how much the clock advances per slot, the clock follows the real time if `None`.
+    pub fn set_clock_ms_per_slot(&mut self, clock_ms_per_slot: Option<u64>) {
+        // Continue from the current clock timestamp when switching to the real time
+        if clock_ms_per_slot.is_none() {
+            self.clock_offset_ms =
+                self.clock_timestamp_ms as i64 - get_current_timestamp_ms() as i64;
+        }
+        self.clock_ms_per_slot = clock_ms_per_slot;
+    }
+
+    /// Moves the clock forward to the given unix timestamp in seconds.
+    ///
+    /// The clock continues from the given timestamp with the next blocks.
+    pub fn warp_to_timestamp(&mut self, unix_timestamp: UnixTimestamp) -> Result<(), String> {
+        let current_unix_timestamp = self.get_clock_unix_timestamp();
+        if unix_timestamp < current_unix_timestamp {
+            return Err(format!(
+                "Warp timestamp must not be less than the current timestamp ({current_unix_timestamp})"
+            ));
+        }
+
+        self.set_clock_unix_timestamp(unix_timestamp);
+        Ok(())
+    }
+
+    /// Sets the unix timestamp of the clock in seconds, even if it's in the past.
+    ///
+    /// The clock continues from the given timestamp with the next blocks.
+    pub fn set_clock_unix_timestamp(&mut self, unix_timestamp: UnixTimestamp) {
+        self.clock_timestamp_ms = (unix_timestamp.max(0) as u64).saturating_mul(1000);
+        self.clock_offset_ms = self.clock_timestamp_ms as i64 - get_current_timestamp_ms() as i64;
+        self.update_clock();
+    }
+
+    pub fn get_transaction_count(&self) -> u64 {
+        self.txs.len() as u64
+    }
+
+    /// Seals the current block and starts a new block at the given slot.
+    ///
+    /// Returns the slot of the sealed block.
+    fn seal_block_with_next_slot(&mut self, next_slot: Slot) -> Slot {
+        let now_ms = get_current_timestamp_ms();
+        let block_time = self.get_clock_unix_timestamp();
+
+        let signatures = mem::take(&mut self.current_block_signatures);
+        for signature in &signatures {
+            if let Some(tx_data) = self.txs.get_mut(signature) {
+                tx_data.set_block_time(block_time);
+            }
+        }
+
+        // The parent is the last sealed block because of the skipped slots
+        let slot = self.slot;
+        let parent_slot = self
+            .blocks
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(slot.saturating_sub(1));
+        let previous_blockhash = self
+            .blocks
+            .get(&parent_slot)
+            .map(|block| block.get_blockhash())
+            .unwrap_or(self.genesis_hash);
+        self.blocks.insert(
+            slot,
+            BlockData::new(
+                slot,
+                parent_slot,
+                self.block_height,
+                self.latest_blockhash,
+                previous_blockhash,
+                block_time,
+                signatures,
+            ),
+        );
+
+        self.new_slot(next_slot);
+        self.current_block_start_ms = now_ms;
+
+        slot
+    }
+
     pub fn airdrop(&mut self, to_pubkey: &Pubkey, lamports: u64) -> transaction::Result<Signature> {
-        let payer = &self.airdrop_kp;
-        let tx = VersionedTransaction::try_new(
-            VersionedMessage::Legacy(Message::new_with_blockhash(
-                &[system_instruction::transfer(
-                    &payer.pubkey(),
-                    to_pubkey,
-                    lamports,
-                )],
-                Some(&payer.pubkey()),
-                &self.latest_blockhash,
-            )),
-            &[payer],
-        )
-        .unwrap();
+        let create_tx = |bank: &Self| {
+            let payer = &bank.airdrop_kp;
+            VersionedTransaction::try_new(
+                VersionedMessage::Legacy(Message::new_with_blockhash(
+                    &[system_instruction::transfer(
+                        &payer.pubkey(),
+                        to_pubkey,
+                        lamports,
+                    )],
+                    Some(&payer.pubkey()),
+                    &bank.latest_blockhash,
+                )),
+                &[payer],
+            )
+            .unwrap()
+        };
 
-        get_sanitized_tx_from_versioned_tx(tx)
+        // Identical airdrops have the same signature within the same block
+        self.tick();
+        let mut tx = create_tx(self);
+        if self.txs.contains_key(&tx.signatures[0]) {
+            self.seal_block();
+            tx = create_tx(self);
+        }
+
+        get_sanitized_tx_from_versioned_tx(tx, PgAddressLoader::new(self))
             .and_then(|sanitized_tx| self.process_tx(sanitized_tx))
     }
 
-    fn new_slot(&mut self) {
+    fn new_slot(&mut self, next_slot: Slot) {
+        let mut slot_hashes = self
+            .sysvar_cache
+            .read()
+            .unwrap()
+            .get_slot_hashes()
+            .map(|slot_hashes| SlotHashes::new(&slot_hashes))
+            .unwrap_or_default();
+
+        // Save the hash of the finished slot
+        slot_hashes.add(self.slot, self.latest_blockhash);
+
+        let skipped_slots = next_slot - self.slot;
         self.latest_blockhash = create_blockhash(&self.latest_blockhash.to_bytes());
-        self.slot += 1;
+        self.slot = next_slot;
         self.block_height += 1;
+
+        // Advance the clock, it never goes backwards
+        self.clock_timestamp_ms = match self.clock_ms_per_slot {
+            Some(ms_per_slot) => self
+                .clock_timestamp_ms
+                .saturating_add(ms_per_slot.saturating_mul(skipped_slots)),
+            None => (get_current_timestamp_ms() as i64)
+                .saturating_add(self.clock_offset_ms)
+                .max(self.clock_timestamp_ms as i64) as u64,
+        };
+
+        self.set_sysvar_account(&slot_hashes);
+        self.sysvar_cache
+            .write()
+            .unwrap()
+            .set_slot_hashes(slot_hashes);
+        self.update_clock();
+    }
+
+    /// Update the clock sysvar based on the bank's slot, epoch schedule and clock timestamp.
+    ///
+    /// Finished epochs are added to the stake history.
+    fn update_clock(&mut self) {
+        let mut clock = self.get_clock();
+        let unix_timestamp = self.get_clock_unix_timestamp();
+        let epoch = self.epoch_schedule.get_epoch(self.slot);
+        if epoch != clock.epoch {
+            clock.epoch_start_timestamp = unix_timestamp;
+
+            // There are no stakes to (de)activate, the entries are only there for the programs
+            // that expect an entry per epoch. Skipped epochs also get an entry, up to the
+            // maximum amount of entries.
+            if epoch > clock.epoch {
+                let mut stake_history = self
+                    .sysvar_cache
+                    .read()
+                    .unwrap()
+                    .get_stake_history()
+                    .map(|stake_history| (*stake_history).clone())
+                    .unwrap_or_default();
+                let first_epoch = clock
+                    .epoch
+                    .max(epoch.saturating_sub(MAX_STAKE_HISTORY_ENTRIES as u64));
+                for finished_epoch in first_epoch..epoch {
+                    stake_history.add(finished_epoch, StakeHistoryEntry::default());
+                }
+
+                self.set_sysvar_account(&stake_history);
+                self.sysvar_cache
+                    .write()
+                    .unwrap()
+                    .set_stake_history(stake_history);
+            }
+        }
+
+        clock.slot = self.slot;
+        clock.epoch = epoch;
+        clock.leader_schedule_epoch = self.epoch_schedule.get_leader_schedule_epoch(self.slot);
+        clock.unix_timestamp = unix_timestamp;
+
+        self.set_sysvar_account(&clock);
+        self.sysvar_cache.write().unwrap().set_clock(clock);
+    }
+
+    /// Update the account data of the given sysvar.
+    ///
+    /// NOTE: The sysvar cache should also be updated separately.
+    fn set_sysvar_account<S: Sysvar>(&mut self, sysvar: &S) {
+        if let Some(account) = self.accounts.get_mut(&S::id()) {
+            to_account(sysvar, account).unwrap();
+        }
     }
 
     fn save_tx(
         &mut self,
         tx: SanitizedTransaction,
-        result: SimulateTransactionResult,
+        result: &SimulateTransactionResult,
         fee: u64,
     ) -> transaction::Result<Signature> {
         let signature = tx.signature();
@@ -373,26 +914,25 @@ impl PgBank {
                                 .iter()
                                 .map(|(_, data)| data.lamports())
                                 .collect(),
-                            log_messages: Some(result.logs),
+                            log_messages: Some(result.logs.clone()),
                             // TODO:
                             pre_token_balances: None,
                             // TODO:
                             post_token_balances: None,
-                            err: result.result.err(),
-                            loaded_addresses: None,
+                            err: result.result.clone().err(),
+                            loaded_addresses: match tx.message() {
+                                SanitizedMessage::V0(msg) => {
+                                    Some(msg.loaded_addresses.clone().into_owned())
+                                }
+                                _ => None,
+                            },
                             compute_units_consumed: Some(result.units_consumed),
                         }),
-                        Some(
-                            self.sysvar_cache
-                                .read()
-                                .unwrap()
-                                .get_clock()
-                                .unwrap()
-                                .unix_timestamp,
-                        ),
+                        // Set after the block is sealed
+                        None,
                     ),
                 );
-                self.new_slot();
+                self.current_block_signatures.push(signature);
 
                 Ok(signature)
             }
@@ -400,10 +940,18 @@ impl PgBank {
     }
 
     fn load_tx(&self, tx: &SanitizedTransaction) -> transaction::Result<LoadedTransaction> {
-        let fee = 0;
+        let fee = self
+            .get_fee_for_message(tx.message())
+            .ok_or(TransactionError::InsufficientFundsForFee)?;
+
+        // Compute unit limit, heap size and prioritization fee are requested by the compute
+        // budget instructions of the transaction
+        let mut compute_budget = ComputeBudget::default();
+        Self::process_compute_budget_instructions(tx.message(), &mut compute_budget)?;
+
         let mut error_counters = TransactionErrorMetrics::default();
         let feature_set = FeatureSet::default();
-        self.load_tx_accounts(&tx, fee, &mut error_counters, &feature_set)
+        self.load_tx_accounts(&tx, fee, compute_budget, &mut error_counters, &feature_set)
     }
 
     fn execute_loaded_tx(
@@ -411,7 +959,7 @@ impl PgBank {
         tx: &SanitizedTransaction,
         loaded_tx: &mut LoadedTransaction,
     ) -> TransactionExecutionResult {
-        let compute_budget = ComputeBudget::default();
+        let compute_budget = loaded_tx.compute_budget;
         let mut transaction_context = TransactionContext::new(
             loaded_tx.accounts.clone(),
             None,
@@ -443,7 +991,7 @@ impl PgBank {
             &mut timings,
             &sysvar_cache,
             *blockhash,
-            PgBank::LAMPORTS_PER_SIGNATURE,
+            self.lamports_per_signature,
             current_accounts_data_len,
             &mut accumulated_consume_units,
         );
@@ -476,7 +1024,19 @@ impl PgBank {
                 },
                 tx_executor_cache,
             },
-            Err(err) => TransactionExecutionResult::NotExecuted(err),
+            // Failed transactions are still executed in order to charge the fee
+            Err(err) => TransactionExecutionResult::Executed {
+                details: TransactionExecutionDetails {
+                    status: Err(err),
+                    log_messages: Some(log_collector.borrow().get_recorded_content().to_vec()),
+                    inner_instructions: None,
+                    durable_nonce_fee: None,
+                    return_data: None,
+                    executed_units: accumulated_consume_units,
+                    accounts_data_len_delta: 0,
+                },
+                tx_executor_cache,
+            },
         }
     }
 
@@ -484,6 +1044,7 @@ impl PgBank {
         &self,
         tx: &SanitizedTransaction,
         fee: u64,
+        compute_budget: ComputeBudget,
         error_counters: &mut TransactionErrorMetrics,
         feature_set: &FeatureSet,
     ) -> transaction::Result<LoadedTransaction> {
@@ -501,6 +1062,7 @@ impl PgBank {
         let requested_loaded_accounts_data_size_limit = None;
 
         let mut accumulated_accounts_data_size: usize = 0;
+        let rent = *self.sysvar_cache.read().unwrap().get_rent().unwrap();
 
         let mut accounts = account_keys
             .iter()
@@ -533,6 +1095,7 @@ impl PgBank {
                                 &mut account,
                                 i as IndexOfAccount,
                                 error_counters,
+                                &rent,
                                 feature_set,
                                 fee,
                             )?;
@@ -618,6 +1181,8 @@ impl PgBank {
             Ok(LoadedTransaction {
                 accounts,
                 program_indices,
+                fee,
+                compute_budget,
             })
         } else {
             error_counters.account_not_found += 1;
@@ -728,6 +1293,30 @@ impl PgBank {
         Ok(account_indices)
     }
 
+    /// Subtract the fee from the fee payer, which is always the first account.
+    ///
+    /// The fee payer is validated to have enough lamports during the transaction loading.
+    fn charge_fee(accounts: &mut [TransactionAccount], fee: u64) {
+        if let Some((_, fee_payer)) = accounts.first_mut() {
+            fee_payer.set_lamports(fee_payer.lamports().saturating_sub(fee));
+        }
+    }
+
+    /// Process the compute budget instructions of the message with the same rules as the
+    /// real clusters.
+    fn process_compute_budget_instructions(
+        msg: &SanitizedMessage,
+        compute_budget: &mut ComputeBudget,
+    ) -> transaction::Result<PrioritizationFeeDetails> {
+        compute_budget.process_instructions(
+            msg.program_instructions_iter(),
+            true,  // default_units_per_instruction
+            false, // support_request_units_deprecated
+            true,  // enable_request_heap_frame_ix
+            true,  // support_set_loaded_accounts_data_size_limit_ix
+        )
+    }
+
     fn construct_instructions_account(
         message: &SanitizedMessage,
         is_owned_by_sysvar: bool,
@@ -750,6 +1339,7 @@ impl PgBank {
         payer_account: &mut AccountSharedData,
         _payer_index: IndexOfAccount,
         error_counters: &mut TransactionErrorMetrics,
+        rent: &Rent,
         _feature_set: &FeatureSet,
         fee: u64,
     ) -> transaction::Result<()> {
@@ -762,7 +1352,8 @@ impl PgBank {
             TransactionError::InvalidAccountForFee
         })? {
             SystemAccountKind::System => 0,
-            SystemAccountKind::Nonce => todo!(),
+            // Nonce accounts must stay rent-exempt after paying the fee
+            SystemAccountKind::Nonce => rent.minimum_balance(nonce::State::size()),
         };
 
         if payer_account.lamports() < fee + min_balance {
@@ -801,16 +1392,60 @@ pub type BankAccounts = HashMap<Pubkey, Account>;
 /// Mapping between Signatures and TransactionData
 pub type BankTxs = HashMap<Signature, TransactionData>;
 
-/// Filler struct, address loader is not yet implemented
-#[derive(Clone, Default)]
-pub struct PgAddressLoader {}
+/// Mapping between slots and sealed blocks
+pub type BankBlocks = BTreeMap<Slot, BlockData>;
 
-impl AddressLoader for PgAddressLoader {
+/// Resolves the address lookup tables of v0 transactions from the bank accounts
+#[derive(Clone, Copy)]
+pub struct PgAddressLoader<'a> {
+    bank: &'a PgBank,
+}
+
+impl<'a> PgAddressLoader<'a> {
+    pub fn new(bank: &'a PgBank) -> Self {
+        Self { bank }
+    }
+
+    fn load_lookup_table_addresses(
+        &self,
+        lookup: &MessageAddressTableLookup,
+        slot_hashes: &SlotHashes,
+    ) -> Result<LoadedAddresses, AddressLookupError> {
+        let table_account = self
+            .bank
+            .get_account(&lookup.account_key)
+            .ok_or(AddressLookupError::LookupTableAccountNotFound)?;
+        if table_account.owner != solana_address_lookup_table_program::id() {
+            return Err(AddressLookupError::InvalidAccountOwner);
+        }
+
+        let current_slot = self.bank.get_slot();
+        let table = AddressLookupTable::deserialize(&table_account.data)
+            .map_err(|_| AddressLookupError::InvalidAccountData)?;
+        Ok(LoadedAddresses {
+            writable: table.lookup(current_slot, &lookup.writable_indexes, slot_hashes)?,
+            readonly: table.lookup(current_slot, &lookup.readonly_indexes, slot_hashes)?,
+        })
+    }
+}
+
+impl AddressLoader for PgAddressLoader<'_> {
     fn load_addresses(
         self,
-        _lookups: &[MessageAddressTableLookup],
+        lookups: &[MessageAddressTableLookup],
     ) -> Result<LoadedAddresses, AddressLoaderError> {
-        Err(AddressLoaderError::Disabled)
+        let slot_hashes = self
+            .bank
+            .sysvar_cache
+            .read()
+            .unwrap()
+            .get_slot_hashes()
+            .map_err(|_| AddressLoaderError::SlotHashesSysvarNotFound)?;
+
+        Ok(lookups
+            .iter()
+            .map(|lookup| self.load_lookup_table_addresses(lookup, &slot_hashes))
+            .collect::<Result<_, AddressLookupError>>()?)
     }
 }
 
@@ -820,6 +1455,8 @@ type TransactionProgramIndices = Vec<Vec<IndexOfAccount>>;
 struct LoadedTransaction {
     pub accounts: Vec<TransactionAccount>,
     pub program_indices: TransactionProgramIndices,
+    pub fee: u64,
+    pub compute_budget: ComputeBudget,
 }
 
 /// Type safe representation of a transaction execution attempt which
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{clock::UnixTimestamp, hash::Hash, signature::Signature, slot_history::Slot};
+
+/// A sealed block of transactions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockData {
+    /// The slot of the block
+    slot: Slot,
+    /// The slot of the parent block
+    parent_slot: Slot,
+    /// The number of blocks beneath this block
+    block_height: u64,
+    /// The blockhash of the block
+    blockhash: Hash,
+    /// The blockhash of the parent block
+    previous_blockhash: Hash,
+    /// The unix timestamp of when the block was sealed
+    block_time: UnixTimestamp,
+    /// The signatures of the transactions in the block, in processing order
+    signatures: Vec<Signature>,
+}
+
+impl BlockData {
+    pub fn new(
+        slot: Slot,
+        parent_slot: Slot,
+        block_height: u64,
+        blockhash: Hash,
+        previous_blockhash: Hash,
+        block_time: UnixTimestamp,
+        signatures: Vec<Signature>,
+    ) -> Self {
+        Self {
+            slot,
+            parent_slot,
+            block_height,
+            blockhash,
+            previous_blockhash,
+            block_time,
+            signatures,
+        }
+    }
+
+    pub fn get_slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn get_parent_slot(&self) -> Slot {
+        self.parent_slot
+    }
+
+    pub fn get_block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    pub fn get_blockhash(&self) -> Hash {
+        self.blockhash
+    }
+
+    pub fn get_previous_blockhash(&self) -> Hash {
+        self.previous_blockhash
+    }
+
+    pub fn get_block_time(&self) -> UnixTimestamp {
+        self.block_time
+    }
+
+    pub fn get_signatures(&self) -> &[Signature] {
+        &self.signatures
+    }
+}
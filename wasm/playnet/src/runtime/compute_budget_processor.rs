@@ -0,0 +1,11 @@
+use solana_program_runtime::invoke_context::InvokeContext;
+use solana_sdk::{instruction::InstructionError, transaction_context::IndexOfAccount};
+
+/// Compute budget instructions are processed by the bank before the execution, the program itself
+/// doesn't do anything.
+pub fn process_compute_budget_instruction(
+    _first_instruction_account: IndexOfAccount,
+    _invoke_context: &mut InvokeContext,
+) -> Result<(), InstructionError> {
+    Ok(())
+}
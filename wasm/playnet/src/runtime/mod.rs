@@ -1,5 +1,8 @@
 pub mod bank;
+pub mod block_history;
+pub mod compute_budget_processor;
 pub mod message_processor;
 pub mod nonce_keyed_account;
 pub mod system_instruction_processor;
+pub mod sysvars;
 pub mod transaction_history;
@@ -0,0 +1,75 @@
+// Sysvars that don't exist in the Solana version Playnet is built with.
+//
+// The ids and the layouts are the same as the later Solana versions so that programs built with
+// those versions are able to read them from their accounts. Getting them via syscalls is not
+// supported because the syscalls don't exist in this version.
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    clock::Slot,
+    hash::Hash,
+    pubkey,
+    pubkey::Pubkey,
+    sysvar::{Sysvar, SysvarId},
+};
+
+/// `EpochRewards` sysvar id
+pub const EPOCH_REWARDS_ID: Pubkey = pubkey!("SysvarEpochRewards1111111111111111111111111");
+
+/// `LastRestartSlot` sysvar id
+pub const LAST_RESTART_SLOT_ID: Pubkey = pubkey!("SysvarLastRestartS1ot1111111111111111111111");
+
+/// Status of the epoch rewards distribution.
+///
+/// Playnet doesn't have staking rewards, so the distribution is never active.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochRewards {
+    /// Block height of the first block of the distribution
+    pub distribution_starting_block_height: u64,
+    /// Amount of partitions the rewards are distributed in
+    pub num_partitions: u64,
+    /// Blockhash of the parent block of the first block of the distribution
+    pub parent_blockhash: Hash,
+    /// Total points of the epoch the rewards are calculated for
+    pub total_points: u128,
+    /// Total rewards of the epoch
+    pub total_rewards: u64,
+    /// Rewards that are distributed so far
+    pub distributed_rewards: u64,
+    /// Whether the rewards are being distributed
+    pub active: bool,
+}
+
+impl SysvarId for EpochRewards {
+    fn id() -> Pubkey {
+        EPOCH_REWARDS_ID
+    }
+
+    fn check_id(pubkey: &Pubkey) -> bool {
+        *pubkey == EPOCH_REWARDS_ID
+    }
+}
+
+impl Sysvar for EpochRewards {}
+
+/// The last slot the cluster was restarted at.
+///
+/// Playnet is never restarted, so the slot is always 0.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRestartSlot {
+    pub last_restart_slot: Slot,
+}
+
+impl SysvarId for LastRestartSlot {
+    fn id() -> Pubkey {
+        LAST_RESTART_SLOT_ID
+    }
+
+    fn check_id(pubkey: &Pubkey) -> bool {
+        *pubkey == LAST_RESTART_SLOT_ID
+    }
+}
+
+impl Sysvar for LastRestartSlot {}
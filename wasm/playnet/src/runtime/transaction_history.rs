@@ -17,7 +17,7 @@ pub struct TransactionData {
     tx: VersionedTransaction,
     /// Metadata produced from the transaction
     meta: Option<ConfirmedTransactionMeta>,
-    /// The unix timestamp of when the transaction was processed
+    /// The unix timestamp of the block of the transaction, `None` until the block is sealed
     block_time: Option<UnixTimestamp>,
 }
 
@@ -51,6 +51,10 @@ impl TransactionData {
     pub fn get_block_time(&self) -> Option<UnixTimestamp> {
         self.block_time
     }
+
+    pub fn set_block_time(&mut self, block_time: UnixTimestamp) {
+        self.block_time = Some(block_time);
+    }
 }
 
 /// Metadata for a confirmed transaction on the ledger
@@ -124,10 +128,12 @@ impl ConfirmedTransactionMeta {
         self.err.as_ref().map(|err| err.to_string())
     }
 
-    /// TODO:
+    /// Returns `None` for legacy transactions
     #[wasm_bindgen(js_name = loadedAddresses)]
-    pub fn loaded_addresses(&self) -> Option<u8> {
-        None
+    pub fn loaded_addresses(&self) -> Option<WasmLoadedAddresses> {
+        self.loaded_addresses
+            .as_ref()
+            .map(|loaded_addresses| WasmLoadedAddresses::from(loaded_addresses.to_owned()))
     }
 
     #[wasm_bindgen(js_name = computeUnitsConsumed)]
@@ -136,6 +142,39 @@ impl ConfirmedTransactionMeta {
     }
 }
 
+/// Addresses loaded from address lookup tables
+#[wasm_bindgen]
+pub struct WasmLoadedAddresses {
+    writable: Vec<Pubkey>,
+    readonly: Vec<Pubkey>,
+}
+
+impl From<LoadedAddresses> for WasmLoadedAddresses {
+    fn from(loaded_addresses: LoadedAddresses) -> Self {
+        Self {
+            writable: loaded_addresses.writable,
+            readonly: loaded_addresses.readonly,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmLoadedAddresses {
+    pub fn writable(&self) -> Vec<JsValue> {
+        self.writable
+            .iter()
+            .map(|pubkey| JsValue::from_str(&pubkey.to_string()))
+            .collect()
+    }
+
+    pub fn readonly(&self) -> Vec<JsValue> {
+        self.readonly
+            .iter()
+            .map(|pubkey| JsValue::from_str(&pubkey.to_string()))
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CompiledInnerInstruction {
     pub index: u8,
@@ -291,3 +291,16 @@ pub enum WasmCommitmentLevel {
     Confirmed,
     Finalized,
 }
+
+/// Encoding of serialized transactions and messages
+#[wasm_bindgen]
+#[derive(Clone, Copy, Default)]
+pub enum WasmEncoding {
+    /// Raw wire format bytes
+    #[default]
+    Binary,
+    /// Base64 encoded wire format
+    Base64,
+    /// JSON serialized legacy `Transaction` or `Message` (only for compatibility)
+    Json,
+}
@@ -1,28 +1,90 @@
+use bincode::Options;
+use serde::de::DeserializeOwned;
 use solana_sdk::{
     hash::{Hash, Hasher},
-    transaction::{self, MessageHash, SanitizedTransaction, Transaction, VersionedTransaction},
+    message::{Message, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    transaction::{
+        self, AddressLoader, MessageHash, SanitizedTransaction, Transaction, VersionedTransaction,
+    },
 };
 
-use crate::runtime::bank::PgAddressLoader;
+use crate::types::WasmEncoding;
 
-/// Tries to convert a serialized transaction into `SanitizedTransaction`
-pub fn get_sanitized_tx_from_serialized_tx(
+/// Tries to de-serialize a transaction in the given encoding.
+///
+/// JSON encoding only supports legacy transactions.
+pub fn deserialize_tx(
     serialized_tx: &[u8],
-) -> transaction::Result<SanitizedTransaction> {
-    let tx: Transaction = serde_json::from_slice(serialized_tx).unwrap();
-    let tx = VersionedTransaction::from(tx);
-    get_sanitized_tx_from_versioned_tx(tx)
+    encoding: WasmEncoding,
+) -> Result<VersionedTransaction, String> {
+    match encoding {
+        WasmEncoding::Json => serde_json::from_slice::<Transaction>(serialized_tx)
+            .map(VersionedTransaction::from)
+            .map_err(|e| format!("Failed to deserialize transaction: {e}")),
+        _ => deserialize_wire(serialized_tx, encoding)
+            .map_err(|e| format!("Failed to deserialize transaction: {e}")),
+    }
+}
+
+/// Tries to de-serialize a message in the given encoding.
+///
+/// JSON encoding only supports legacy messages.
+pub fn deserialize_msg(
+    serialized_msg: &[u8],
+    encoding: WasmEncoding,
+) -> Result<VersionedMessage, String> {
+    match encoding {
+        WasmEncoding::Json => serde_json::from_slice::<Message>(serialized_msg)
+            .map(VersionedMessage::Legacy)
+            .map_err(|e| format!("Failed to deserialize message: {e}")),
+        _ => deserialize_wire(serialized_msg, encoding)
+            .map_err(|e| format!("Failed to deserialize message: {e}")),
+    }
+}
+
+/// De-serialize the wire format bytes with the same limits as the real RPC.
+///
+/// Similar to the real RPC, trailing bytes after the value are ignored.
+fn deserialize_wire<T: DeserializeOwned>(
+    serialized: &[u8],
+    encoding: WasmEncoding,
+) -> Result<T, String> {
+    let decoded;
+    let bytes = match encoding {
+        WasmEncoding::Base64 => {
+            decoded = base64::decode(serialized).map_err(|e| format!("invalid base64: {e}"))?;
+            &decoded[..]
+        }
+        _ => serialized,
+    };
+    if bytes.len() > PACKET_DATA_SIZE {
+        return Err(format!(
+            "encoded data too large: {} bytes (max: {PACKET_DATA_SIZE} bytes)",
+            bytes.len()
+        ));
+    }
+
+    bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize_from(bytes)
+        .map_err(|e| e.to_string())
 }
 
-/// Tries to convert a versioned transaction into `SanitizedTransaction`
+/// Tries to convert a versioned transaction into `SanitizedTransaction`.
+///
+/// Address lookup tables of v0 transactions are resolved with the given `address_loader`.
 pub fn get_sanitized_tx_from_versioned_tx(
     versioned_tx: VersionedTransaction,
+    address_loader: impl AddressLoader,
 ) -> transaction::Result<SanitizedTransaction> {
     SanitizedTransaction::try_create(
         versioned_tx,
         MessageHash::Compute,
         Some(false), // is_simple_vote_tx
-        PgAddressLoader::default(),
+        address_loader,
         true, // require_static_program_ids
     )
 }
@@ -33,3 +95,26 @@ pub fn create_blockhash(bytes: &[u8]) -> Hash {
     hasher.hash(bytes);
     hasher.result()
 }
+
+/// Get the current unix timestamp in milliseconds.
+#[cfg(target_arch = "wasm32")]
+pub fn get_current_timestamp_ms() -> u64 {
+    date_now() as u64
+}
+
+/// Get the current unix timestamp in milliseconds.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_current_timestamp_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    /// `std::time` is not supported in browsers
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
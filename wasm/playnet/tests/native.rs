@@ -0,0 +1,223 @@
+// Tests of the native Playnet host.
+//
+// The tests run the `playnet` binary and talk to its servers the same way the clients do.
+
+#![cfg(feature = "native")]
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+/// Maximum amount of time to wait for the server
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tests whether the HTTP server works as expected
+#[test]
+fn http() {
+    let server = Server::start();
+
+    assert_eq!(
+        server.http("GET", "/health", ""),
+        Some((200, "ok".to_owned()))
+    );
+    assert_eq!(
+        server.http("PUT", "/", "").map(|(status, _)| status),
+        Some(405)
+    );
+
+    let pubkey = Pubkey::new_unique().to_string();
+    let response = server.rpc("requestAirdrop", json!([pubkey, 1_000_000]));
+    assert!(response["result"].is_string());
+    let response = server.rpc("getBalance", json!([pubkey]));
+    assert_eq!(response["result"]["value"], 1_000_000);
+
+    // Errors are JSON-RPC responses
+    let (status, body) = server.http("POST", "/", "{").unwrap();
+    assert_eq!(status, 200);
+    let response = serde_json::from_str::<Value>(&body).unwrap();
+    assert_eq!(response["error"]["code"], -32700);
+}
+
+/// Tests whether the websocket subscriptions work as expected
+#[test]
+fn ws() {
+    let server = Server::start();
+
+    let (mut ws, _) = tungstenite::connect(format!("ws://127.0.0.1:{}", server.port + 1)).unwrap();
+    if let MaybeTlsStream::Plain(stream) = ws.get_ref() {
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    }
+
+    let pubkey = Pubkey::new_unique().to_string();
+    let account_subscription_id =
+        ws_request(&mut ws, 1, "accountSubscribe", json!([pubkey]))["result"].clone();
+    let slot_subscription_id = ws_request(&mut ws, 2, "slotSubscribe", json!([]))["result"].clone();
+    assert_ne!(account_subscription_id, slot_subscription_id);
+
+    // Notifications
+    let signature = server.rpc("requestAirdrop", json!([pubkey, 1_000_000]))["result"].clone();
+    let notification = read_until(&mut ws, |message| {
+        message["method"] == "accountNotification"
+    });
+    assert_eq!(
+        notification["params"]["subscription"],
+        account_subscription_id
+    );
+    assert_eq!(
+        notification["params"]["result"]["value"]["lamports"],
+        1_000_000
+    );
+
+    // Subscribe after the account notification in order to not skip it while waiting for the
+    // signature notification
+    let signature_subscription_id =
+        ws_request(&mut ws, 3, "signatureSubscribe", json!([signature]))["result"].clone();
+    let notification = read_until(&mut ws, |message| {
+        message["method"] == "signatureNotification"
+    });
+    assert_eq!(
+        notification["params"]["subscription"],
+        signature_subscription_id
+    );
+    assert!(notification["params"]["result"]["value"]["err"].is_null());
+
+    let notification = read_until(&mut ws, |message| message["method"] == "slotNotification");
+    assert_eq!(notification["params"]["subscription"], slot_subscription_id);
+    let result = &notification["params"]["result"];
+    let (slot, parent) = (
+        result["slot"].as_u64().unwrap(),
+        result["parent"].as_u64().unwrap(),
+    );
+    assert!(parent < slot);
+
+    // The parent is a sealed block
+    let response = server.rpc("getBlock", json!([parent]));
+    assert!(response["result"]["blockHeight"].is_u64());
+
+    // Unsubscribe
+    let params = json!([slot_subscription_id]);
+    assert_eq!(
+        ws_request(&mut ws, 4, "slotUnsubscribe", params.clone())["result"],
+        true
+    );
+    assert_eq!(
+        ws_request(&mut ws, 5, "slotUnsubscribe", params)["result"],
+        false
+    );
+    assert_eq!(
+        ws_request(&mut ws, 6, "unknownMethod", json!([]))["error"]["code"],
+        -32601
+    );
+}
+
+/// Running `playnet` binary that is killed on drop
+struct Server {
+    /// Child process of the binary
+    child: Child,
+    /// Port of the JSON-RPC HTTP server, websocket server uses `port + 1`
+    port: u16,
+}
+
+impl Server {
+    /// Start the binary with the websocket server and wait until it's ready.
+    fn start() -> Self {
+        let port = get_free_port();
+        let child = Command::new(env!("CARGO_BIN_EXE_playnet"))
+            .args(["--rpc-port", &port.to_string(), "--ws"])
+            .args(["--block-interval-ms", "100"])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let server = Self { child, port };
+
+        let start = Instant::now();
+        while server.http("GET", "/health", "").is_none() {
+            assert!(start.elapsed() < TIMEOUT, "Server didn't start");
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        server
+    }
+
+    /// Send an HTTP request and return the status code and the body of the response.
+    ///
+    /// `None` is returned if the server couldn't be reached.
+    fn http(&self, method: &str, path: &str, body: &str) -> Option<(u16, String)> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).ok()?;
+        stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+
+        // HTTP/1.0 closes the connection after the response and doesn't use chunked encoding
+        write!(
+            stream,
+            "{method} {path} HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+
+        let (head, body) = response.split_once("\r\n\r\n")?;
+        let status = head.split(' ').nth(1)?.parse().ok()?;
+        Some((status, body.to_owned()))
+    }
+
+    /// Send a JSON-RPC request and return the response.
+    fn rpc(&self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 0, "method": method, "params": params });
+        let (status, body) = self.http("POST", "/", &request.to_string()).unwrap();
+        assert_eq!(status, 200);
+        serde_json::from_str(&body).unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Get a free port whose next port is also free (for the websocket server).
+fn get_free_port() -> u16 {
+    loop {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        if port < u16::MAX && TcpListener::bind(("127.0.0.1", port + 1)).is_ok() {
+            return port;
+        }
+    }
+}
+
+/// Send a websocket request and return its response.
+fn ws_request(
+    ws: &mut WebSocket<MaybeTlsStream<TcpStream>>,
+    id: u64,
+    method: &str,
+    params: Value,
+) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    ws.send(Message::text(request.to_string())).unwrap();
+    read_until(ws, |message| message["id"] == id)
+}
+
+/// Read the websocket messages until a message matches the predicate and return the message.
+fn read_until(ws: &mut WebSocket<MaybeTlsStream<TcpStream>>, f: impl Fn(&Value) -> bool) -> Value {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "Message not received");
+        if let Message::Text(text) = ws.read().unwrap() {
+            let message = serde_json::from_str(text.as_str()).unwrap();
+            if f(&message) {
+                return message;
+            }
+        }
+    }
+}